@@ -0,0 +1,29 @@
+-- Durable job queue
+--
+-- Workers claim jobs with FOR UPDATE SKIP LOCKED. A claimed job is 'running' until
+-- locked_until; if the worker dies, the job becomes claimable again after that.
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+    completed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Index for workers looking for claimable jobs
+CREATE INDEX idx_jobs_claimable ON jobs(run_at) WHERE status IN ('pending', 'running');
+
+-- Index for admin listing by status
+CREATE INDEX idx_jobs_status ON jobs(status, created_at DESC);
+
+CREATE TRIGGER update_jobs_updated_at
+    BEFORE UPDATE ON jobs
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,15 @@
+-- Slack events already accepted, keyed by Slack's event_id
+--
+-- Slack retries events it considers unacknowledged (X-Slack-Retry-Num); rows here
+-- let us acknowledge the retry without processing the event twice. Rows older
+-- than the configured TTL are purged periodically.
+CREATE TABLE processed_slack_events (
+    event_id TEXT PRIMARY KEY,
+    team_id TEXT NOT NULL,
+    retry_count INTEGER NOT NULL DEFAULT 0,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Index for TTL cleanup
+CREATE INDEX idx_processed_slack_events_first_seen ON processed_slack_events(first_seen_at);
@@ -0,0 +1,88 @@
+-- Split save tracking into per-track state and append-only attempt history
+--
+-- track_saves holds one row per (workspace, user, thread, track) with its current
+-- status, upserted on every attempt. save_action_log becomes the append-only
+-- history of attempts and loses its unique index, so retries after a failure and
+-- repeat mentions (already_saved) can be recorded.
+
+CREATE TABLE track_saves (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    slack_workspace_id TEXT NOT NULL,
+    slack_user_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    thread_ts TEXT NOT NULL,
+    spotify_track_id TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'saved', 'failed')),
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error_code TEXT,
+    last_error_message TEXT,
+    saved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_track_saves_unique ON track_saves(
+    slack_workspace_id,
+    slack_user_id,
+    thread_ts,
+    spotify_track_id
+);
+CREATE INDEX idx_track_saves_user ON track_saves(slack_workspace_id, slack_user_id);
+
+CREATE TRIGGER update_track_saves_updated_at
+    BEFORE UPDATE ON track_saves
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Backfill state from existing log rows (at most one per key, given the old index)
+INSERT INTO track_saves (
+    slack_workspace_id,
+    slack_user_id,
+    channel_id,
+    thread_ts,
+    spotify_track_id,
+    status,
+    attempt_count,
+    last_error_code,
+    last_error_message,
+    saved_at,
+    created_at,
+    updated_at
+)
+SELECT
+    slack_workspace_id,
+    slack_user_id,
+    channel_id,
+    thread_ts,
+    spotify_track_id,
+    CASE WHEN status = 'failed' THEN 'failed' ELSE 'saved' END,
+    1,
+    error_code,
+    error_message,
+    CASE WHEN status = 'failed' THEN NULL ELSE created_at END,
+    created_at,
+    created_at
+FROM save_action_log;
+
+-- Link history rows to their state row
+ALTER TABLE save_action_log
+    ADD COLUMN track_save_id UUID REFERENCES track_saves(id) ON DELETE CASCADE;
+
+UPDATE save_action_log l
+SET track_save_id = s.id
+FROM track_saves s
+WHERE s.slack_workspace_id = l.slack_workspace_id
+    AND s.slack_user_id = l.slack_user_id
+    AND s.thread_ts = l.thread_ts
+    AND s.spotify_track_id = l.spotify_track_id;
+
+DROP INDEX idx_save_log_unique;
+
+CREATE INDEX idx_save_log_track_save ON save_action_log(track_save_id, created_at);
+CREATE INDEX idx_save_log_thread_track ON save_action_log(
+    slack_workspace_id,
+    slack_user_id,
+    thread_ts,
+    spotify_track_id,
+    created_at DESC
+);
@@ -0,0 +1,14 @@
+-- Envelope encryption of Spotify tokens
+--
+-- When set, token_data_key is the row's data key wrapped by the key
+-- encryption key token_key_id, and access_token/refresh_token hold ciphertext.
+-- Rows with a NULL token_key_id still hold plaintext tokens until the
+-- re-encryption pass (`savethebeat rotate-token-keys`) reaches them.
+ALTER TABLE user_auth
+    ADD COLUMN token_key_id TEXT,
+    ADD COLUMN token_data_key TEXT,
+    ADD CONSTRAINT user_auth_token_key_check
+        CHECK ((token_key_id IS NULL) = (token_data_key IS NULL));
+
+-- Index for finding rows not yet on the active key
+CREATE INDEX idx_user_auth_token_key ON user_auth(token_key_id);
@@ -0,0 +1,17 @@
+-- Pending Spotify OAuth states, shared by all app instances
+--
+-- A row is created by /spotify/connect and deleted when /spotify/callback
+-- consumes it, so the callback can land on any replica. Rows that are never
+-- consumed expire after ten minutes and are purged periodically.
+CREATE TABLE oauth_states (
+    token TEXT PRIMARY KEY,
+    slack_workspace_id TEXT NOT NULL,
+    slack_user_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Index for the per-user cap
+CREATE INDEX idx_oauth_states_user ON oauth_states(slack_workspace_id, slack_user_id, created_at);
+
+-- Index for expiry and cleanup
+CREATE INDEX idx_oauth_states_created_at ON oauth_states(created_at);
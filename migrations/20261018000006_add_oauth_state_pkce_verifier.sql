@@ -0,0 +1,7 @@
+-- PKCE code verifier for each pending Spotify authorization
+--
+-- States created before this migration have no verifier and could not
+-- complete the exchange, so they are dropped (users simply reconnect).
+DELETE FROM oauth_states;
+
+ALTER TABLE oauth_states ADD COLUMN pkce_verifier TEXT NOT NULL;
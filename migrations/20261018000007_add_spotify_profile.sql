@@ -0,0 +1,12 @@
+-- Spotify profile captured on connect
+--
+-- Filled from GET /v1/me in the OAuth callback (and refreshed by /spotify/verify).
+-- spotify_country and spotify_product require the user-read-private scope.
+ALTER TABLE user_auth
+    ADD COLUMN spotify_display_name TEXT,
+    ADD COLUMN spotify_country TEXT,
+    ADD COLUMN spotify_product TEXT,
+    ADD COLUMN spotify_profile_updated_at TIMESTAMPTZ;
+
+-- Index for finding Slack users linked to the same Spotify account
+CREATE INDEX idx_user_auth_spotify_user ON user_auth(spotify_user_id);
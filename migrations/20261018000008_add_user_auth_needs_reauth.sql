@@ -0,0 +1,8 @@
+-- Flag accounts whose refresh token Spotify rejected (invalid_grant)
+--
+-- Set when the user removed the app's access on Spotify; mentions are answered
+-- with a reconnect message instead of calling Spotify until the next successful
+-- connect clears it.
+ALTER TABLE user_auth
+    ADD COLUMN needs_reauth BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN needs_reauth_at TIMESTAMPTZ;
@@ -0,0 +1,10 @@
+-- Track token refresh health
+--
+-- last_refreshed_at is set on every successful refresh (lazy or by the
+-- background refresher), which probes accounts that haven't been refreshed in
+-- a while to find revoked tokens before the next mention does.
+ALTER TABLE user_auth
+    ADD COLUMN last_refreshed_at TIMESTAMPTZ;
+
+-- Index for the refresher's scan of tokens nearing expiry
+CREATE INDEX idx_user_auth_expires_at ON user_auth(expires_at) WHERE NOT needs_reauth;
@@ -0,0 +1,24 @@
+-- Slack app installations
+--
+-- One row per workspace that installed the app through /slack/install
+-- (oauth.v2.access). The bot token is encrypted like user_auth tokens when
+-- TOKEN_ENCRYPTION_KEYS is set. Reinstalling updates the row in place.
+CREATE TABLE installations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    team_id TEXT NOT NULL UNIQUE,
+    team_name TEXT,
+    app_id TEXT,
+    bot_user_id TEXT NOT NULL,
+    bot_token TEXT NOT NULL,
+    token_key_id TEXT,
+    token_data_key TEXT,
+    scopes TEXT NOT NULL,
+    installed_by_user_id TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_installations_updated_at
+    BEFORE UPDATE ON installations
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,15 @@
+-- Audit trail for workspace-level data changes
+--
+-- Written when Slack reports the app was uninstalled or its tokens revoked, and
+-- when the workspace's data is purged after the grace period (or the purge is
+-- cancelled because the app was reinstalled).
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    action TEXT NOT NULL,
+    workspace_id TEXT NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Index for listing a workspace's entries, newest first
+CREATE INDEX idx_audit_log_workspace ON audit_log(workspace_id, created_at DESC);
@@ -0,0 +1,12 @@
+-- Enterprise Grid installations
+--
+-- Workspace installs in a Grid org record the org's enterprise ID. Org-wide
+-- installs have no single workspace, so they are stored under the enterprise ID
+-- in team_id and serve every workspace in the org.
+ALTER TABLE installations
+    ADD COLUMN enterprise_id TEXT,
+    ADD COLUMN is_enterprise_install BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Index for resolving bot tokens by enterprise
+CREATE INDEX idx_installations_enterprise_id ON installations(enterprise_id)
+    WHERE enterprise_id IS NOT NULL;
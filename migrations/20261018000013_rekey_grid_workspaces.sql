@@ -0,0 +1,87 @@
+-- Move Enterprise Grid users' data to the enterprise key
+--
+-- Grid users are keyed by enterprise ID, but rows written before that were
+-- keyed by the user's workspace. rekey_grid_workspace() moves a Grid
+-- workspace's Spotify links and save history to the enterprise key, once per
+-- workspace (recorded in grid_workspaces). The app calls it whenever it sees a
+-- workspace with an enterprise ID; Grid workspaces already known from
+-- installations are moved here.
+CREATE TABLE grid_workspaces (
+    team_id TEXT PRIMARY KEY,
+    enterprise_id TEXT NOT NULL,
+    rekeyed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Returns whether the workspace's rows were moved (false if already done)
+CREATE FUNCTION rekey_grid_workspace(p_team_id TEXT, p_enterprise_id TEXT)
+RETURNS BOOLEAN AS $$
+BEGIN
+    INSERT INTO grid_workspaces (team_id, enterprise_id)
+    VALUES (p_team_id, p_enterprise_id)
+    ON CONFLICT (team_id) DO NOTHING;
+
+    IF NOT FOUND THEN
+        RETURN FALSE;
+    END IF;
+
+    -- A user who already linked Spotify under the enterprise key keeps that link
+    DELETE FROM user_auth t
+    USING user_auth e
+    WHERE t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id;
+
+    UPDATE user_auth
+    SET slack_workspace_id = p_enterprise_id
+    WHERE slack_workspace_id = p_team_id;
+
+    -- A track saved under both keys keeps the enterprise row, which takes over
+    -- the workspace row's history and saved state
+    UPDATE track_saves e
+    SET status = 'saved',
+        saved_at = COALESCE(e.saved_at, t.saved_at),
+        last_error_code = NULL,
+        last_error_message = NULL
+    FROM track_saves t
+    WHERE t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id
+        AND e.thread_ts = t.thread_ts
+        AND e.spotify_track_id = t.spotify_track_id
+        AND t.status = 'saved'
+        AND e.status <> 'saved';
+
+    UPDATE save_action_log l
+    SET track_save_id = e.id
+    FROM track_saves t, track_saves e
+    WHERE l.track_save_id = t.id
+        AND t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id
+        AND e.thread_ts = t.thread_ts
+        AND e.spotify_track_id = t.spotify_track_id;
+
+    DELETE FROM track_saves t
+    USING track_saves e
+    WHERE t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id
+        AND e.thread_ts = t.thread_ts
+        AND e.spotify_track_id = t.spotify_track_id;
+
+    UPDATE track_saves
+    SET slack_workspace_id = p_enterprise_id
+    WHERE slack_workspace_id = p_team_id;
+
+    UPDATE save_action_log
+    SET slack_workspace_id = p_enterprise_id
+    WHERE slack_workspace_id = p_team_id;
+
+    RETURN TRUE;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT rekey_grid_workspace(team_id, enterprise_id)
+FROM installations
+WHERE enterprise_id IS NOT NULL
+    AND NOT is_enterprise_install;
@@ -0,0 +1,8 @@
+-- Workspace a Spotify connect link was sent from
+--
+-- For Enterprise Grid and Slack Connect users the identity workspace isn't the
+-- workspace whose bot sent the link, so the callback needs it to message the
+-- user. Null for links that didn't carry it.
+ALTER TABLE oauth_states
+    ADD COLUMN slack_team_id TEXT,
+    ADD COLUMN slack_enterprise_id TEXT;
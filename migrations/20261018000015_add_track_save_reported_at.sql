@@ -0,0 +1,84 @@
+-- Record when a successful save was reported to the user
+--
+-- A save is marked saved before it's reported, so a job retried after the
+-- report failed finds it saved. reported_at stays NULL until the report goes
+-- through; a retry that finds a saved but unreported save reports it as saved
+-- instead of already saved.
+ALTER TABLE track_saves
+    ADD COLUMN reported_at TIMESTAMPTZ;
+
+-- Saves made before this column were reported when they were made
+UPDATE track_saves
+SET reported_at = saved_at
+WHERE status = 'saved';
+
+-- A Grid workspace's saved row moved onto the enterprise row keeps its
+-- reported state
+CREATE OR REPLACE FUNCTION rekey_grid_workspace(p_team_id TEXT, p_enterprise_id TEXT)
+RETURNS BOOLEAN AS $$
+BEGIN
+    INSERT INTO grid_workspaces (team_id, enterprise_id)
+    VALUES (p_team_id, p_enterprise_id)
+    ON CONFLICT (team_id) DO NOTHING;
+
+    IF NOT FOUND THEN
+        RETURN FALSE;
+    END IF;
+
+    -- A user who already linked Spotify under the enterprise key keeps that link
+    DELETE FROM user_auth t
+    USING user_auth e
+    WHERE t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id;
+
+    UPDATE user_auth
+    SET slack_workspace_id = p_enterprise_id
+    WHERE slack_workspace_id = p_team_id;
+
+    -- A track saved under both keys keeps the enterprise row, which takes over
+    -- the workspace row's history and saved state
+    UPDATE track_saves e
+    SET status = 'saved',
+        saved_at = COALESCE(e.saved_at, t.saved_at),
+        reported_at = COALESCE(e.reported_at, t.reported_at),
+        last_error_code = NULL,
+        last_error_message = NULL
+    FROM track_saves t
+    WHERE t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id
+        AND e.thread_ts = t.thread_ts
+        AND e.spotify_track_id = t.spotify_track_id
+        AND t.status = 'saved'
+        AND e.status <> 'saved';
+
+    UPDATE save_action_log l
+    SET track_save_id = e.id
+    FROM track_saves t, track_saves e
+    WHERE l.track_save_id = t.id
+        AND t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id
+        AND e.thread_ts = t.thread_ts
+        AND e.spotify_track_id = t.spotify_track_id;
+
+    DELETE FROM track_saves t
+    USING track_saves e
+    WHERE t.slack_workspace_id = p_team_id
+        AND e.slack_workspace_id = p_enterprise_id
+        AND e.slack_user_id = t.slack_user_id
+        AND e.thread_ts = t.thread_ts
+        AND e.spotify_track_id = t.spotify_track_id;
+
+    UPDATE track_saves
+    SET slack_workspace_id = p_enterprise_id
+    WHERE slack_workspace_id = p_team_id;
+
+    UPDATE save_action_log
+    SET slack_workspace_id = p_enterprise_id
+    WHERE slack_workspace_id = p_team_id;
+
+    RETURN TRUE;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,7 @@
+-- Whether a Spotify connect flow started from a link the bot signed
+--
+-- Anyone can open /spotify/connect for any Slack user, so the callback only
+-- messages the user in Slack about a failed authorization when the flow
+-- started from a link the bot sent them.
+ALTER TABLE oauth_states
+    ADD COLUMN link_signed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,303 @@
+use crate::db::models::{AuditLogEntry, Job, TokenHealthCounts};
+use crate::db::repository::{
+    count_token_health, get_job, list_audit_log, list_jobs, retry_dead_job,
+};
+use crate::error::AppError;
+use crate::metrics::metrics;
+use crate::spotify::client::TOKEN_REFRESH_BUFFER;
+use crate::spotify::disconnect::{DisconnectOutcome, disconnect_user};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Application state for admin routes
+#[derive(Clone)]
+pub struct AdminState {
+    pub db: PgPool,
+    pub admin_token: String,
+}
+
+/// Check the `Authorization: Bearer <ADMIN_TOKEN>` header
+///
+/// # Errors
+/// - `Unauthorized` if the header is missing or the token doesn't match
+fn authorize(state: &AdminState, headers: &HeaderMap) -> Result<(), AppError> {
+    let token = headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized)?;
+
+    if !constant_time_eq(token.as_bytes(), state.admin_token.as_bytes()) {
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok(())
+}
+
+/// Compare two byte strings without short-circuiting on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Query parameters for /admin/jobs endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListJobsQuery {
+    pub status: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+/// List background jobs
+///
+/// # Endpoint
+/// GET /admin/jobs?status=<STATUS>&limit=<N>
+///
+/// # Query Parameters
+/// - `status`: Optional filter (`pending`, `running`, `completed`, `dead`)
+/// - `limit`: Maximum number of jobs (default 50, max 500)
+///
+/// # Returns
+/// JSON array of jobs, newest first
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+pub async fn list_jobs_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Query(params): Query<ListJobsQuery>,
+) -> Result<Json<Vec<Job>>, AppError> {
+    authorize(&state, &headers)?;
+
+    let jobs = list_jobs(
+        &state.db,
+        params.status.as_deref(),
+        params.limit.clamp(1, 500),
+    )
+    .await?;
+
+    Ok(Json(jobs))
+}
+
+/// Get a single background job
+///
+/// # Endpoint
+/// GET /admin/jobs/{id}
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+/// - 404 Not Found if no job has this ID
+pub async fn get_job_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Job>, AppError> {
+    authorize(&state, &headers)?;
+
+    let job = get_job(&state.db, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Job not found".to_string()))?;
+
+    Ok(Json(job))
+}
+
+/// Requeue a dead-lettered job
+///
+/// # Endpoint
+/// POST /admin/jobs/{id}/retry
+///
+/// # Returns
+/// The requeued job with a fresh attempt budget
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+/// - 404 Not Found if no dead job has this ID
+pub async fn retry_job_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Job>, AppError> {
+    authorize(&state, &headers)?;
+
+    let job = retry_dead_job(&state.db, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Dead job not found".to_string()))?;
+
+    tracing::info!(job_id = %job.id, kind = %job.kind, "Requeued dead job");
+
+    Ok(Json(job))
+}
+
+/// Query parameters for the disconnect endpoint
+#[derive(Debug, Deserialize)]
+pub struct DisconnectQuery {
+    #[serde(default)]
+    pub purge_history: bool,
+}
+
+/// Disconnect a Slack user from Spotify
+///
+/// Deletes the user's stored tokens, as the slash command and App Home button
+/// do. Spotify has no revocation endpoint, so the grant stays listed on the
+/// user's Spotify account until they remove it there.
+///
+/// # Endpoint
+/// DELETE /admin/users/{workspace_id}/{user_id}?purge_history=<BOOL>
+///
+/// # Query Parameters
+/// - `purge_history`: Also delete the user's save history (default false)
+///
+/// # Returns
+/// Whether the user was connected and how many track saves were purged
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+pub async fn disconnect_user_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path((workspace_id, user_id)): Path<(String, String)>,
+    Query(params): Query<DisconnectQuery>,
+) -> Result<Json<DisconnectOutcome>, AppError> {
+    authorize(&state, &headers)?;
+
+    let outcome = disconnect_user(&state.db, &workspace_id, &user_id, params.purge_history).await?;
+
+    Ok(Json(outcome))
+}
+
+/// Count linked Spotify accounts by token state
+///
+/// # Endpoint
+/// GET /admin/tokens/health
+///
+/// # Returns
+/// Counts of healthy accounts, accounts whose access token is expired or due
+/// for refresh, and broken accounts that need the user to reconnect
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+pub async fn token_health_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<Json<TokenHealthCounts>, AppError> {
+    authorize(&state, &headers)?;
+
+    let health = count_token_health(&state.db, Utc::now() + TOKEN_REFRESH_BUFFER).await?;
+
+    Ok(Json(health))
+}
+
+/// Query parameters for /admin/audit endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListAuditQuery {
+    pub workspace_id: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+/// List audit trail entries
+///
+/// # Endpoint
+/// GET /admin/audit?workspace_id=<TEAM_ID>&limit=<N>
+///
+/// # Query Parameters
+/// - `workspace_id`: Optional Slack workspace filter
+/// - `limit`: Maximum number of entries (default 50, max 500)
+///
+/// # Returns
+/// JSON array of audit entries (uninstalls and workspace purges), newest first
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+pub async fn list_audit_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Query(params): Query<ListAuditQuery>,
+) -> Result<Json<Vec<AuditLogEntry>>, AppError> {
+    authorize(&state, &headers)?;
+
+    let entries = list_audit_log(
+        &state.db,
+        params.workspace_id.as_deref(),
+        params.limit.clamp(1, 500),
+    )
+    .await?;
+
+    Ok(Json(entries))
+}
+
+/// Export Prometheus metrics
+///
+/// Admin-only because the counters reveal workspace activity.
+///
+/// # Endpoint
+/// GET /metrics
+///
+/// # Returns
+/// Prometheus text-format counters
+///
+/// # Errors
+/// - 401 Unauthorized if the admin token is missing or wrong
+pub async fn metrics_handler(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<String, AppError> {
+    authorize(&state, &headers)?;
+
+    Ok(metrics().render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> AdminState {
+        let db = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .connect_lazy("postgresql://localhost/savethebeat_test")
+            .unwrap();
+
+        AdminState {
+            db,
+            admin_token: "secret-admin-token".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize_valid_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            "Bearer secret-admin-token".parse().unwrap(),
+        );
+        assert!(authorize(&state(), &headers).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_authorize_wrong_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer nope".parse().unwrap());
+        assert!(matches!(
+            authorize(&state(), &headers),
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_authorize_missing_header() {
+        assert!(matches!(
+            authorize(&state(), &HeaderMap::new()),
+            Err(AppError::Unauthorized)
+        ));
+    }
+}
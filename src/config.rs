@@ -18,8 +18,103 @@ pub struct Config {
     pub base_url: String,
 
     // Slack (Phase 2+)
+    // Comma-separated signing secrets, current first; previous secrets stay
+    // accepted while a rotation rolls out
     pub slack_signing_secret: Option<String>,
+    #[serde(default = "default_slack_signature_max_skew_secs")]
+    pub slack_signature_max_skew_secs: i64,
     pub slack_bot_token: Option<String>,
+    // Multi-workspace install via /slack/install (disabled unless both are set);
+    // SLACK_BOT_TOKEN then only serves workspaces without an installation
+    pub slack_client_id: Option<String>,
+    pub slack_client_secret: Option<String>,
+    #[serde(default = "default_slack_bot_scopes")]
+    pub slack_bot_scopes: String,
+    #[serde(default = "default_slack_authorize_url")]
+    pub slack_authorize_url: String,
+    #[serde(default = "default_slack_event_ttl_hours")]
+    pub slack_event_ttl_hours: i64,
+    // Most thread messages searched for a Spotify link per mention
+    #[serde(default = "default_slack_thread_max_messages")]
+    pub slack_thread_max_messages: usize,
+    // How far back a top-level mention without a link looks for the channel's
+    // most recent track (0 messages disables the lookback)
+    #[serde(default = "default_slack_channel_lookback_hours")]
+    pub slack_channel_lookback_hours: i64,
+    #[serde(default = "default_slack_channel_lookback_messages")]
+    pub slack_channel_lookback_messages: usize,
+    // How events reach the app: "http" (POST /slack/events, needs the signing
+    // secret) or "socket" (Socket Mode over WebSocket, needs SLACK_APP_TOKEN)
+    #[serde(default = "default_slack_transport")]
+    pub slack_transport: String,
+    pub slack_app_token: Option<String>,
+    #[serde(default = "default_slack_socket_connections")]
+    pub slack_socket_connections: usize,
+    #[serde(default = "default_slack_socket_max_backoff_secs")]
+    pub slack_socket_max_backoff_secs: u64,
+    // Whether users from other organizations in Slack Connect channels may use
+    // the bot ("allow" or "deny")
+    #[serde(default = "default_slack_external_users")]
+    pub slack_external_users: String,
+    // How long an uninstalled workspace's tokens and history are kept
+    #[serde(default = "default_slack_uninstall_grace_hours")]
+    pub slack_uninstall_grace_hours: i64,
+
+    // Outbound HTTP (overridable so tests can point at local stand-ins)
+    #[serde(default = "default_slack_api_base_url")]
+    pub slack_api_base_url: String,
+    #[serde(default = "default_spotify_api_base_url")]
+    pub spotify_api_base_url: String,
+    #[serde(default = "default_spotify_accounts_base_url")]
+    pub spotify_accounts_base_url: String,
+    #[serde(default = "default_http_connect_timeout_secs")]
+    pub http_connect_timeout_secs: u64,
+    #[serde(default = "default_http_timeout_secs")]
+    pub http_timeout_secs: u64,
+    // Token refreshes hold a row lock while waiting for Spotify, so they get a
+    // shorter timeout than other requests
+    #[serde(default = "default_spotify_token_refresh_timeout_secs")]
+    pub spotify_token_refresh_timeout_secs: u64,
+
+    // Background jobs
+    #[serde(default = "default_job_workers")]
+    pub job_workers: usize,
+    #[serde(default = "default_job_visibility_timeout_secs")]
+    pub job_visibility_timeout_secs: u64,
+    #[serde(default = "default_job_max_attempts")]
+    pub job_max_attempts: i32,
+    #[serde(default = "default_job_poll_interval_ms")]
+    pub job_poll_interval_ms: u64,
+    #[serde(default = "default_job_retry_backoff_secs")]
+    pub job_retry_backoff_secs: u64,
+    // How long completed and dead jobs are kept before the hourly sweep deletes them
+    #[serde(default = "default_job_retention_days")]
+    pub job_retention_days: i64,
+    #[serde(default = "default_job_dead_retention_days")]
+    pub job_dead_retention_days: i64,
+
+    // Spotify OAuth state ("postgres" or "memory"; memory only works with one instance)
+    #[serde(default = "default_oauth_state_store")]
+    pub oauth_state_store: String,
+    #[serde(default = "default_oauth_state_max_pending")]
+    pub oauth_state_max_pending: usize,
+    #[serde(default = "default_oauth_state_max_per_user")]
+    pub oauth_state_max_per_user: usize,
+
+    // Token encryption at rest: comma-separated `key_id:base64_key` pairs,
+    // the first of which encrypts new tokens (tokens stay plaintext if unset)
+    pub token_encryption_keys: Option<String>,
+
+    // Background token refresh (interval 0 disables the refresher)
+    #[serde(default = "default_token_refresh_interval_secs")]
+    pub token_refresh_interval_secs: u64,
+    #[serde(default = "default_token_active_window_hours")]
+    pub token_active_window_hours: i64,
+    #[serde(default = "default_token_probe_interval_hours")]
+    pub token_probe_interval_hours: i64,
+
+    // Admin API (disabled unless a token is set)
+    pub admin_token: Option<String>,
 
     #[serde(default = "default_rust_log")]
     pub rust_log: String,
@@ -33,6 +128,130 @@ fn default_host() -> String {
     "0.0.0.0".to_string()
 }
 
+fn default_slack_event_ttl_hours() -> i64 {
+    24
+}
+
+fn default_slack_thread_max_messages() -> usize {
+    1000
+}
+
+fn default_slack_channel_lookback_hours() -> i64 {
+    24
+}
+
+fn default_slack_channel_lookback_messages() -> usize {
+    50
+}
+
+fn default_slack_signature_max_skew_secs() -> i64 {
+    300
+}
+
+fn default_slack_transport() -> String {
+    "http".to_string()
+}
+
+fn default_slack_socket_connections() -> usize {
+    2
+}
+
+fn default_slack_socket_max_backoff_secs() -> u64 {
+    60
+}
+
+fn default_slack_external_users() -> String {
+    "allow".to_string()
+}
+
+fn default_slack_uninstall_grace_hours() -> i64 {
+    72
+}
+
+fn default_slack_bot_scopes() -> String {
+    "app_mentions:read,channels:history,channels:join,groups:history,im:history,mpim:history,reactions:write,chat:write,commands,links:read,links:write".to_string()
+}
+
+fn default_slack_authorize_url() -> String {
+    "https://slack.com/oauth/v2/authorize".to_string()
+}
+
+fn default_slack_api_base_url() -> String {
+    "https://slack.com/api".to_string()
+}
+
+fn default_spotify_api_base_url() -> String {
+    "https://api.spotify.com/v1".to_string()
+}
+
+fn default_spotify_accounts_base_url() -> String {
+    "https://accounts.spotify.com".to_string()
+}
+
+fn default_http_connect_timeout_secs() -> u64 {
+    5
+}
+
+fn default_http_timeout_secs() -> u64 {
+    10
+}
+
+fn default_spotify_token_refresh_timeout_secs() -> u64 {
+    5
+}
+
+fn default_job_workers() -> usize {
+    2
+}
+
+fn default_job_visibility_timeout_secs() -> u64 {
+    120
+}
+
+fn default_job_max_attempts() -> i32 {
+    5
+}
+
+fn default_job_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_job_retry_backoff_secs() -> u64 {
+    5
+}
+
+fn default_job_retention_days() -> i64 {
+    7
+}
+
+fn default_job_dead_retention_days() -> i64 {
+    30
+}
+
+fn default_oauth_state_store() -> String {
+    "postgres".to_string()
+}
+
+fn default_oauth_state_max_pending() -> usize {
+    10_000
+}
+
+fn default_oauth_state_max_per_user() -> usize {
+    5
+}
+
+fn default_token_refresh_interval_secs() -> u64 {
+    300
+}
+
+fn default_token_active_window_hours() -> i64 {
+    24
+}
+
+fn default_token_probe_interval_hours() -> i64 {
+    24
+}
+
 fn default_rust_log() -> String {
     "info,savethebeat=debug".to_string()
 }
@@ -42,4 +261,34 @@ impl Config {
         dotenvy::dotenv().ok();
         envy::from_env::<Config>().map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))
     }
+
+    /// Build config from explicit key/value pairs instead of the process environment
+    ///
+    /// Keys use the same names as environment variables (e.g. `DATABASE_URL`), and
+    /// unset optional values fall back to their defaults. Mainly useful for tests.
+    pub fn from_vars<I, K, V>(vars: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        envy::from_iter::<_, Config>(vars.into_iter().map(|(k, v)| (k.into(), v.into())))
+            .map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_config() -> Config {
+    Config::from_vars([
+        ("DATABASE_URL", "postgresql://localhost/savethebeat_test"),
+        ("SPOTIFY_CLIENT_ID", "test_client_id"),
+        ("SPOTIFY_CLIENT_SECRET", "test_client_secret"),
+        (
+            "SPOTIFY_REDIRECT_URI",
+            "http://localhost:3000/spotify/callback",
+        ),
+        ("BASE_URL", "http://localhost:3000"),
+        ("RUST_LOG", "info"),
+    ])
+    .expect("test config should be valid")
 }
@@ -0,0 +1,504 @@
+//! Envelope encryption for Spotify tokens stored at rest
+//!
+//! Each `user_auth` row gets its own random data key, which encrypts the
+//! access and refresh tokens with AES-256-GCM. The data key is stored next to
+//! the tokens, itself encrypted ("wrapped") by a long-lived key encryption key
+//! from config, along with that key's ID. Rotating the key encryption key only
+//! requires rewrapping the data keys; token ciphertexts stay untouched.
+//! Single secrets such as Slack bot tokens use the same scheme.
+
+use crate::config::Config;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    #[error("Invalid token encryption keys: {0}")]
+    InvalidKeys(String),
+
+    #[error("Unknown token encryption key: {0}")]
+    UnknownKey(String),
+
+    #[error("Encrypted value is malformed")]
+    Malformed,
+
+    #[error("Encryption failed")]
+    Encrypt,
+
+    #[error("Decryption failed")]
+    Decrypt,
+}
+
+/// Token columns as stored in `user_auth`
+///
+/// `key_id` and `data_key` are `None` for rows written without encryption,
+/// in which case the tokens are plaintext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedTokens {
+    pub key_id: Option<String>,
+    pub data_key: Option<String>,
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+/// A single secret as stored at rest (e.g., an installation's bot token)
+///
+/// As with `SealedTokens`, `key_id` and `data_key` are `None` for plaintext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedSecret {
+    pub key_id: Option<String>,
+    pub data_key: Option<String>,
+    pub value: String,
+}
+
+/// Encrypts and decrypts stored tokens with the configured key encryption keys
+///
+/// Cheap to clone. A cipher built without keys is disabled: it stores tokens
+/// as plaintext and can only read plaintext rows.
+#[derive(Clone, Default)]
+pub struct TokenCipher {
+    inner: Arc<Keyring>,
+}
+
+#[derive(Default)]
+struct Keyring {
+    keys: HashMap<String, Aes256Gcm>,
+    active: Option<String>,
+}
+
+impl fmt::Debug for TokenCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut key_ids: Vec<&String> = self.inner.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("TokenCipher")
+            .field("active", &self.inner.active)
+            .field("keys", &key_ids)
+            .finish()
+    }
+}
+
+impl TokenCipher {
+    /// Build a cipher from `TOKEN_ENCRYPTION_KEYS`, or a disabled one if unset
+    ///
+    /// # Errors
+    /// Returns error if the key list is malformed
+    pub fn from_config(config: &Config) -> Result<Self, CryptoError> {
+        match &config.token_encryption_keys {
+            Some(spec) => Self::from_keys(spec),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Parse a comma-separated list of `key_id:base64_key` pairs
+    ///
+    /// The first key is active and encrypts new tokens; the others are only
+    /// used to read rows that have not been rotated yet. Keys must decode to
+    /// 32 bytes.
+    ///
+    /// # Errors
+    /// Returns error if the list is empty, a key ID is repeated, or a key is
+    /// not valid base64 of the right length
+    pub fn from_keys(spec: &str) -> Result<Self, CryptoError> {
+        let mut keyring = Keyring::default();
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key_id, encoded) = entry.split_once(':').ok_or_else(|| {
+                CryptoError::InvalidKeys(format!("expected key_id:base64_key, got {:?}", entry))
+            })?;
+            let key_id = key_id.trim();
+            if key_id.is_empty() {
+                return Err(CryptoError::InvalidKeys("empty key ID".to_string()));
+            }
+
+            let bytes = STANDARD.decode(encoded.trim()).map_err(|_| {
+                CryptoError::InvalidKeys(format!("key {} is not valid base64", key_id))
+            })?;
+            if bytes.len() != KEY_LEN {
+                return Err(CryptoError::InvalidKeys(format!(
+                    "key {} must be {} bytes, got {}",
+                    key_id,
+                    KEY_LEN,
+                    bytes.len()
+                )));
+            }
+
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes));
+            if keyring.keys.insert(key_id.to_string(), cipher).is_some() {
+                return Err(CryptoError::InvalidKeys(format!(
+                    "duplicate key ID {}",
+                    key_id
+                )));
+            }
+            keyring.active.get_or_insert_with(|| key_id.to_string());
+        }
+
+        if keyring.active.is_none() {
+            return Err(CryptoError::InvalidKeys("no keys given".to_string()));
+        }
+
+        Ok(Self {
+            inner: Arc::new(keyring),
+        })
+    }
+
+    /// Whether new tokens are encrypted
+    pub fn is_enabled(&self) -> bool {
+        self.inner.active.is_some()
+    }
+
+    /// ID of the key that encrypts new tokens
+    pub fn active_key_id(&self) -> Option<&str> {
+        self.inner.active.as_deref()
+    }
+
+    /// Encrypt tokens under a fresh data key wrapped by the active key
+    ///
+    /// Returns the tokens unchanged if the cipher is disabled.
+    pub fn seal(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+    ) -> Result<SealedTokens, CryptoError> {
+        let Some(key_id) = &self.inner.active else {
+            return Ok(SealedTokens {
+                key_id: None,
+                data_key: None,
+                access_token: access_token.to_string(),
+                refresh_token: refresh_token.to_string(),
+            });
+        };
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let data_cipher = Aes256Gcm::new(&data_key);
+
+        Ok(SealedTokens {
+            key_id: Some(key_id.clone()),
+            data_key: Some(self.wrap(key_id, &data_key)?),
+            access_token: encrypt(&data_cipher, access_token.as_bytes(), b"access_token")?,
+            refresh_token: encrypt(&data_cipher, refresh_token.as_bytes(), b"refresh_token")?,
+        })
+    }
+
+    /// Decrypt stored tokens, returning `(access_token, refresh_token)`
+    ///
+    /// # Errors
+    /// Returns error if the row's key is not configured or a value fails
+    /// authentication
+    pub fn open(&self, sealed: &SealedTokens) -> Result<(String, String), CryptoError> {
+        let (Some(key_id), Some(data_key)) = (&sealed.key_id, &sealed.data_key) else {
+            return Ok((sealed.access_token.clone(), sealed.refresh_token.clone()));
+        };
+
+        let data_cipher = self.unwrap(key_id, data_key)?;
+        let access_token = decrypt(&data_cipher, &sealed.access_token, b"access_token")?;
+        let refresh_token = decrypt(&data_cipher, &sealed.refresh_token, b"refresh_token")?;
+
+        Ok((
+            String::from_utf8(access_token).map_err(|_| CryptoError::Malformed)?,
+            String::from_utf8(refresh_token).map_err(|_| CryptoError::Malformed)?,
+        ))
+    }
+
+    /// Encrypt a single secret under a fresh data key wrapped by the active key
+    ///
+    /// `label` names the column and is authenticated with the ciphertext, so a
+    /// value can't be moved to another column undetected. Returns the secret
+    /// unchanged if the cipher is disabled.
+    pub fn seal_secret(&self, secret: &str, label: &str) -> Result<SealedSecret, CryptoError> {
+        let Some(key_id) = &self.inner.active else {
+            return Ok(SealedSecret {
+                key_id: None,
+                data_key: None,
+                value: secret.to_string(),
+            });
+        };
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let data_cipher = Aes256Gcm::new(&data_key);
+
+        Ok(SealedSecret {
+            key_id: Some(key_id.clone()),
+            data_key: Some(self.wrap(key_id, &data_key)?),
+            value: encrypt(&data_cipher, secret.as_bytes(), label.as_bytes())?,
+        })
+    }
+
+    /// Decrypt a secret sealed with `seal_secret` under the same `label`
+    ///
+    /// # Errors
+    /// Returns error if the secret's key is not configured or the value fails
+    /// authentication
+    pub fn open_secret(&self, sealed: &SealedSecret, label: &str) -> Result<String, CryptoError> {
+        let (Some(key_id), Some(data_key)) = (&sealed.key_id, &sealed.data_key) else {
+            return Ok(sealed.value.clone());
+        };
+
+        let data_cipher = self.unwrap(key_id, data_key)?;
+        let value = decrypt(&data_cipher, &sealed.value, label.as_bytes())?;
+        String::from_utf8(value).map_err(|_| CryptoError::Malformed)
+    }
+
+    /// Move stored tokens onto the active key
+    ///
+    /// Rows under an older key only have their data key rewrapped; plaintext
+    /// rows are encrypted.
+    ///
+    /// # Returns
+    /// The new column values, or None if the row is already under the active
+    /// key (or the cipher is disabled)
+    pub fn rotate(&self, sealed: &SealedTokens) -> Result<Option<SealedTokens>, CryptoError> {
+        let Some(active) = &self.inner.active else {
+            return Ok(None);
+        };
+
+        match (&sealed.key_id, &sealed.data_key) {
+            (Some(key_id), _) if key_id == active => Ok(None),
+            (Some(key_id), Some(data_key)) => {
+                let raw_key = self.unwrap_raw(key_id, data_key)?;
+                Ok(Some(SealedTokens {
+                    key_id: Some(active.clone()),
+                    data_key: Some(self.wrap(active, Key::<Aes256Gcm>::from_slice(&raw_key))?),
+                    access_token: sealed.access_token.clone(),
+                    refresh_token: sealed.refresh_token.clone(),
+                }))
+            }
+            _ => self
+                .seal(&sealed.access_token, &sealed.refresh_token)
+                .map(Some),
+        }
+    }
+
+    fn key(&self, key_id: &str) -> Result<&Aes256Gcm, CryptoError> {
+        self.inner
+            .keys
+            .get(key_id)
+            .ok_or_else(|| CryptoError::UnknownKey(key_id.to_string()))
+    }
+
+    fn wrap(&self, key_id: &str, data_key: &Key<Aes256Gcm>) -> Result<String, CryptoError> {
+        encrypt(self.key(key_id)?, data_key.as_slice(), key_id.as_bytes())
+    }
+
+    fn unwrap_raw(&self, key_id: &str, wrapped: &str) -> Result<Vec<u8>, CryptoError> {
+        let raw_key = decrypt(self.key(key_id)?, wrapped, key_id.as_bytes())?;
+        if raw_key.len() != KEY_LEN {
+            return Err(CryptoError::Malformed);
+        }
+        Ok(raw_key)
+    }
+
+    fn unwrap(&self, key_id: &str, wrapped: &str) -> Result<Aes256Gcm, CryptoError> {
+        let raw_key = self.unwrap_raw(key_id, wrapped)?;
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&raw_key)))
+    }
+}
+
+/// Encrypt with a random nonce, returning base64 of `nonce || ciphertext`
+fn encrypt(cipher: &Aes256Gcm, plaintext: &[u8], aad: &[u8]) -> Result<String, CryptoError> {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| CryptoError::Encrypt)?;
+
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(out))
+}
+
+fn decrypt(cipher: &Aes256Gcm, encoded: &str, aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let bytes = STANDARD
+        .decode(encoded)
+        .map_err(|_| CryptoError::Malformed)?;
+    if bytes.len() < NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+/// Cipher with deterministic keys derived from each key ID, first one active
+#[cfg(test)]
+pub(crate) fn test_cipher(key_ids: &[&str]) -> TokenCipher {
+    use sha2::{Digest, Sha256};
+
+    let spec: Vec<String> = key_ids
+        .iter()
+        .map(|id| format!("{}:{}", id, STANDARD.encode(Sha256::digest(id.as_bytes()))))
+        .collect();
+    TokenCipher::from_keys(&spec.join(",")).expect("test keys should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_roundtrip() {
+        let cipher = test_cipher(&["k1"]);
+        let sealed = cipher.seal("access", "refresh").unwrap();
+
+        assert_eq!(sealed.key_id.as_deref(), Some("k1"));
+        assert!(sealed.data_key.is_some());
+        assert_ne!(sealed.access_token, "access");
+        assert_ne!(sealed.refresh_token, "refresh");
+
+        let (access, refresh) = cipher.open(&sealed).unwrap();
+        assert_eq!(access, "access");
+        assert_eq!(refresh, "refresh");
+    }
+
+    #[test]
+    fn test_disabled_cipher_passes_through() {
+        let cipher = TokenCipher::default();
+        assert!(!cipher.is_enabled());
+
+        let sealed = cipher.seal("access", "refresh").unwrap();
+        assert_eq!(sealed.key_id, None);
+        assert_eq!(sealed.access_token, "access");
+        assert_eq!(
+            cipher.open(&sealed).unwrap(),
+            ("access".to_string(), "refresh".to_string())
+        );
+        assert_eq!(cipher.rotate(&sealed).unwrap(), None);
+    }
+
+    #[test]
+    fn test_open_plaintext_row_with_enabled_cipher() {
+        let cipher = test_cipher(&["k1"]);
+        let legacy = TokenCipher::default().seal("access", "refresh").unwrap();
+
+        assert_eq!(
+            cipher.open(&legacy).unwrap(),
+            ("access".to_string(), "refresh".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rotate_rewraps_data_key_only() {
+        let old = test_cipher(&["k1"]);
+        let sealed = old.seal("access", "refresh").unwrap();
+
+        // New key first, old key kept for reading
+        let cipher = test_cipher(&["k2", "k1"]);
+        assert_eq!(cipher.active_key_id(), Some("k2"));
+
+        let rotated = cipher.rotate(&sealed).unwrap().unwrap();
+        assert_eq!(rotated.key_id.as_deref(), Some("k2"));
+        assert_ne!(rotated.data_key, sealed.data_key);
+        assert_eq!(rotated.access_token, sealed.access_token);
+        assert_eq!(rotated.refresh_token, sealed.refresh_token);
+        assert_eq!(
+            cipher.open(&rotated).unwrap(),
+            ("access".to_string(), "refresh".to_string())
+        );
+
+        // Already on the active key
+        assert_eq!(cipher.rotate(&rotated).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rotate_encrypts_plaintext_row() {
+        let cipher = test_cipher(&["k1"]);
+        let legacy = TokenCipher::default().seal("access", "refresh").unwrap();
+
+        let rotated = cipher.rotate(&legacy).unwrap().unwrap();
+        assert_eq!(rotated.key_id.as_deref(), Some("k1"));
+        assert_ne!(rotated.access_token, "access");
+        assert_eq!(cipher.open(&rotated).unwrap().0, "access");
+    }
+
+    #[test]
+    fn test_open_with_unknown_key_fails() {
+        let sealed = test_cipher(&["k1"]).seal("access", "refresh").unwrap();
+        let other = test_cipher(&["k9"]);
+
+        assert!(matches!(
+            other.open(&sealed),
+            Err(CryptoError::UnknownKey(id)) if id == "k1"
+        ));
+    }
+
+    #[test]
+    fn test_open_detects_swapped_columns() {
+        let cipher = test_cipher(&["k1"]);
+        let mut sealed = cipher.seal("access", "refresh").unwrap();
+        std::mem::swap(&mut sealed.access_token, &mut sealed.refresh_token);
+
+        assert!(matches!(cipher.open(&sealed), Err(CryptoError::Decrypt)));
+    }
+
+    #[test]
+    fn test_seal_and_open_secret() {
+        let cipher = test_cipher(&["k1"]);
+        let sealed = cipher.seal_secret("xoxb-secret", "bot_token").unwrap();
+
+        assert_eq!(sealed.key_id.as_deref(), Some("k1"));
+        assert_ne!(sealed.value, "xoxb-secret");
+        assert_eq!(
+            cipher.open_secret(&sealed, "bot_token").unwrap(),
+            "xoxb-secret"
+        );
+
+        // The label is authenticated
+        assert!(matches!(
+            cipher.open_secret(&sealed, "access_token"),
+            Err(CryptoError::Decrypt)
+        ));
+
+        let plain = TokenCipher::default()
+            .seal_secret("xoxb-secret", "bot_token")
+            .unwrap();
+        assert_eq!(plain.value, "xoxb-secret");
+        assert_eq!(plain.key_id, None);
+    }
+
+    #[test]
+    fn test_from_keys_rejects_invalid_specs() {
+        let short = format!("k1:{}", STANDARD.encode([1u8; 16]));
+        let dup = format!(
+            "k1:{},k1:{}",
+            STANDARD.encode([1u8; KEY_LEN]),
+            STANDARD.encode([2u8; KEY_LEN])
+        );
+
+        for spec in [
+            "",
+            "k1",
+            "k1:not-base64!",
+            ":AAAA",
+            short.as_str(),
+            dup.as_str(),
+        ] {
+            assert!(
+                matches!(
+                    TokenCipher::from_keys(spec),
+                    Err(CryptoError::InvalidKeys(_))
+                ),
+                "expected {:?} to be rejected",
+                spec
+            );
+        }
+    }
+}
@@ -15,6 +15,26 @@ pub struct UserAuth {
     pub paused: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub token_key_id: Option<String>,
+    pub token_data_key: Option<String>,
+    pub spotify_display_name: Option<String>,
+    pub spotify_country: Option<String>,
+    pub spotify_product: Option<String>,
+    pub spotify_profile_updated_at: Option<DateTime<Utc>>,
+    pub needs_reauth: bool,
+    pub needs_reauth_at: Option<DateTime<Utc>>,
+    pub last_refreshed_at: Option<DateTime<Utc>>,
+}
+
+/// Spotify accounts by token state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TokenHealthCounts {
+    /// Access token valid beyond the refresh buffer
+    pub healthy: i64,
+    /// Access token expired or due for refresh (refreshable on next use)
+    pub expiring: i64,
+    /// Refresh token rejected; the user must reconnect
+    pub broken: i64,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -30,4 +50,88 @@ pub struct SaveActionLog {
     pub error_code: Option<String>,
     pub error_message: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub track_save_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TrackSave {
+    pub id: Uuid,
+    pub slack_workspace_id: String,
+    pub slack_user_id: String,
+    pub channel_id: String,
+    pub thread_ts: String,
+    pub spotify_track_id: String,
+    pub status: String,
+    pub attempt_count: i32,
+    pub last_error_code: Option<String>,
+    pub last_error_message: Option<String>,
+    pub saved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub reported_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Job {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthStateRecord {
+    pub token: String,
+    pub slack_workspace_id: String,
+    pub slack_user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub pkce_verifier: String,
+    pub slack_team_id: Option<String>,
+    pub slack_enterprise_id: Option<String>,
+    pub link_signed: bool,
+}
+
+/// A workspace's Slack app installation (bot token decrypted when read)
+#[derive(Debug, Clone, FromRow)]
+pub struct Installation {
+    pub id: Uuid,
+    pub team_id: String,
+    pub team_name: Option<String>,
+    pub app_id: Option<String>,
+    pub bot_user_id: String,
+    pub bot_token: String,
+    pub token_key_id: Option<String>,
+    pub token_data_key: Option<String>,
+    pub scopes: String,
+    pub installed_by_user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub enterprise_id: Option<String>,
+    pub is_enterprise_install: bool,
+}
+
+/// An entry in the workspace audit trail
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub action: String,
+    pub workspace_id: String,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Rows deleted when purging a workspace's data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct WorkspacePurgeCounts {
+    pub user_auth: u64,
+    pub track_saves: u64,
+    pub save_actions: u64,
 }
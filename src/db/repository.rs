@@ -1,12 +1,19 @@
-use crate::db::models::{SaveActionLog, UserAuth};
+use crate::crypto::{SealedSecret, SealedTokens, TokenCipher};
+use crate::db::models::{
+    AuditLogEntry, Installation, Job, OAuthStateRecord, SaveActionLog, TokenHealthCounts,
+    TrackSave, UserAuth, WorkspacePurgeCounts,
+};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool};
 use uuid::Uuid;
 
 /// Get user authentication record by Slack workspace and user IDs.
 ///
+/// Tokens are decrypted before being returned.
+///
 /// # Arguments
 /// * `pool` - Database connection pool
+/// * `cipher` - Token cipher holding the row's key
 /// * `workspace_id` - Slack workspace ID
 /// * `user_id` - Slack user ID
 ///
@@ -14,13 +21,14 @@ use uuid::Uuid;
 /// Optional UserAuth if found, None otherwise
 ///
 /// # Errors
-/// Returns error if database query fails
+/// Returns error if database query fails or the tokens cannot be decrypted
 pub async fn get_user_auth(
     pool: &PgPool,
+    cipher: &TokenCipher,
     workspace_id: &str,
     user_id: &str,
 ) -> Result<Option<UserAuth>, sqlx::Error> {
-    sqlx::query_as!(
+    let user_auth = sqlx::query_as!(
         UserAuth,
         r#"
         SELECT * FROM user_auth
@@ -30,74 +38,122 @@ pub async fn get_user_auth(
         user_id
     )
     .fetch_optional(pool)
-    .await
+    .await?;
+
+    user_auth
+        .map(|user_auth| decrypt_user_auth(cipher, user_auth))
+        .transpose()
+}
+
+/// Parameters for storing a user's Spotify authentication
+pub struct UserAuthParams<'a> {
+    pub workspace_id: &'a str,
+    pub user_id: &'a str,
+    /// Spotify profile, or None if it couldn't be fetched
+    pub profile: Option<SpotifyProfileParams<'a>>,
+    pub access_token: &'a str,
+    pub refresh_token: &'a str,
+    pub expires_at: DateTime<Utc>,
 }
 
 /// Insert or update user authentication record.
 ///
-/// Uses ON CONFLICT to update existing records with new token information.
+/// Uses ON CONFLICT to update existing records with new token information,
+/// clearing any `needs_reauth` flag. Tokens are encrypted under the cipher's
+/// active key before being stored.
+///
+/// The Spotify profile (ID, display name, country, product) is written as a
+/// whole with the tokens. Without a profile all four columns are cleared
+/// rather than keeping the previous ones, since a reconnect may have linked
+/// a different Spotify account.
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `workspace_id` - Slack workspace ID
-/// * `user_id` - Slack user ID
-/// * `spotify_user_id` - Spotify user ID (optional)
-/// * `access_token` - Spotify access token
-/// * `refresh_token` - Spotify refresh token
-/// * `expires_at` - Token expiration timestamp
+/// * `cipher` - Token cipher used to encrypt the tokens
+/// * `params` - Slack identity, Spotify profile and tokens to store
 ///
 /// # Returns
-/// The created or updated UserAuth record
+/// The created or updated UserAuth record, with decrypted tokens
 ///
 /// # Errors
-/// Returns error if database operation fails
+/// Returns error if encryption or the database operation fails
 pub async fn upsert_user_auth(
     pool: &PgPool,
-    workspace_id: &str,
-    user_id: &str,
-    spotify_user_id: Option<String>,
-    access_token: &str,
-    refresh_token: &str,
-    expires_at: DateTime<Utc>,
+    cipher: &TokenCipher,
+    params: UserAuthParams<'_>,
 ) -> Result<UserAuth, sqlx::Error> {
-    sqlx::query_as!(
+    let sealed = cipher
+        .seal(params.access_token, params.refresh_token)
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    let profile = params.profile.as_ref();
+
+    let user_auth = sqlx::query_as!(
         UserAuth,
         r#"
         INSERT INTO user_auth (
             slack_workspace_id,
             slack_user_id,
             spotify_user_id,
+            spotify_display_name,
+            spotify_country,
+            spotify_product,
+            spotify_profile_updated_at,
             access_token,
             refresh_token,
-            expires_at
+            expires_at,
+            token_key_id,
+            token_data_key
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6,
+            CASE WHEN $3::TEXT IS NULL THEN NULL ELSE NOW() END,
+            $7, $8, $9, $10, $11
         )
-        VALUES ($1, $2, $3, $4, $5, $6)
         ON CONFLICT (slack_workspace_id, slack_user_id)
         DO UPDATE SET
             spotify_user_id = EXCLUDED.spotify_user_id,
+            spotify_display_name = EXCLUDED.spotify_display_name,
+            spotify_country = EXCLUDED.spotify_country,
+            spotify_product = EXCLUDED.spotify_product,
+            spotify_profile_updated_at = EXCLUDED.spotify_profile_updated_at,
             access_token = EXCLUDED.access_token,
             refresh_token = EXCLUDED.refresh_token,
             expires_at = EXCLUDED.expires_at,
+            token_key_id = EXCLUDED.token_key_id,
+            token_data_key = EXCLUDED.token_data_key,
+            needs_reauth = false,
+            needs_reauth_at = NULL,
             updated_at = NOW()
         RETURNING *
         "#,
-        workspace_id,
-        user_id,
-        spotify_user_id,
-        access_token,
-        refresh_token,
-        expires_at
+        params.workspace_id,
+        params.user_id,
+        profile.map(|profile| profile.spotify_user_id),
+        profile.and_then(|profile| profile.display_name),
+        profile.and_then(|profile| profile.country),
+        profile.and_then(|profile| profile.product),
+        sealed.access_token,
+        sealed.refresh_token,
+        params.expires_at,
+        sealed.key_id,
+        sealed.data_key
     )
     .fetch_one(pool)
-    .await
+    .await?;
+
+    decrypt_user_auth(cipher, user_auth)
 }
 
 /// Update access and refresh tokens for a user.
 ///
-/// Used when refreshing expired tokens.
+/// Used when refreshing expired tokens; records the refresh in
+/// `last_refreshed_at`. The new tokens get a fresh data key under the cipher's
+/// active key. Accepts any executor so the update can join
+/// the transaction holding the refresh lock.
 ///
 /// # Arguments
-/// * `pool` - Database connection pool
+/// * `executor` - Database pool, connection or transaction
+/// * `cipher` - Token cipher used to encrypt the tokens
 /// * `id` - User auth record ID
 /// * `access_token` - New Spotify access token
 /// * `refresh_token` - New Spotify refresh token
@@ -106,14 +162,19 @@ pub async fn upsert_user_auth(
 /// # Errors
 /// Returns error if:
 /// - Record with given ID doesn't exist
-/// - Database update fails
-pub async fn update_tokens(
-    pool: &PgPool,
+/// - Encryption or the database update fails
+pub async fn update_tokens<'e>(
+    executor: impl PgExecutor<'e>,
+    cipher: &TokenCipher,
     id: Uuid,
     access_token: &str,
     refresh_token: &str,
     expires_at: DateTime<Utc>,
 ) -> Result<(), sqlx::Error> {
+    let sealed = cipher
+        .seal(access_token, refresh_token)
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
     sqlx::query!(
         r#"
         UPDATE user_auth
@@ -121,12 +182,226 @@ pub async fn update_tokens(
             access_token = $1,
             refresh_token = $2,
             expires_at = $3,
+            token_key_id = $4,
+            token_data_key = $5,
+            last_refreshed_at = NOW(),
             updated_at = NOW()
-        WHERE id = $4
+        WHERE id = $6
         "#,
-        access_token,
-        refresh_token,
+        sealed.access_token,
+        sealed.refresh_token,
         expires_at,
+        sealed.key_id,
+        sealed.data_key,
+        id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Lock a user auth record for a token refresh
+///
+/// Takes a row lock (`SELECT ... FOR UPDATE`) held until the caller's
+/// transaction ends, so concurrent refreshes of the same user, in this process
+/// or another, run one at a time. Returns the current (decrypted) record so
+/// the caller can re-check whether a refresh is still needed.
+///
+/// The lock is held across the refresh request to Spotify, so the transaction
+/// gets a `lock_timeout` and an `idle_in_transaction_session_timeout` of
+/// `timeout`: waiters give up instead of queueing behind a stuck refresh, and a
+/// holder that stops talking to the database loses its session (and the lock).
+///
+/// # Arguments
+/// * `conn` - Connection with an open transaction
+/// * `cipher` - Token cipher holding the row's key
+/// * `id` - User auth record ID
+/// * `timeout` - Longest wait for the lock, and longest idle time holding it
+///
+/// # Returns
+/// The locked UserAuth, or None if it was deleted
+///
+/// # Errors
+/// Returns error if the lock isn't granted within `timeout`, the database
+/// query fails or the tokens cannot be decrypted
+pub async fn lock_user_auth(
+    conn: &mut PgConnection,
+    cipher: &TokenCipher,
+    id: Uuid,
+    timeout: std::time::Duration,
+) -> Result<Option<UserAuth>, sqlx::Error> {
+    let timeout = format!("{}ms", timeout.as_millis().max(1));
+    sqlx::query!(
+        r#"
+        SELECT
+            set_config('lock_timeout', $1, true) AS lock_timeout,
+            set_config('idle_in_transaction_session_timeout', $1, true) AS idle_timeout
+        "#,
+        timeout
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let user_auth = sqlx::query_as!(
+        UserAuth,
+        r#"
+        SELECT * FROM user_auth
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    user_auth
+        .map(|user_auth| decrypt_user_auth(cipher, user_auth))
+        .transpose()
+}
+
+/// Flag a user auth record as needing reauthorization
+///
+/// Called when Spotify rejects the refresh token; cleared by the next
+/// `upsert_user_auth` (a successful connect).
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `id` - User auth record ID
+pub async fn mark_needs_reauth<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE user_auth
+        SET
+            needs_reauth = true,
+            needs_reauth_at = COALESCE(needs_reauth_at, NOW()),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Select user auth records for the background token refresher
+///
+/// Picks accounts not flagged `needs_reauth` whose token is due for refresh
+/// and that are either active (a track save since `active_since`) or haven't
+/// been refreshed since `probe_before`. Idle accounts are thereby probed once
+/// per probe interval instead of on every pass.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `refresh_before` - Tokens expiring at or before this are due
+/// * `active_since` - Accounts with track saves since then count as active
+/// * `probe_before` - Idle accounts last refreshed before this are probed
+/// * `limit` - Maximum number of records
+///
+/// # Returns
+/// IDs of the records to refresh, soonest expiry first
+pub async fn list_token_refresh_candidates(
+    pool: &PgPool,
+    refresh_before: DateTime<Utc>,
+    active_since: DateTime<Utc>,
+    probe_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT u.id
+        FROM user_auth u
+        WHERE NOT u.needs_reauth
+            AND u.expires_at <= $1
+            AND (
+                EXISTS (
+                    SELECT 1 FROM track_saves t
+                    WHERE t.slack_workspace_id = u.slack_workspace_id
+                        AND t.slack_user_id = u.slack_user_id
+                        AND t.updated_at >= $2
+                )
+                OR COALESCE(u.last_refreshed_at, u.created_at) <= $3
+            )
+        ORDER BY u.expires_at
+        LIMIT $4
+        "#,
+        refresh_before,
+        active_since,
+        probe_before,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Count Spotify accounts by token state
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `refresh_before` - Tokens expiring at or before this count as expiring
+pub async fn count_token_health(
+    pool: &PgPool,
+    refresh_before: DateTime<Utc>,
+) -> Result<TokenHealthCounts, sqlx::Error> {
+    sqlx::query_as!(
+        TokenHealthCounts,
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE NOT needs_reauth AND expires_at > $1) AS "healthy!",
+            COUNT(*) FILTER (WHERE NOT needs_reauth AND expires_at <= $1) AS "expiring!",
+            COUNT(*) FILTER (WHERE needs_reauth) AS "broken!"
+        FROM user_auth
+        "#,
+        refresh_before
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Spotify profile fields stored alongside a user's tokens
+#[derive(Debug, Clone, Default)]
+pub struct SpotifyProfileParams<'a> {
+    pub spotify_user_id: &'a str,
+    pub display_name: Option<&'a str>,
+    pub country: Option<&'a str>,
+    pub product: Option<&'a str>,
+}
+
+/// Store the Spotify profile for a user auth record
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `id` - User auth record ID
+/// * `profile` - Profile from Spotify's `/v1/me`
+///
+/// # Errors
+/// Returns error if the database update fails
+pub async fn update_spotify_profile(
+    pool: &PgPool,
+    id: Uuid,
+    profile: SpotifyProfileParams<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE user_auth
+        SET
+            spotify_user_id = $1,
+            spotify_display_name = $2,
+            spotify_country = $3,
+            spotify_product = $4,
+            spotify_profile_updated_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $5
+        "#,
+        profile.spotify_user_id,
+        profile.display_name,
+        profile.country,
+        profile.product,
         id
     )
     .execute(pool)
@@ -135,9 +410,240 @@ pub async fn update_tokens(
     Ok(())
 }
 
-/// Check if a save action already exists for a given track in a thread
+/// Count other Slack users linked to the same Spotify account
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `spotify_user_id` - Spotify user ID
+/// * `exclude_id` - User auth record to leave out (the one just linked)
+///
+/// # Returns
+/// Number of other user auth records with this Spotify user ID
+pub async fn count_other_spotify_links(
+    pool: &PgPool,
+    spotify_user_id: &str,
+    exclude_id: Uuid,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM user_auth
+        WHERE spotify_user_id = $1 AND id <> $2
+        "#,
+        spotify_user_id,
+        exclude_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Re-encrypt stored tokens under the cipher's active key
+///
+/// Walks `user_auth` in batches, rewrapping data keys of rows under an older
+/// key and encrypting plaintext rows. Each batch is its own transaction and
+/// skips rows locked by in-flight token refreshes, so this can run while the
+/// app is serving; those rows are written under the active key anyway.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `cipher` - Token cipher holding the active key and every key still in use
+/// * `batch_size` - Rows per transaction
+///
+/// # Returns
+/// Number of rows re-encrypted (0 if the cipher is disabled)
+///
+/// # Errors
+/// Returns error if a row's key is not configured or a database operation fails
+pub async fn reencrypt_user_tokens(
+    pool: &PgPool,
+    cipher: &TokenCipher,
+    batch_size: i64,
+) -> Result<u64, sqlx::Error> {
+    let Some(active_key_id) = cipher.active_key_id() else {
+        return Ok(0);
+    };
+
+    let mut reencrypted = 0;
+    loop {
+        let mut tx = pool.begin().await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, access_token, refresh_token, token_key_id, token_data_key
+            FROM user_auth
+            WHERE token_key_id IS DISTINCT FROM $1
+            ORDER BY id
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+            "#,
+            active_key_id,
+            batch_size
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            let sealed = SealedTokens {
+                key_id: row.token_key_id,
+                data_key: row.token_data_key,
+                access_token: row.access_token,
+                refresh_token: row.refresh_token,
+            };
+            let Some(rotated) = cipher
+                .rotate(&sealed)
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?
+            else {
+                continue;
+            };
+
+            sqlx::query!(
+                r#"
+                UPDATE user_auth
+                SET
+                    access_token = $1,
+                    refresh_token = $2,
+                    token_key_id = $3,
+                    token_data_key = $4
+                WHERE id = $5
+                "#,
+                rotated.access_token,
+                rotated.refresh_token,
+                rotated.key_id,
+                rotated.data_key,
+                row.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            reencrypted += 1;
+        }
+
+        tx.commit().await?;
+    }
+
+    Ok(reencrypted)
+}
+
+fn decrypt_user_auth(
+    cipher: &TokenCipher,
+    mut user_auth: UserAuth,
+) -> Result<UserAuth, sqlx::Error> {
+    let sealed = SealedTokens {
+        key_id: user_auth.token_key_id.clone(),
+        data_key: user_auth.token_data_key.clone(),
+        access_token: std::mem::take(&mut user_auth.access_token),
+        refresh_token: std::mem::take(&mut user_auth.refresh_token),
+    };
+    let (access_token, refresh_token) = cipher
+        .open(&sealed)
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+
+    user_auth.access_token = access_token;
+    user_auth.refresh_token = refresh_token;
+    Ok(user_auth)
+}
+
+/// Check whether a Slack user has linked Spotify, without decrypting tokens
+pub async fn user_auth_exists(
+    pool: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM user_auth
+            WHERE slack_workspace_id = $1 AND slack_user_id = $2
+        ) AS "exists!"
+        "#,
+        workspace_id,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Delete a Slack user's stored Spotify tokens
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `workspace_id` - Slack workspace ID
+/// * `user_id` - Slack user ID
+///
+/// # Returns
+/// true if the user had linked Spotify, false if there was nothing to delete
+pub async fn delete_user_auth(
+    pool: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_auth
+        WHERE slack_workspace_id = $1 AND slack_user_id = $2
+        "#,
+        workspace_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete a Slack user's save history
+///
+/// Removes their track saves and every logged attempt, including attempts not
+/// linked to a track save.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `workspace_id` - Slack workspace ID
+/// * `user_id` - Slack user ID
 ///
-/// Used for idempotency - prevents saving the same track multiple times.
+/// # Returns
+/// Number of track saves deleted
+pub async fn purge_save_history(
+    pool: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM save_action_log
+        WHERE slack_workspace_id = $1 AND slack_user_id = $2
+        "#,
+        workspace_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM track_saves
+        WHERE slack_workspace_id = $1 AND slack_user_id = $2
+        "#,
+        workspace_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Get the most recent save attempt for a given track in a thread
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
@@ -147,7 +653,7 @@ pub async fn update_tokens(
 /// * `track_id` - Spotify track ID
 ///
 /// # Returns
-/// Some(SaveActionLog) if a save action exists, None otherwise
+/// The latest SaveActionLog entry if any attempt exists, None otherwise
 pub async fn get_save_action(
     pool: &PgPool,
     workspace_id: &str,
@@ -169,7 +675,8 @@ pub async fn get_save_action(
             status,
             error_code,
             error_message,
-            created_at
+            created_at,
+            track_save_id
         FROM save_action_log
         WHERE slack_workspace_id = $1
             AND slack_user_id = $2
@@ -189,6 +696,7 @@ pub async fn get_save_action(
 
 /// Parameters for creating a save action log
 pub struct SaveActionParams<'a> {
+    pub track_save_id: Option<Uuid>,
     pub workspace_id: &'a str,
     pub user_id: &'a str,
     pub channel_id: &'a str,
@@ -202,7 +710,8 @@ pub struct SaveActionParams<'a> {
 
 /// Create a save action log entry
 ///
-/// Records an attempt to save a track, whether successful or failed.
+/// Appends an attempt to save a track to the history, whether it was saved,
+/// already saved or failed. Entries are never updated.
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
@@ -226,9 +735,10 @@ pub async fn create_save_action(
             spotify_track_id,
             status,
             error_code,
-            error_message
+            error_message,
+            track_save_id
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         RETURNING
             id,
             slack_workspace_id,
@@ -240,7 +750,8 @@ pub async fn create_save_action(
             status,
             error_code,
             error_message,
-            created_at
+            created_at,
+            track_save_id
         "#,
         params.workspace_id,
         params.user_id,
@@ -250,40 +761,1101 @@ pub async fn create_save_action(
         params.track_id,
         params.status,
         params.error_code,
-        params.error_message
+        params.error_message,
+        params.track_save_id
     )
     .fetch_one(pool)
     .await
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[sqlx::test]
-    async fn test_get_user_auth_not_found(pool: PgPool) -> sqlx::Result<()> {
-        let result = get_user_auth(&pool, "T123", "U456").await?;
-        assert!(result.is_none());
-        Ok(())
-    }
-
-    #[sqlx::test]
-    async fn test_upsert_user_auth_insert(pool: PgPool) -> sqlx::Result<()> {
-        let expires_at = Utc::now() + chrono::Duration::hours(1);
-
-        let user = upsert_user_auth(
-            &pool,
-            "T123",
-            "U456",
-            Some("spotify123".to_string()),
-            "access_token_value",
-            "refresh_token_value",
-            expires_at,
-        )
-        .await?;
-
-        assert_eq!(user.slack_workspace_id, "T123");
-        assert_eq!(user.slack_user_id, "U456");
+/// List the attempt history for a track save, oldest first
+pub async fn list_save_attempts(
+    pool: &PgPool,
+    track_save_id: Uuid,
+) -> Result<Vec<SaveActionLog>, sqlx::Error> {
+    sqlx::query_as!(
+        SaveActionLog,
+        r#"
+        SELECT
+            id,
+            slack_workspace_id,
+            slack_user_id,
+            channel_id,
+            thread_ts,
+            mention_ts,
+            spotify_track_id,
+            status,
+            error_code,
+            error_message,
+            created_at,
+            track_save_id
+        FROM save_action_log
+        WHERE track_save_id = $1
+        ORDER BY created_at, id
+        "#,
+        track_save_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Identifies a track save: one track, for one user, in one thread
+pub struct TrackSaveKey<'a> {
+    pub workspace_id: &'a str,
+    pub user_id: &'a str,
+    pub channel_id: &'a str,
+    pub thread_ts: &'a str,
+    pub track_id: &'a str,
+}
+
+/// Get the save state for a track in a thread
+///
+/// # Returns
+/// Some(TrackSave) if the track has been attempted before, None otherwise
+pub async fn get_track_save(
+    pool: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+    thread_ts: &str,
+    track_id: &str,
+) -> Result<Option<TrackSave>, sqlx::Error> {
+    sqlx::query_as!(
+        TrackSave,
+        r#"
+        SELECT * FROM track_saves
+        WHERE slack_workspace_id = $1
+            AND slack_user_id = $2
+            AND thread_ts = $3
+            AND spotify_track_id = $4
+        "#,
+        workspace_id,
+        user_id,
+        thread_ts,
+        track_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Start a save attempt, creating or updating the track's save state
+///
+/// Upserts the state row: a new or previously failed save moves to `pending`
+/// and its attempt count is incremented; a save that already succeeded is
+/// returned unchanged with status `saved`, so the caller can skip the API call.
+///
+/// # Returns
+/// The TrackSave row after the upsert
+pub async fn begin_track_save(
+    pool: &PgPool,
+    key: TrackSaveKey<'_>,
+) -> Result<TrackSave, sqlx::Error> {
+    sqlx::query_as!(
+        TrackSave,
+        r#"
+        INSERT INTO track_saves (
+            slack_workspace_id,
+            slack_user_id,
+            channel_id,
+            thread_ts,
+            spotify_track_id,
+            status,
+            attempt_count
+        )
+        VALUES ($1, $2, $3, $4, $5, 'pending', 1)
+        ON CONFLICT (slack_workspace_id, slack_user_id, thread_ts, spotify_track_id)
+        DO UPDATE SET
+            status = CASE
+                WHEN track_saves.status = 'saved' THEN 'saved'
+                ELSE 'pending'
+            END,
+            attempt_count = CASE
+                WHEN track_saves.status = 'saved' THEN track_saves.attempt_count
+                ELSE track_saves.attempt_count + 1
+            END,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+        key.workspace_id,
+        key.user_id,
+        key.channel_id,
+        key.thread_ts,
+        key.track_id
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Record the outcome of a save attempt on the track's save state
+///
+/// A save that already succeeded stays `saved`: a late failure from a
+/// concurrent attempt (a duplicate mention or a re-claimed job) leaves the row
+/// unchanged, since the track is in the library either way.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `id` - Track save ID
+/// * `status` - Outcome (`saved` or `failed`)
+/// * `error_code` - Error code for failures
+/// * `error_message` - Error message for failures
+///
+/// # Returns
+/// The TrackSave row after recording the outcome
+pub async fn finish_track_save(
+    pool: &PgPool,
+    id: Uuid,
+    status: &str,
+    error_code: Option<&str>,
+    error_message: Option<&str>,
+) -> Result<TrackSave, sqlx::Error> {
+    sqlx::query_as!(
+        TrackSave,
+        r#"
+        UPDATE track_saves
+        SET
+            status = CASE WHEN status = 'saved' THEN status ELSE $2 END,
+            last_error_code = CASE
+                WHEN status = 'saved' THEN last_error_code
+                ELSE COALESCE($3, last_error_code)
+            END,
+            last_error_message = CASE
+                WHEN status = 'saved' THEN last_error_message
+                ELSE COALESCE($4, last_error_message)
+            END,
+            saved_at = CASE
+                WHEN status <> 'saved' AND $2 = 'saved' THEN NOW()
+                ELSE saved_at
+            END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+        id,
+        status,
+        error_code,
+        error_message
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Record that a successful save was reported to the user
+///
+/// Until then, a retried job that finds the save `saved` reports it as saved
+/// rather than already saved.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `id` - Track save ID
+pub async fn mark_track_save_reported(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE track_saves
+        SET reported_at = COALESCE(reported_at, NOW()),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Enqueue a background job
+///
+/// Accepts any executor so the insert can join a caller's transaction.
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `kind` - Job kind (e.g., "process_mention")
+/// * `payload` - JSON payload for the job handler
+/// * `max_attempts` - Attempts before the job is moved to the dead-letter state
+/// * `run_at` - Earliest time the job may run
+///
+/// # Returns
+/// The created Job record
+///
+/// # Errors
+/// Returns error if database insert fails
+pub async fn enqueue_job<'e>(
+    executor: impl PgExecutor<'e>,
+    kind: &str,
+    payload: &serde_json::Value,
+    max_attempts: i32,
+    run_at: DateTime<Utc>,
+) -> Result<Job, sqlx::Error> {
+    sqlx::query_as!(
+        Job,
+        r#"
+        INSERT INTO jobs (kind, payload, max_attempts, run_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+        kind,
+        payload,
+        max_attempts,
+        run_at
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Claim the next runnable job
+///
+/// Picks the oldest pending job whose `run_at` has passed, or a running job whose
+/// visibility timeout (`locked_until`) has expired, using `FOR UPDATE SKIP LOCKED`
+/// so concurrent workers never claim the same job. The claimed job is marked
+/// running, locked for `visibility_timeout_secs` and its attempt count incremented.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `visibility_timeout_secs` - How long the job stays locked to this worker
+///
+/// # Returns
+/// Some(Job) if a job was claimed, None if the queue is empty
+pub async fn claim_next_job(
+    pool: &PgPool,
+    visibility_timeout_secs: f64,
+) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as!(
+        Job,
+        r#"
+        UPDATE jobs
+        SET
+            status = 'running',
+            attempts = attempts + 1,
+            locked_until = NOW() + make_interval(secs => $1),
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE (status = 'pending' AND run_at <= NOW())
+                OR (status = 'running' AND locked_until < NOW())
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#,
+        visibility_timeout_secs
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Mark a claimed job as completed
+///
+/// Only applies while the job is still held by the caller's claim, identified
+/// by the attempt number it was claimed with. A worker that overran its
+/// visibility timeout must not finish a job another worker has re-claimed.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `id` - Job ID
+/// * `attempts` - Attempt count of the caller's claim
+///
+/// # Returns
+/// true if the job was completed, false if the claim was lost
+///
+/// # Errors
+/// Returns error if database update fails
+pub async fn complete_job(pool: &PgPool, id: Uuid, attempts: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET
+            status = 'completed',
+            locked_until = NULL,
+            completed_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        "#,
+        id,
+        attempts
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record a failed job attempt
+///
+/// Reschedules the job for `retry_at`, or moves it to the dead-letter state
+/// (`dead`) once it has used all of its attempts. Like `complete_job`, only
+/// applies while the caller's claim is still current.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `id` - Job ID
+/// * `attempts` - Attempt count of the caller's claim
+/// * `error` - Error message from the failed attempt
+/// * `retry_at` - When to run the job again if attempts remain
+///
+/// # Returns
+/// The updated Job record, or None if the claim was lost
+pub async fn fail_job(
+    pool: &PgPool,
+    id: Uuid,
+    attempts: i32,
+    error: &str,
+    retry_at: DateTime<Utc>,
+) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as!(
+        Job,
+        r#"
+        UPDATE jobs
+        SET
+            status = CASE WHEN attempts >= max_attempts THEN 'dead' ELSE 'pending' END,
+            run_at = $4,
+            locked_until = NULL,
+            last_error = $3,
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        RETURNING *
+        "#,
+        id,
+        attempts,
+        error,
+        retry_at
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Move a claimed job straight to the dead-letter state
+///
+/// Used for jobs that can never succeed (e.g., unknown kind or bad payload).
+/// Only applies while the caller's claim is still current.
+///
+/// # Returns
+/// true if the job was dead-lettered, false if the claim was lost
+pub async fn dead_letter_job(
+    pool: &PgPool,
+    id: Uuid,
+    attempts: i32,
+    error: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET
+            status = 'dead',
+            locked_until = NULL,
+            last_error = $3,
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        "#,
+        id,
+        attempts,
+        error
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete finished jobs
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `completed_before` - Completed jobs last updated before this are deleted
+/// * `dead_before` - Dead jobs last updated before this are deleted
+///
+/// # Returns
+/// Number of jobs deleted
+pub async fn delete_finished_jobs_before(
+    pool: &PgPool,
+    completed_before: DateTime<Utc>,
+    dead_before: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM jobs
+        WHERE (status = 'completed' AND updated_at < $1)
+            OR (status = 'dead' AND updated_at < $2)
+        "#,
+        completed_before,
+        dead_before
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Get a job by ID
+pub async fn get_job(pool: &PgPool, id: Uuid) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as!(Job, "SELECT * FROM jobs WHERE id = $1", id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// List jobs, newest first
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `status` - Only return jobs in this status (all statuses if None)
+/// * `limit` - Maximum number of jobs to return
+pub async fn list_jobs(
+    pool: &PgPool,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Job>, sqlx::Error> {
+    sqlx::query_as!(
+        Job,
+        r#"
+        SELECT * FROM jobs
+        WHERE $1::TEXT IS NULL OR status = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        status,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Requeue a dead job for immediate retry with a fresh attempt budget
+///
+/// # Returns
+/// Some(Job) if the job was dead and has been requeued, None otherwise
+pub async fn retry_dead_job(pool: &PgPool, id: Uuid) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as!(
+        Job,
+        r#"
+        UPDATE jobs
+        SET
+            status = 'pending',
+            attempts = 0,
+            run_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'dead'
+        RETURNING *
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Record a Slack event delivery, deduplicating on `event_id`
+///
+/// Inserts the event on first delivery. On a repeat delivery (a Slack retry or a
+/// replay), bumps its retry count instead. Accepts any executor so the check can
+/// share a transaction with the job enqueue.
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `event_id` - Slack event ID (e.g., "Ev123ABC")
+/// * `team_id` - Slack workspace ID the event belongs to
+///
+/// # Returns
+/// true if this is the first delivery of the event, false if it was already seen
+pub async fn record_slack_event<'e>(
+    executor: impl PgExecutor<'e>,
+    event_id: &str,
+    team_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO processed_slack_events (event_id, team_id)
+        VALUES ($1, $2)
+        ON CONFLICT (event_id)
+        DO UPDATE SET
+            retry_count = processed_slack_events.retry_count + 1,
+            last_seen_at = NOW()
+        RETURNING (xmax = 0) AS "inserted!"
+        "#,
+        event_id,
+        team_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.inserted)
+}
+
+/// Delete processed Slack event records first seen before `cutoff`
+///
+/// # Returns
+/// Number of records deleted
+pub async fn delete_processed_slack_events_before(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM processed_slack_events WHERE first_seen_at < $1",
+        cutoff
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Store a pending OAuth state
+pub async fn insert_oauth_state(
+    pool: &PgPool,
+    state: &OAuthStateRecord,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO oauth_states (
+            token,
+            slack_workspace_id,
+            slack_user_id,
+            created_at,
+            pkce_verifier,
+            slack_team_id,
+            slack_enterprise_id,
+            link_signed
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        state.token,
+        state.slack_workspace_id,
+        state.slack_user_id,
+        state.created_at,
+        state.pkce_verifier,
+        state.slack_team_id,
+        state.slack_enterprise_id,
+        state.link_signed
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Atomically remove and return a pending OAuth state
+///
+/// # Returns
+/// Some(OAuthStateRecord) if the token existed, None otherwise. Expiry is
+/// left to the caller, so an expired token is still consumed.
+pub async fn consume_oauth_state(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<OAuthStateRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        OAuthStateRecord,
+        "DELETE FROM oauth_states WHERE token = $1 RETURNING *",
+        token
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Delete a user's oldest pending OAuth states, keeping the newest `keep`
+///
+/// # Returns
+/// Number of states deleted
+pub async fn trim_oauth_states_for_user(
+    pool: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+    keep: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM oauth_states
+        WHERE token IN (
+            SELECT token FROM oauth_states
+            WHERE slack_workspace_id = $1 AND slack_user_id = $2
+            ORDER BY created_at DESC
+            OFFSET $3
+        )
+        "#,
+        workspace_id,
+        user_id,
+        keep
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Count OAuth states created after `since`
+pub async fn count_oauth_states_since(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM oauth_states WHERE created_at > $1"#,
+        since
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Delete OAuth states created before `cutoff`
+///
+/// # Returns
+/// Number of states deleted
+pub async fn delete_oauth_states_before(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM oauth_states WHERE created_at < $1", cutoff)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Column label authenticated with encrypted bot tokens
+const BOT_TOKEN_LABEL: &str = "bot_token";
+
+/// Parameters for storing a Slack app installation
+pub struct InstallationParams<'a> {
+    /// Workspace ID, or the enterprise ID for an org-wide install
+    pub team_id: &'a str,
+    pub team_name: Option<&'a str>,
+    /// Enterprise Grid org, if the workspace belongs to one
+    pub enterprise_id: Option<&'a str>,
+    pub is_enterprise_install: bool,
+    pub app_id: Option<&'a str>,
+    pub bot_user_id: &'a str,
+    pub bot_token: &'a str,
+    pub scopes: &'a str,
+    pub installed_by_user_id: Option<&'a str>,
+}
+
+/// Insert or update a workspace's Slack app installation
+///
+/// Reinstalling (e.g., to grant new scopes) replaces the stored bot token. The
+/// token is encrypted under the cipher's active key.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `cipher` - Token cipher used to encrypt the bot token
+/// * `params` - Workspace, bot identity and token from `oauth.v2.access`
+///
+/// # Returns
+/// The stored installation, with the decrypted bot token
+///
+/// # Errors
+/// Returns error if encryption or the database operation fails
+pub async fn upsert_installation(
+    pool: &PgPool,
+    cipher: &TokenCipher,
+    params: InstallationParams<'_>,
+) -> Result<Installation, sqlx::Error> {
+    let sealed = cipher
+        .seal_secret(params.bot_token, BOT_TOKEN_LABEL)
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    let installation = sqlx::query_as!(
+        Installation,
+        r#"
+        INSERT INTO installations (
+            team_id,
+            team_name,
+            app_id,
+            bot_user_id,
+            bot_token,
+            token_key_id,
+            token_data_key,
+            scopes,
+            installed_by_user_id,
+            enterprise_id,
+            is_enterprise_install
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (team_id)
+        DO UPDATE SET
+            team_name = EXCLUDED.team_name,
+            enterprise_id = EXCLUDED.enterprise_id,
+            is_enterprise_install = EXCLUDED.is_enterprise_install,
+            app_id = EXCLUDED.app_id,
+            bot_user_id = EXCLUDED.bot_user_id,
+            bot_token = EXCLUDED.bot_token,
+            token_key_id = EXCLUDED.token_key_id,
+            token_data_key = EXCLUDED.token_data_key,
+            scopes = EXCLUDED.scopes,
+            installed_by_user_id = EXCLUDED.installed_by_user_id
+        RETURNING *
+        "#,
+        params.team_id,
+        params.team_name,
+        params.app_id,
+        params.bot_user_id,
+        sealed.value,
+        sealed.key_id,
+        sealed.data_key,
+        params.scopes,
+        params.installed_by_user_id,
+        params.enterprise_id,
+        params.is_enterprise_install
+    )
+    .fetch_one(pool)
+    .await?;
+
+    decrypt_installation(cipher, installation)
+}
+
+/// Get the Slack app installation serving a workspace
+///
+/// Prefers the workspace's own installation, then an org-wide install for its
+/// Enterprise Grid org. Another workspace's installation in the same org is
+/// never used: its bot can't act in this workspace.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `cipher` - Token cipher holding the row's key
+/// * `team_id` - Slack workspace ID (an enterprise ID matches only the org-wide
+///   install)
+/// * `enterprise_id` - Enterprise Grid org of the workspace, if any
+///
+/// # Returns
+/// The installation with its decrypted bot token, or None if the app wasn't
+/// installed through the OAuth flow
+///
+/// # Errors
+/// Returns error if database query fails or the token cannot be decrypted
+pub async fn get_installation(
+    pool: &PgPool,
+    cipher: &TokenCipher,
+    team_id: &str,
+    enterprise_id: Option<&str>,
+) -> Result<Option<Installation>, sqlx::Error> {
+    let installation = sqlx::query_as!(
+        Installation,
+        r#"
+        SELECT * FROM installations
+        WHERE team_id = $1
+            OR (is_enterprise_install AND team_id = $2)
+        ORDER BY team_id = $1 DESC, is_enterprise_install DESC, updated_at DESC
+        LIMIT 1
+        "#,
+        team_id,
+        enterprise_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    installation
+        .map(|installation| decrypt_installation(cipher, installation))
+        .transpose()
+}
+
+/// Re-encrypt stored bot tokens under the cipher's active key
+///
+/// The installation counterpart of `reencrypt_user_tokens`. There is one row
+/// per workspace, so all rows are rewritten in a single transaction.
+///
+/// # Returns
+/// Number of installations re-encrypted (0 if the cipher is disabled)
+///
+/// # Errors
+/// Returns error if a row's key is not configured or a database operation fails
+pub async fn reencrypt_installation_tokens(
+    pool: &PgPool,
+    cipher: &TokenCipher,
+) -> Result<u64, sqlx::Error> {
+    let Some(active_key_id) = cipher.active_key_id() else {
+        return Ok(0);
+    };
+
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query_as!(
+        Installation,
+        r#"
+        SELECT * FROM installations
+        WHERE token_key_id IS DISTINCT FROM $1
+        FOR UPDATE
+        "#,
+        active_key_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut reencrypted = 0;
+    for row in rows {
+        let id = row.id;
+        let installation = decrypt_installation(cipher, row)?;
+        let sealed = cipher
+            .seal_secret(&installation.bot_token, BOT_TOKEN_LABEL)
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE installations
+            SET bot_token = $1, token_key_id = $2, token_data_key = $3
+            WHERE id = $4
+            "#,
+            sealed.value,
+            sealed.key_id,
+            sealed.data_key,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        reencrypted += 1;
+    }
+
+    tx.commit().await?;
+    Ok(reencrypted)
+}
+
+/// Delete a workspace's Slack app installation
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `team_id` - Slack workspace ID
+///
+/// # Returns
+/// true if the workspace had an installation
+pub async fn delete_installation<'e>(
+    executor: impl PgExecutor<'e>,
+    team_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM installations WHERE team_id = $1", team_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Move a Grid workspace's Spotify links and save history to the enterprise key
+///
+/// Rows written before Grid users were keyed by enterprise ID are stored
+/// under the workspace ID. This moves them once per workspace; later calls
+/// for the same workspace do nothing.
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `team_id` - Slack workspace ID
+/// * `enterprise_id` - Enterprise Grid org of the workspace
+///
+/// # Returns
+/// true if this call moved the workspace's rows
+pub async fn rekey_grid_workspace<'e>(
+    executor: impl PgExecutor<'e>,
+    team_id: &str,
+    enterprise_id: &str,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT rekey_grid_workspace($1, $2) AS "rekeyed!""#,
+        team_id,
+        enterprise_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Enterprise Grid org a workspace's rows were moved to, if any
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `team_id` - Slack workspace ID
+pub async fn get_grid_enterprise(
+    pool: &PgPool,
+    team_id: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT enterprise_id FROM grid_workspaces WHERE team_id = $1",
+        team_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Whether data stored under an identity key is still in use since `since`
+///
+/// True if the workspace has an installation again, or for an enterprise ID
+/// if any installation in the Grid org remains, or if tracks were saved under
+/// the key after `since`. Used to cancel a scheduled purge; workspaces on the
+/// `SLACK_BOT_TOKEN` fallback have no installation row, so activity is the
+/// only signal for them.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `workspace_id` - Identity key: Slack workspace ID or enterprise ID
+/// * `since` - When the app was uninstalled
+pub async fn workspace_active_since(
+    pool: &PgPool,
+    workspace_id: &str,
+    since: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let active = sqlx::query_scalar!(
+        r#"
+        SELECT
+            EXISTS (
+                SELECT 1 FROM installations
+                WHERE team_id = $1 OR enterprise_id = $1
+            )
+            OR EXISTS (
+                SELECT 1 FROM track_saves
+                WHERE slack_workspace_id = $1 AND updated_at > $2
+            ) AS "active!"
+        "#,
+        workspace_id,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(active)
+}
+
+/// Delete all Spotify tokens and save history stored under an identity key
+///
+/// Runs in one transaction, so a failed purge leaves the data untouched.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `workspace_id` - Identity key: Slack workspace ID or enterprise ID
+///
+/// # Returns
+/// Number of rows deleted per table
+pub async fn purge_workspace_data(
+    pool: &PgPool,
+    workspace_id: &str,
+) -> Result<WorkspacePurgeCounts, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let save_actions = sqlx::query!(
+        "DELETE FROM save_action_log WHERE slack_workspace_id = $1",
+        workspace_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let track_saves = sqlx::query!(
+        "DELETE FROM track_saves WHERE slack_workspace_id = $1",
+        workspace_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let user_auth = sqlx::query!(
+        "DELETE FROM user_auth WHERE slack_workspace_id = $1",
+        workspace_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(WorkspacePurgeCounts {
+        user_auth,
+        track_saves,
+        save_actions,
+    })
+}
+
+/// Record an entry in the audit trail
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `action` - What happened (e.g., "app_uninstalled")
+/// * `workspace_id` - Slack workspace the action concerns
+/// * `details` - Action-specific details
+pub async fn insert_audit_log<'e>(
+    executor: impl PgExecutor<'e>,
+    action: &str,
+    workspace_id: &str,
+    details: &serde_json::Value,
+) -> Result<AuditLogEntry, sqlx::Error> {
+    sqlx::query_as!(
+        AuditLogEntry,
+        r#"
+        INSERT INTO audit_log (action, workspace_id, details)
+        VALUES ($1, $2, $3)
+        RETURNING *
+        "#,
+        action,
+        workspace_id,
+        details
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// List audit trail entries, newest first
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `workspace_id` - Only entries for this workspace, if set
+/// * `limit` - Maximum number of entries
+pub async fn list_audit_log(
+    pool: &PgPool,
+    workspace_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        AuditLogEntry,
+        r#"
+        SELECT * FROM audit_log
+        WHERE $1::TEXT IS NULL OR workspace_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        workspace_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+fn decrypt_installation(
+    cipher: &TokenCipher,
+    mut installation: Installation,
+) -> Result<Installation, sqlx::Error> {
+    let sealed = SealedSecret {
+        key_id: installation.token_key_id.clone(),
+        data_key: installation.token_data_key.clone(),
+        value: std::mem::take(&mut installation.bot_token),
+    };
+    installation.bot_token = cipher
+        .open_secret(&sealed, BOT_TOKEN_LABEL)
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+    Ok(installation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_cipher;
+
+    #[sqlx::test]
+    async fn test_get_user_auth_not_found(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let result = get_user_auth(&pool, &cipher, "T123", "U456").await?;
+        assert!(result.is_none());
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_upsert_user_auth_insert(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let expires_at = Utc::now() + chrono::Duration::hours(1);
+
+        let user = upsert_user_auth(
+            &pool,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify123",
+                    ..Default::default()
+                }),
+                access_token: "access_token_value",
+                refresh_token: "refresh_token_value",
+                expires_at,
+            },
+        )
+        .await?;
+
+        assert_eq!(user.slack_workspace_id, "T123");
+        assert_eq!(user.slack_user_id, "U456");
         assert_eq!(user.spotify_user_id, Some("spotify123".to_string()));
         assert_eq!(user.access_token, "access_token_value");
         assert_eq!(user.refresh_token, "refresh_token_value");
@@ -294,17 +1866,24 @@ mod tests {
 
     #[sqlx::test]
     async fn test_upsert_user_auth_update(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
         let expires_at = Utc::now() + chrono::Duration::hours(1);
 
         // Insert initial record
         let user1 = upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify123".to_string()),
-            "old_access_token",
-            "old_refresh_token",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify123",
+                    ..Default::default()
+                }),
+                access_token: "old_access_token",
+                refresh_token: "old_refresh_token",
+                expires_at,
+            },
         )
         .await?;
 
@@ -312,12 +1891,18 @@ mod tests {
         let new_expires_at = Utc::now() + chrono::Duration::hours(2);
         let user2 = upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify123".to_string()),
-            "new_access_token",
-            "new_refresh_token",
-            new_expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify123",
+                    ..Default::default()
+                }),
+                access_token: "new_access_token",
+                refresh_token: "new_refresh_token",
+                expires_at: new_expires_at,
+            },
         )
         .await?;
 
@@ -336,22 +1921,29 @@ mod tests {
 
     #[sqlx::test]
     async fn test_get_user_auth_found(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
         let expires_at = Utc::now() + chrono::Duration::hours(1);
 
         // Insert a user
         upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify123".to_string()),
-            "access_token",
-            "refresh_token",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify123",
+                    ..Default::default()
+                }),
+                access_token: "access_token",
+                refresh_token: "refresh_token",
+                expires_at,
+            },
         )
         .await?;
 
         // Retrieve the user
-        let result = get_user_auth(&pool, "T123", "U456").await?;
+        let result = get_user_auth(&pool, &cipher, "T123", "U456").await?;
         assert!(result.is_some());
 
         let user = result.unwrap();
@@ -364,26 +1956,43 @@ mod tests {
 
     #[sqlx::test]
     async fn test_update_tokens(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
         let expires_at = Utc::now() + chrono::Duration::hours(1);
 
         // Insert a user
         let user = upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify123".to_string()),
-            "old_access",
-            "old_refresh",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify123",
+                    ..Default::default()
+                }),
+                access_token: "old_access",
+                refresh_token: "old_refresh",
+                expires_at,
+            },
         )
         .await?;
 
         // Update tokens
         let new_expires_at = Utc::now() + chrono::Duration::hours(2);
-        update_tokens(&pool, user.id, "new_access", "new_refresh", new_expires_at).await?;
+        update_tokens(
+            &pool,
+            &cipher,
+            user.id,
+            "new_access",
+            "new_refresh",
+            new_expires_at,
+        )
+        .await?;
 
         // Fetch and verify
-        let updated_user = get_user_auth(&pool, "T123", "U456").await?.unwrap();
+        let updated_user = get_user_auth(&pool, &cipher, "T123", "U456")
+            .await?
+            .unwrap();
         assert_eq!(updated_user.access_token, "new_access");
         assert_eq!(updated_user.refresh_token, "new_refresh");
         assert!(updated_user.updated_at > user.updated_at);
@@ -391,41 +2000,1058 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_tokens_encrypted_at_rest(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let expires_at = Utc::now() + chrono::Duration::hours(1);
+
+        let user = upsert_user_auth(
+            &pool,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: None,
+                access_token: "secret_access",
+                refresh_token: "secret_refresh",
+                expires_at,
+            },
+        )
+        .await?;
+        assert_eq!(user.access_token, "secret_access");
+        assert_eq!(user.token_key_id.as_deref(), Some("k1"));
+
+        let raw = sqlx::query!(
+            "SELECT access_token, refresh_token, token_data_key FROM user_auth WHERE id = $1",
+            user.id
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_ne!(raw.access_token, "secret_access");
+        assert_ne!(raw.refresh_token, "secret_refresh");
+        assert!(raw.token_data_key.is_some());
+
+        // Reading without the key fails instead of returning ciphertext
+        let result = get_user_auth(&pool, &test_cipher(&["other"]), "T123", "U456").await;
+        assert!(matches!(result, Err(sqlx::Error::Decode(_))));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_reencrypt_user_tokens(pool: PgPool) -> sqlx::Result<()> {
+        let expires_at = Utc::now() + chrono::Duration::hours(1);
+
+        // One plaintext row from before encryption, one under the old key
+        upsert_user_auth(
+            &pool,
+            &TokenCipher::default(),
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U_PLAIN",
+                profile: None,
+                access_token: "plain_access",
+                refresh_token: "plain_refresh",
+                expires_at,
+            },
+        )
+        .await?;
+        upsert_user_auth(
+            &pool,
+            &test_cipher(&["k1"]),
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U_OLD",
+                profile: None,
+                access_token: "old_access",
+                refresh_token: "old_refresh",
+                expires_at,
+            },
+        )
+        .await?;
+
+        // k2 is active; k1 is still configured for reading
+        let cipher = test_cipher(&["k2", "k1"]);
+        assert_eq!(reencrypt_user_tokens(&pool, &cipher, 1).await?, 2);
+        assert_eq!(reencrypt_user_tokens(&pool, &cipher, 1).await?, 0);
+
+        let plain = get_user_auth(&pool, &cipher, "T123", "U_PLAIN")
+            .await?
+            .unwrap();
+        assert_eq!(plain.token_key_id.as_deref(), Some("k2"));
+        assert_eq!(plain.access_token, "plain_access");
+
+        let old = get_user_auth(&pool, &cipher, "T123", "U_OLD")
+            .await?
+            .unwrap();
+        assert_eq!(old.token_key_id.as_deref(), Some("k2"));
+        assert_eq!(old.refresh_token, "old_refresh");
+
+        // The old key can be retired
+        let rotated = test_cipher(&["k2"]);
+        let old = get_user_auth(&pool, &rotated, "T123", "U_OLD")
+            .await?
+            .unwrap();
+        assert_eq!(old.access_token, "old_access");
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn test_unique_constraint(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
         let expires_at = Utc::now() + chrono::Duration::hours(1);
 
         // Insert first user
         upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify123".to_string()),
-            "access1",
-            "refresh1",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify123",
+                    ..Default::default()
+                }),
+                access_token: "access1",
+                refresh_token: "refresh1",
+                expires_at,
+            },
         )
         .await?;
 
         // Upsert same workspace/user should update, not error
         let result = upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify456".to_string()),
-            "access2",
-            "refresh2",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify456",
+                    ..Default::default()
+                }),
+                access_token: "access2",
+                refresh_token: "refresh2",
+                expires_at,
+            },
         )
         .await;
 
         assert!(result.is_ok());
 
         // Verify only one record exists
-        let user = get_user_auth(&pool, "T123", "U456").await?.unwrap();
+        let user = get_user_auth(&pool, &cipher, "T123", "U456")
+            .await?
+            .unwrap();
         assert_eq!(user.spotify_user_id, Some("spotify456".to_string()));
         assert_eq!(user.access_token, "access2");
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn test_lock_user_auth_times_out(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let user = upsert_user_auth(
+            &pool,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: None,
+                access_token: "access",
+                refresh_token: "refresh",
+                expires_at: Utc::now(),
+            },
+        )
+        .await?;
+        let hold = std::time::Duration::from_millis(500);
+        let wait = std::time::Duration::from_millis(100);
+
+        let mut holder = pool.begin().await?;
+        assert!(
+            lock_user_auth(&mut holder, &cipher, user.id, hold)
+                .await?
+                .is_some()
+        );
+
+        // A second refresh gives up instead of waiting out the holder
+        let mut waiter = pool.begin().await?;
+        let err = lock_user_auth(&mut waiter, &cipher, user.id, wait)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_database_error().and_then(|e| e.code()).as_deref(),
+            Some("55P03")
+        );
+        waiter.rollback().await?;
+
+        // A holder left idle loses its session, releasing the lock
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(holder.commit().await.is_err());
+        let mut next = pool.begin().await?;
+        assert!(
+            lock_user_auth(&mut next, &cipher, user.id, wait)
+                .await?
+                .is_some()
+        );
+        next.commit().await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_enqueue_and_claim_job(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({ "hello": "world" });
+        let job = enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+        assert_eq!(job.status, "pending");
+        assert_eq!(job.attempts, 0);
+
+        let claimed = claim_next_job(&pool, 60.0).await?.unwrap();
+        assert_eq!(claimed.id, job.id);
+        assert_eq!(claimed.status, "running");
+        assert_eq!(claimed.attempts, 1);
+        assert_eq!(claimed.payload, payload);
+        assert!(claimed.locked_until.unwrap() > Utc::now());
+
+        // Locked job is not claimable again
+        assert!(claim_next_job(&pool, 60.0).await?.is_none());
+
+        assert!(complete_job(&pool, job.id, claimed.attempts).await?);
+        let completed = get_job(&pool, job.id).await?.unwrap();
+        assert_eq!(completed.status, "completed");
+        assert!(completed.completed_at.is_some());
+        assert!(completed.locked_until.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_claim_skips_future_jobs(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        enqueue_job(
+            &pool,
+            "test_job",
+            &payload,
+            3,
+            Utc::now() + chrono::Duration::minutes(5),
+        )
+        .await?;
+
+        assert!(claim_next_job(&pool, 60.0).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_concurrent_claims_get_distinct_jobs(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        for _ in 0..5 {
+            enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+        }
+
+        let mut claims = tokio::task::JoinSet::new();
+        for _ in 0..5 {
+            let pool = pool.clone();
+            claims.spawn(async move { claim_next_job(&pool, 60.0).await });
+        }
+        let mut ids: Vec<Uuid> = claims
+            .join_all()
+            .await
+            .into_iter()
+            .map(|claim| claim.unwrap().unwrap().id)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 5);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_fail_job_retries_then_dead_letters(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        let job = enqueue_job(&pool, "test_job", &payload, 2, Utc::now()).await?;
+
+        // First failure: rescheduled
+        let claimed = claim_next_job(&pool, 60.0).await?.unwrap();
+        let failed = fail_job(&pool, job.id, claimed.attempts, "boom", Utc::now())
+            .await?
+            .unwrap();
+        assert_eq!(failed.status, "pending");
+        assert_eq!(failed.last_error.as_deref(), Some("boom"));
+        assert!(failed.locked_until.is_none());
+
+        // Second failure: out of attempts
+        let claimed = claim_next_job(&pool, 60.0).await?.unwrap();
+        let dead = fail_job(&pool, job.id, claimed.attempts, "boom again", Utc::now())
+            .await?
+            .unwrap();
+        assert_eq!(dead.status, "dead");
+        assert_eq!(dead.attempts, 2);
+        assert!(claim_next_job(&pool, 60.0).await?.is_none());
+
+        // Admin retry gives it a fresh budget
+        let retried = retry_dead_job(&pool, job.id).await?.unwrap();
+        assert_eq!(retried.status, "pending");
+        assert_eq!(retried.attempts, 0);
+        assert!(retry_dead_job(&pool, job.id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_expired_lock_is_reclaimed(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        let job = enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+
+        // Claim with a visibility timeout that has already passed
+        claim_next_job(&pool, -1.0).await?.unwrap();
+
+        let reclaimed = claim_next_job(&pool, 60.0).await?.unwrap();
+        assert_eq!(reclaimed.id, job.id);
+        assert_eq!(reclaimed.attempts, 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_stale_claim_cannot_finish_reclaimed_job(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        let job = enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+
+        // The first worker overruns its lock and the job is re-claimed
+        let stale = claim_next_job(&pool, -1.0).await?.unwrap();
+        let current = claim_next_job(&pool, 60.0).await?.unwrap();
+
+        assert!(!complete_job(&pool, job.id, stale.attempts).await?);
+        assert!(
+            fail_job(&pool, job.id, stale.attempts, "late", Utc::now())
+                .await?
+                .is_none()
+        );
+        assert!(!dead_letter_job(&pool, job.id, stale.attempts, "late").await?);
+        let running = get_job(&pool, job.id).await?.unwrap();
+        assert_eq!(running.status, "running");
+        assert!(running.last_error.is_none());
+
+        // The current claim still finishes it, once
+        assert!(complete_job(&pool, job.id, current.attempts).await?);
+        assert!(!complete_job(&pool, job.id, current.attempts).await?);
+        assert_eq!(get_job(&pool, job.id).await?.unwrap().status, "completed");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_delete_finished_jobs_before(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            let job = enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+            ids.push(job.id);
+        }
+        let (completed, dead, recent, pending) = (ids[0], ids[1], ids[2], ids[3]);
+        for _ in 0..3 {
+            let claimed = claim_next_job(&pool, 60.0).await?.unwrap();
+            if claimed.id == dead {
+                dead_letter_job(&pool, claimed.id, claimed.attempts, "boom").await?;
+            } else {
+                complete_job(&pool, claimed.id, claimed.attempts).await?;
+            }
+        }
+        // Backdate past the updated_at trigger
+        sqlx::query("ALTER TABLE jobs DISABLE TRIGGER update_jobs_updated_at")
+            .execute(&pool)
+            .await?;
+        sqlx::query("UPDATE jobs SET updated_at = NOW() - INTERVAL '10 days' WHERE id <> $1")
+            .bind(recent)
+            .execute(&pool)
+            .await?;
+
+        // Completed jobs go after a day, dead ones after a month
+        let deleted = delete_finished_jobs_before(
+            &pool,
+            Utc::now() - chrono::Duration::days(1),
+            Utc::now() - chrono::Duration::days(30),
+        )
+        .await?;
+        assert_eq!(deleted, 1);
+        assert!(get_job(&pool, completed).await?.is_none());
+        assert!(get_job(&pool, dead).await?.is_some());
+        assert!(get_job(&pool, recent).await?.is_some());
+        assert!(get_job(&pool, pending).await?.is_some());
+
+        let deleted = delete_finished_jobs_before(&pool, Utc::now(), Utc::now()).await?;
+        assert_eq!(deleted, 2);
+        assert!(get_job(&pool, pending).await?.is_some());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_list_jobs_by_status(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        let first = enqueue_job(&pool, "test_job", &payload, 1, Utc::now()).await?;
+        enqueue_job(&pool, "test_job", &payload, 1, Utc::now()).await?;
+        let claimed = claim_next_job(&pool, 60.0).await?.unwrap();
+        assert_eq!(claimed.id, first.id);
+        dead_letter_job(&pool, first.id, claimed.attempts, "bad payload").await?;
+
+        assert_eq!(list_jobs(&pool, None, 10).await?.len(), 2);
+        let dead = list_jobs(&pool, Some("dead"), 10).await?;
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].id, first.id);
+        assert_eq!(list_jobs(&pool, Some("pending"), 10).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_record_slack_event_dedup(pool: PgPool) -> sqlx::Result<()> {
+        assert!(record_slack_event(&pool, "Ev001", "T123").await?);
+        assert!(!record_slack_event(&pool, "Ev001", "T123").await?);
+        assert!(!record_slack_event(&pool, "Ev001", "T123").await?);
+        assert!(record_slack_event(&pool, "Ev002", "T123").await?);
+
+        let retry_count: i32 = sqlx::query_scalar(
+            "SELECT retry_count FROM processed_slack_events WHERE event_id = 'Ev001'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(retry_count, 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_delete_processed_slack_events_before(pool: PgPool) -> sqlx::Result<()> {
+        record_slack_event(&pool, "Ev_OLD", "T123").await?;
+        sqlx::query(
+            "UPDATE processed_slack_events SET first_seen_at = NOW() - INTERVAL '2 days' WHERE event_id = 'Ev_OLD'",
+        )
+        .execute(&pool)
+        .await?;
+        record_slack_event(&pool, "Ev_NEW", "T123").await?;
+
+        let deleted =
+            delete_processed_slack_events_before(&pool, Utc::now() - chrono::Duration::days(1))
+                .await?;
+        assert_eq!(deleted, 1);
+
+        // The purged event is treated as new again
+        assert!(record_slack_event(&pool, "Ev_OLD", "T123").await?);
+        assert!(!record_slack_event(&pool, "Ev_NEW", "T123").await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_track_save_retry_after_failure(pool: PgPool) -> sqlx::Result<()> {
+        let key = || TrackSaveKey {
+            workspace_id: "T123",
+            user_id: "U456",
+            channel_id: "C789",
+            thread_ts: "1234567890.000000",
+            track_id: "track123",
+        };
+
+        // First attempt fails
+        let save = begin_track_save(&pool, key()).await?;
+        assert_eq!(save.status, "pending");
+        assert_eq!(save.attempt_count, 1);
+        let failed =
+            finish_track_save(&pool, save.id, "failed", Some("spotify_error"), Some("503")).await?;
+        assert_eq!(failed.status, "failed");
+        assert_eq!(failed.last_error_code.as_deref(), Some("spotify_error"));
+
+        // Retry is allowed and counted on the same row
+        let retry = begin_track_save(&pool, key()).await?;
+        assert_eq!(retry.id, save.id);
+        assert_eq!(retry.status, "pending");
+        assert_eq!(retry.attempt_count, 2);
+        let saved = finish_track_save(&pool, retry.id, "saved", None, None).await?;
+        assert_eq!(saved.status, "saved");
+        assert!(saved.saved_at.is_some());
+
+        // Once saved, further attempts see the saved state unchanged
+        let again = begin_track_save(&pool, key()).await?;
+        assert_eq!(again.status, "saved");
+        assert_eq!(again.attempt_count, 2);
+
+        let state = get_track_save(&pool, "T123", "U456", "1234567890.000000", "track123")
+            .await?
+            .unwrap();
+        assert_eq!(state.id, save.id);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_late_failure_does_not_overwrite_saved(pool: PgPool) -> sqlx::Result<()> {
+        let key = || TrackSaveKey {
+            workspace_id: "T123",
+            user_id: "U456",
+            channel_id: "C789",
+            thread_ts: "1234567890.000000",
+            track_id: "track123",
+        };
+
+        // Two attempts start before either finishes
+        let first = begin_track_save(&pool, key()).await?;
+        let second = begin_track_save(&pool, key()).await?;
+        assert_eq!(first.id, second.id);
+
+        let (saved, failed) = tokio::join!(
+            finish_track_save(&pool, first.id, "saved", None, None),
+            finish_track_save(
+                &pool,
+                second.id,
+                "failed",
+                Some("spotify_error"),
+                Some("503")
+            ),
+        );
+        saved?;
+        failed?;
+
+        // Whichever finished last, the track stays saved
+        let state = get_track_save(&pool, "T123", "U456", "1234567890.000000", "track123")
+            .await?
+            .unwrap();
+        assert_eq!(state.status, "saved");
+        assert!(state.saved_at.is_some());
+
+        // A failure reported after the save is ignored as well
+        let late =
+            finish_track_save(&pool, first.id, "failed", Some("auth_error"), Some("401")).await?;
+        assert_eq!(late.status, "saved");
+        assert_eq!(late.saved_at, state.saved_at);
+        assert_eq!(late.last_error_code, state.last_error_code);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_save_attempt_history_is_append_only(pool: PgPool) -> sqlx::Result<()> {
+        let save = begin_track_save(
+            &pool,
+            TrackSaveKey {
+                workspace_id: "T123",
+                user_id: "U456",
+                channel_id: "C789",
+                thread_ts: "1234567890.000000",
+                track_id: "track123",
+            },
+        )
+        .await?;
+
+        for (mention_ts, status) in [
+            ("1234567890.000001", "failed"),
+            ("1234567890.000002", "saved"),
+            ("1234567890.000003", "already_saved"),
+        ] {
+            create_save_action(
+                &pool,
+                SaveActionParams {
+                    track_save_id: Some(save.id),
+                    workspace_id: "T123",
+                    user_id: "U456",
+                    channel_id: "C789",
+                    thread_ts: "1234567890.000000",
+                    mention_ts,
+                    track_id: "track123",
+                    status,
+                    error_code: None,
+                    error_message: None,
+                },
+            )
+            .await?;
+        }
+
+        let attempts = list_save_attempts(&pool, save.id).await?;
+        let statuses: Vec<&str> = attempts.iter().map(|a| a.status.as_str()).collect();
+        assert_eq!(statuses, vec!["failed", "saved", "already_saved"]);
+
+        let latest = get_save_action(&pool, "T123", "U456", "1234567890.000000", "track123")
+            .await?
+            .unwrap();
+        assert_eq!(latest.status, "already_saved");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_disconnect_deletes_tokens_and_history(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        for user_id in ["U456", "U789"] {
+            upsert_user_auth(
+                &pool,
+                &cipher,
+                UserAuthParams {
+                    workspace_id: "T123",
+                    user_id,
+                    profile: None,
+                    access_token: "access",
+                    refresh_token: "refresh",
+                    expires_at: Utc::now() + chrono::Duration::hours(1),
+                },
+            )
+            .await?;
+
+            let save = begin_track_save(
+                &pool,
+                TrackSaveKey {
+                    workspace_id: "T123",
+                    user_id,
+                    channel_id: "C789",
+                    thread_ts: "1234567890.000000",
+                    track_id: "track123",
+                },
+            )
+            .await?;
+            create_save_action(
+                &pool,
+                SaveActionParams {
+                    track_save_id: Some(save.id),
+                    workspace_id: "T123",
+                    user_id,
+                    channel_id: "C789",
+                    thread_ts: "1234567890.000000",
+                    mention_ts: "1234567890.000001",
+                    track_id: "track123",
+                    status: "saved",
+                    error_code: None,
+                    error_message: None,
+                },
+            )
+            .await?;
+        }
+
+        assert!(user_auth_exists(&pool, "T123", "U456").await?);
+        assert!(delete_user_auth(&pool, "T123", "U456").await?);
+        assert!(!user_auth_exists(&pool, "T123", "U456").await?);
+        assert!(!delete_user_auth(&pool, "T123", "U456").await?);
+
+        assert_eq!(purge_save_history(&pool, "T123", "U456").await?, 1);
+        assert!(
+            get_save_action(&pool, "T123", "U456", "1234567890.000000", "track123")
+                .await?
+                .is_none()
+        );
+
+        // Other users are untouched
+        assert!(user_auth_exists(&pool, "T123", "U789").await?);
+        assert!(
+            get_track_save(&pool, "T123", "U789", "1234567890.000000", "track123")
+                .await?
+                .is_some()
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_spotify_profile_and_shared_accounts(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let mut ids = Vec::new();
+        for user_id in ["U1", "U2"] {
+            let user = upsert_user_auth(
+                &pool,
+                &cipher,
+                UserAuthParams {
+                    workspace_id: "T123",
+                    user_id,
+                    profile: None,
+                    access_token: "access",
+                    refresh_token: "refresh",
+                    expires_at: Utc::now() + chrono::Duration::hours(1),
+                },
+            )
+            .await?;
+            ids.push(user.id);
+        }
+
+        update_spotify_profile(
+            &pool,
+            ids[0],
+            SpotifyProfileParams {
+                spotify_user_id: "spotify123",
+                display_name: Some("Listener"),
+                country: Some("SE"),
+                product: Some("premium"),
+            },
+        )
+        .await?;
+
+        let user = get_user_auth(&pool, &cipher, "T123", "U1").await?.unwrap();
+        assert_eq!(user.spotify_user_id.as_deref(), Some("spotify123"));
+        assert_eq!(user.spotify_display_name.as_deref(), Some("Listener"));
+        assert_eq!(user.spotify_country.as_deref(), Some("SE"));
+        assert_eq!(user.spotify_product.as_deref(), Some("premium"));
+        assert!(user.spotify_profile_updated_at.is_some());
+
+        assert_eq!(
+            count_other_spotify_links(&pool, "spotify123", ids[0]).await?,
+            0
+        );
+
+        update_spotify_profile(
+            &pool,
+            ids[1],
+            SpotifyProfileParams {
+                spotify_user_id: "spotify123",
+                display_name: None,
+                country: None,
+                product: None,
+            },
+        )
+        .await?;
+        assert_eq!(
+            count_other_spotify_links(&pool, "spotify123", ids[0]).await?,
+            1
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_needs_reauth_cleared_on_reconnect(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let params = || UserAuthParams {
+            workspace_id: "T123",
+            user_id: "U456",
+            profile: None,
+            access_token: "access",
+            refresh_token: "refresh",
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+        };
+
+        let user = upsert_user_auth(&pool, &cipher, params()).await?;
+        assert!(!user.needs_reauth);
+
+        mark_needs_reauth(&pool, user.id).await?;
+        let flagged = get_user_auth(&pool, &cipher, "T123", "U456")
+            .await?
+            .unwrap();
+        assert!(flagged.needs_reauth);
+        let flagged_at = flagged.needs_reauth_at.unwrap();
+
+        // Marking again keeps the original timestamp
+        mark_needs_reauth(&pool, user.id).await?;
+        let again = get_user_auth(&pool, &cipher, "T123", "U456")
+            .await?
+            .unwrap();
+        assert_eq!(again.needs_reauth_at, Some(flagged_at));
+
+        let reconnected = upsert_user_auth(&pool, &cipher, params()).await?;
+        assert!(!reconnected.needs_reauth);
+        assert!(reconnected.needs_reauth_at.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_token_refresh_candidates_and_health(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        let now = Utc::now();
+        let mut ids = std::collections::HashMap::new();
+        for (user_id, expires_in) in [
+            ("U_ACTIVE", chrono::Duration::minutes(1)),
+            ("U_IDLE", -chrono::Duration::hours(3)),
+            ("U_PROBE", -chrono::Duration::hours(3)),
+            ("U_FRESH", chrono::Duration::hours(1)),
+            ("U_BROKEN", -chrono::Duration::hours(3)),
+        ] {
+            let user = upsert_user_auth(
+                &pool,
+                &cipher,
+                UserAuthParams {
+                    workspace_id: "T123",
+                    user_id,
+                    profile: None,
+                    access_token: "access",
+                    refresh_token: "refresh",
+                    expires_at: now + expires_in,
+                },
+            )
+            .await?;
+            ids.insert(user_id, user.id);
+        }
+
+        // U_ACTIVE saved a track recently; U_PROBE was last refreshed long ago
+        begin_track_save(
+            &pool,
+            TrackSaveKey {
+                workspace_id: "T123",
+                user_id: "U_ACTIVE",
+                channel_id: "C789",
+                thread_ts: "1234567890.000000",
+                track_id: "track123",
+            },
+        )
+        .await?;
+        sqlx::query!(
+            "UPDATE user_auth SET created_at = NOW() - INTERVAL '2 days' WHERE id = $1",
+            ids["U_PROBE"]
+        )
+        .execute(&pool)
+        .await?;
+        mark_needs_reauth(&pool, ids["U_BROKEN"]).await?;
+
+        let candidates = list_token_refresh_candidates(
+            &pool,
+            now + chrono::Duration::minutes(5),
+            now - chrono::Duration::hours(24),
+            now - chrono::Duration::hours(24),
+            10,
+        )
+        .await?;
+        assert_eq!(candidates, vec![ids["U_PROBE"], ids["U_ACTIVE"]]);
+
+        let health = count_token_health(&pool, now + chrono::Duration::minutes(5)).await?;
+        assert_eq!(
+            health,
+            TokenHealthCounts {
+                healthy: 1,
+                expiring: 3,
+                broken: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_installation_upsert_and_reencrypt(pool: PgPool) -> sqlx::Result<()> {
+        let old_cipher = test_cipher(&["k1"]);
+        let params = |bot_token| InstallationParams {
+            team_id: "T123",
+            team_name: Some("Example"),
+            app_id: Some("A123"),
+            bot_user_id: "UBOT",
+            bot_token,
+            scopes: "app_mentions:read,chat:write",
+            installed_by_user_id: Some("U456"),
+            enterprise_id: None,
+            is_enterprise_install: false,
+        };
+
+        let first = upsert_installation(&pool, &old_cipher, params("xoxb-first")).await?;
+        assert_eq!(first.bot_token, "xoxb-first");
+        assert_eq!(first.token_key_id.as_deref(), Some("k1"));
+
+        // Reinstalling replaces the token in place
+        let second = upsert_installation(&pool, &old_cipher, params("xoxb-second")).await?;
+        assert_eq!(second.id, first.id);
+
+        let stored: String = sqlx::query_scalar!(
+            "SELECT bot_token FROM installations WHERE id = $1",
+            first.id
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_ne!(stored, "xoxb-second");
+
+        let new_cipher = test_cipher(&["k2", "k1"]);
+        assert_eq!(reencrypt_installation_tokens(&pool, &new_cipher).await?, 1);
+        assert_eq!(reencrypt_installation_tokens(&pool, &new_cipher).await?, 0);
+
+        let installation = get_installation(&pool, &test_cipher(&["k2"]), "T123", None)
+            .await?
+            .unwrap();
+        assert_eq!(installation.bot_token, "xoxb-second");
+        assert_eq!(installation.bot_user_id, "UBOT");
+        assert!(
+            get_installation(&pool, &new_cipher, "T999", None)
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_purge_workspace_data(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        for (workspace_id, user_id) in [("T_GONE", "U1"), ("T_GONE", "U2"), ("T_KEEP", "U1")] {
+            upsert_user_auth(
+                &pool,
+                &cipher,
+                UserAuthParams {
+                    workspace_id,
+                    user_id,
+                    profile: None,
+                    access_token: "access",
+                    refresh_token: "refresh",
+                    expires_at: Utc::now(),
+                },
+            )
+            .await?;
+            let key = TrackSaveKey {
+                workspace_id,
+                user_id,
+                channel_id: "C789",
+                thread_ts: "1234567890.000000",
+                track_id: "track123",
+            };
+            let track_save = begin_track_save(&pool, key).await?;
+            create_save_action(
+                &pool,
+                SaveActionParams {
+                    track_save_id: Some(track_save.id),
+                    workspace_id,
+                    user_id,
+                    channel_id: "C789",
+                    thread_ts: "1234567890.000000",
+                    mention_ts: "1234567890.123456",
+                    track_id: "track123",
+                    status: "saved",
+                    error_code: None,
+                    error_message: None,
+                },
+            )
+            .await?;
+        }
+        let before = Utc::now();
+        assert!(
+            workspace_active_since(&pool, "T_GONE", before - chrono::Duration::hours(1)).await?
+        );
+        assert!(!workspace_active_since(&pool, "T_GONE", before).await?);
+
+        // An enterprise key stays in use while any workspace of the org is installed
+        assert!(!workspace_active_since(&pool, "E_ORG", before).await?);
+        upsert_installation(
+            &pool,
+            &cipher,
+            InstallationParams {
+                team_id: "T_SIBLING",
+                team_name: None,
+                app_id: None,
+                bot_user_id: "UBOT",
+                bot_token: "xoxb-sibling",
+                scopes: "chat:write",
+                installed_by_user_id: None,
+                enterprise_id: Some("E_ORG"),
+                is_enterprise_install: false,
+            },
+        )
+        .await?;
+        assert!(workspace_active_since(&pool, "E_ORG", before).await?);
+        assert!(!workspace_active_since(&pool, "T_GONE", before).await?);
+
+        let counts = purge_workspace_data(&pool, "T_GONE").await?;
+        assert_eq!(
+            counts,
+            WorkspacePurgeCounts {
+                user_auth: 2,
+                track_saves: 2,
+                save_actions: 2,
+            }
+        );
+        assert!(user_auth_exists(&pool, "T_KEEP", "U1").await?);
+
+        insert_audit_log(
+            &pool,
+            "workspace_data_purged",
+            "T_GONE",
+            &serde_json::json!({ "user_auth": 2 }),
+        )
+        .await?;
+        insert_audit_log(&pool, "app_uninstalled", "T_KEEP", &serde_json::json!({})).await?;
+
+        let entries = list_audit_log(&pool, Some("T_GONE"), 10).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "workspace_data_purged");
+        assert_eq!(entries[0].details["user_auth"], 2);
+        assert_eq!(list_audit_log(&pool, None, 10).await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_rekey_grid_workspace(pool: PgPool) -> sqlx::Result<()> {
+        let cipher = test_cipher(&["k1"]);
+        // U1 only linked under the workspace; U2 relinked under the enterprise
+        for (workspace_id, user_id, access_token) in [
+            ("T_GRID", "U1", "u1-access"),
+            ("T_GRID", "U2", "u2-old-access"),
+            ("E_GRID", "U2", "u2-access"),
+        ] {
+            upsert_user_auth(
+                &pool,
+                &cipher,
+                UserAuthParams {
+                    workspace_id,
+                    user_id,
+                    profile: None,
+                    access_token,
+                    refresh_token: "refresh",
+                    expires_at: Utc::now(),
+                },
+            )
+            .await?;
+        }
+        for (workspace_id, status) in [("T_GRID", "saved"), ("E_GRID", "failed")] {
+            let key = TrackSaveKey {
+                workspace_id,
+                user_id: "U2",
+                channel_id: "C789",
+                thread_ts: "1234567890.000000",
+                track_id: "track123",
+            };
+            let track_save = begin_track_save(&pool, key).await?;
+            finish_track_save(&pool, track_save.id, status, None, None).await?;
+            create_save_action(
+                &pool,
+                SaveActionParams {
+                    track_save_id: Some(track_save.id),
+                    workspace_id,
+                    user_id: "U2",
+                    channel_id: "C789",
+                    thread_ts: "1234567890.000000",
+                    mention_ts: "1234567890.123456",
+                    track_id: "track123",
+                    status,
+                    error_code: None,
+                    error_message: None,
+                },
+            )
+            .await?;
+        }
+
+        assert!(rekey_grid_workspace(&pool, "T_GRID", "E_GRID").await?);
+        assert_eq!(
+            get_grid_enterprise(&pool, "T_GRID").await?.as_deref(),
+            Some("E_GRID")
+        );
+
+        let u1 = get_user_auth(&pool, &cipher, "E_GRID", "U1")
+            .await?
+            .unwrap();
+        assert_eq!(u1.access_token, "u1-access");
+        let u2 = get_user_auth(&pool, &cipher, "E_GRID", "U2")
+            .await?
+            .unwrap();
+        assert_eq!(u2.access_token, "u2-access");
+        assert!(!user_auth_exists(&pool, "T_GRID", "U2").await?);
+
+        // The enterprise row keeps both attempts and the saved state
+        let save = get_track_save(&pool, "E_GRID", "U2", "1234567890.000000", "track123")
+            .await?
+            .unwrap();
+        assert_eq!(save.status, "saved");
+        assert_eq!(list_save_attempts(&pool, save.id).await?.len(), 2);
+        assert!(
+            get_track_save(&pool, "T_GRID", "U2", "1234567890.000000", "track123")
+                .await?
+                .is_none()
+        );
+
+        // Once per workspace
+        assert!(!rekey_grid_workspace(&pool, "T_GRID", "E_GRID").await?);
+
+        Ok(())
+    }
 }
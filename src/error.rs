@@ -21,9 +21,24 @@ pub enum AppError {
     #[error("Spotify API error: {0}")]
     SpotifyApi(String),
 
+    #[error("Spotify access revoked, reauthorization required")]
+    SpotifyReauthRequired,
+
+    #[error("User not authenticated with Spotify")]
+    SpotifyNotConnected,
+
     #[error("Invalid request: {0}")]
     BadRequest(String),
 
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("Slack signature invalid: {0}")]
     SignatureInvalid(String),
 
@@ -34,7 +49,10 @@ pub enum AppError {
     SignatureExpired(String),
 
     #[error("Slack API error: {0}")]
-    SlackApi(String),
+    SlackApi(#[from] crate::slack::client::SlackError),
+
+    #[error("Slack app not installed in workspace {0}")]
+    SlackNotInstalled(String),
 
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
@@ -62,10 +80,36 @@ impl IntoResponse for AppError {
                 tracing::error!("Spotify API error: {}", msg);
                 (StatusCode::BAD_GATEWAY, "Spotify API error")
             }
+            AppError::SpotifyReauthRequired => {
+                tracing::warn!("Spotify reauthorization required");
+                (
+                    StatusCode::FORBIDDEN,
+                    "Spotify access was revoked, please reconnect",
+                )
+            }
+            AppError::SpotifyNotConnected => {
+                tracing::warn!("User not authenticated with Spotify");
+                (
+                    StatusCode::BAD_REQUEST,
+                    "User not authenticated with Spotify",
+                )
+            }
             AppError::BadRequest(msg) => {
                 tracing::warn!("Bad request: {}", msg);
                 (StatusCode::BAD_REQUEST, msg.as_str())
             }
+            AppError::Unauthorized => {
+                tracing::warn!("Unauthorized request");
+                (StatusCode::UNAUTHORIZED, "Unauthorized")
+            }
+            AppError::NotFound(msg) => {
+                tracing::debug!("Not found: {}", msg);
+                (StatusCode::NOT_FOUND, msg.as_str())
+            }
+            AppError::TooManyRequests(msg) => {
+                tracing::warn!("Too many requests: {}", msg);
+                (StatusCode::TOO_MANY_REQUESTS, msg.as_str())
+            }
             AppError::SignatureInvalid(msg) => {
                 tracing::warn!("Invalid Slack signature: {}", msg);
                 (StatusCode::UNAUTHORIZED, "Invalid signature")
@@ -78,10 +122,17 @@ impl IntoResponse for AppError {
                 tracing::warn!("Slack signature expired: {}", msg);
                 (StatusCode::UNAUTHORIZED, "Signature expired")
             }
-            AppError::SlackApi(msg) => {
-                tracing::error!("Slack API error: {}", msg);
+            AppError::SlackApi(err) => {
+                tracing::error!("Slack API error: {}", err);
                 (StatusCode::BAD_GATEWAY, "Slack API error")
             }
+            AppError::SlackNotInstalled(team_id) => {
+                tracing::warn!(team_id = %team_id, "No bot token for Slack workspace");
+                (
+                    StatusCode::NOT_FOUND,
+                    "Slack app is not installed in this workspace",
+                )
+            }
             AppError::Internal(err) => {
                 tracing::error!("Internal error: {:?}", err);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
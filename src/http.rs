@@ -0,0 +1,28 @@
+use crate::config::Config;
+use std::time::Duration;
+
+/// User agent sent with every outbound request to Slack and Spotify
+pub const USER_AGENT: &str = concat!("savethebeat/", env!("CARGO_PKG_VERSION"));
+
+/// Build the shared outbound HTTP client
+///
+/// A single `reqwest::Client` is created at startup and cloned into the Slack and
+/// Spotify clients, so they share one connection pool.
+///
+/// # Arguments
+/// * `config` - Application configuration with timeout settings
+///
+/// # Errors
+/// Returns error if the TLS backend cannot be initialized
+pub fn build_http_client(config: &Config) -> anyhow::Result<reqwest::Client> {
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(config.http_connect_timeout_secs))
+        .timeout(Duration::from_secs(config.http_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(90))
+        // Neither API redirects; refusing redirects keeps bearer tokens on the intended host
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    Ok(client)
+}
@@ -0,0 +1,299 @@
+pub mod retention;
+pub mod worker;
+
+use crate::db::models::Job;
+use crate::db::repository::enqueue_job;
+use crate::error::AppError;
+use crate::slack::commands::SaveButtonClick;
+use crate::slack::events::{LinkSharedEvent, MentionEvent, WorkflowStepEvent};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Background job payloads
+///
+/// Serialized as `{"kind": ..., "payload": ...}`; `kind` is stored in its own
+/// column and `payload` as JSONB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum JobPayload {
+    /// Find and save the Spotify track for an app_mention
+    ProcessMention(MentionEvent),
+    /// Preview the Spotify track links of a link_shared event
+    UnfurlLinks(LinkSharedEvent),
+    /// Save a track for a user who clicked Save on its preview
+    SaveSharedTrack(SaveButtonClick),
+    /// Run the "Save Spotify track" workflow step
+    RunWorkflowStep(WorkflowStepEvent),
+    /// Publish a user's App Home tab
+    PublishAppHome {
+        /// Identity workspace of the user
+        workspace_id: String,
+        /// Workspace whose Home tab it is (None in jobs queued before it was
+        /// recorded)
+        #[serde(default)]
+        team_id: Option<String>,
+        #[serde(default)]
+        enterprise_id: Option<String>,
+        user_id: String,
+    },
+    /// Delete an uninstalled workspace's data once its grace period is over
+    PurgeWorkspace {
+        /// Identity key the data is stored under (enterprise ID for Grid)
+        workspace_id: String,
+        uninstalled_at: DateTime<Utc>,
+    },
+}
+
+impl JobPayload {
+    /// Decode the payload of a stored job
+    ///
+    /// # Errors
+    /// Returns error if the kind is unknown or the payload doesn't match it
+    pub fn from_job(job: &Job) -> Result<Self, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "kind": job.kind,
+            "payload": job.payload,
+        }))
+    }
+
+    /// Split into the `(kind, payload)` pair stored in the jobs table
+    fn to_columns(&self) -> Result<(String, serde_json::Value), serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        let kind = value["kind"].as_str().unwrap_or_default().to_string();
+        let payload = value["payload"].take();
+        Ok((kind, payload))
+    }
+}
+
+/// Handle for enqueueing jobs
+///
+/// Enqueueing wakes a local worker immediately; workers on other instances pick
+/// the job up on their next poll.
+#[derive(Clone, Debug)]
+pub struct JobQueue {
+    db: PgPool,
+    notify: Arc<Notify>,
+    max_attempts: i32,
+}
+
+impl JobQueue {
+    /// Create a new job queue
+    ///
+    /// # Arguments
+    /// * `db` - Database connection pool
+    /// * `max_attempts` - Attempts per job before it is dead-lettered
+    pub fn new(db: PgPool, max_attempts: i32) -> Self {
+        Self {
+            db,
+            notify: Arc::new(Notify::new()),
+            max_attempts,
+        }
+    }
+
+    /// Enqueue a job to run as soon as a worker is free
+    ///
+    /// # Errors
+    /// Returns error if the payload cannot be serialized or the insert fails
+    pub async fn enqueue(&self, payload: &JobPayload) -> Result<Job, AppError> {
+        self.enqueue_at(payload, Utc::now()).await
+    }
+
+    /// Enqueue a job to run no earlier than `run_at`
+    ///
+    /// # Errors
+    /// Returns error if the payload cannot be serialized or the insert fails
+    pub async fn enqueue_at(
+        &self,
+        payload: &JobPayload,
+        run_at: DateTime<Utc>,
+    ) -> Result<Job, AppError> {
+        let (kind, payload) = payload
+            .to_columns()
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode job: {}", e)))?;
+
+        let job = enqueue_job(&self.db, &kind, &payload, self.max_attempts, run_at).await?;
+
+        tracing::info!(job_id = %job.id, kind = %job.kind, "Enqueued job");
+        self.notify.notify_one();
+
+        Ok(job)
+    }
+
+    /// Enqueue a job as part of a caller's transaction
+    ///
+    /// Workers are not woken, since the job isn't visible until the transaction
+    /// commits; call `wake` after committing.
+    ///
+    /// # Errors
+    /// Returns error if the payload cannot be serialized or the insert fails
+    pub async fn enqueue_in(
+        &self,
+        conn: &mut PgConnection,
+        payload: &JobPayload,
+    ) -> Result<Job, AppError> {
+        self.enqueue_in_at(conn, payload, Utc::now()).await
+    }
+
+    /// Enqueue a job to run no earlier than `run_at`, as part of a caller's
+    /// transaction
+    ///
+    /// # Errors
+    /// Returns error if the payload cannot be serialized or the insert fails
+    pub async fn enqueue_in_at(
+        &self,
+        conn: &mut PgConnection,
+        payload: &JobPayload,
+        run_at: DateTime<Utc>,
+    ) -> Result<Job, AppError> {
+        let (kind, payload) = payload
+            .to_columns()
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode job: {}", e)))?;
+
+        let job = enqueue_job(conn, &kind, &payload, self.max_attempts, run_at).await?;
+
+        tracing::info!(job_id = %job.id, kind = %job.kind, "Enqueued job");
+
+        Ok(job)
+    }
+
+    /// Wake an idle local worker to pick up newly committed jobs
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Wait until a job is enqueued in this process
+    pub(crate) async fn notified(&self) {
+        self.notify.notified().await
+    }
+}
+
+/// Delay before retrying a job that failed its `attempts`-th attempt
+///
+/// Exponential backoff starting at `base` and doubling per attempt, capped at one hour.
+pub fn retry_backoff(attempts: i32, base: Duration) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    base.saturating_mul(2u32.pow(exponent))
+        .min(Duration::from_secs(60 * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mention() -> MentionEvent {
+        MentionEvent {
+            workspace_id: "T123".to_string(),
+            user_id: "U456".to_string(),
+            channel_id: "C789".to_string(),
+            thread_ts: "1234567890.000000".to_string(),
+            mention_ts: "1234567890.123456".to_string(),
+            text: "<@UBOT> save".to_string(),
+            team_id: Some("T123".to_string()),
+            enterprise_id: None,
+            user_team_id: None,
+            context_team_id: None,
+            external: false,
+        }
+    }
+
+    #[test]
+    fn test_job_payload_round_trip() {
+        let payload = JobPayload::ProcessMention(mention());
+        let (kind, value) = payload.to_columns().unwrap();
+        assert_eq!(kind, "process_mention");
+        assert_eq!(value["channel_id"], "C789");
+
+        let job = Job {
+            id: uuid::Uuid::new_v4(),
+            kind,
+            payload: value,
+            status: "running".to_string(),
+            attempts: 1,
+            max_attempts: 5,
+            run_at: Utc::now(),
+            locked_until: None,
+            last_error: None,
+            completed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        match JobPayload::from_job(&job).unwrap() {
+            JobPayload::ProcessMention(decoded) => {
+                assert_eq!(decoded.workspace_id, "T123");
+                assert_eq!(decoded.mention_ts, "1234567890.123456");
+            }
+            other => panic!("Unexpected payload: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_publish_app_home_columns() {
+        let payload = JobPayload::PublishAppHome {
+            workspace_id: "E123".to_string(),
+            team_id: Some("T123".to_string()),
+            enterprise_id: Some("E123".to_string()),
+            user_id: "U456".to_string(),
+        };
+        let (kind, value) = payload.to_columns().unwrap();
+        assert_eq!(kind, "publish_app_home");
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "workspace_id": "E123",
+                "team_id": "T123",
+                "enterprise_id": "E123",
+                "user_id": "U456",
+            })
+        );
+
+        // Jobs queued before the team was recorded still decode
+        let queued: JobPayload = serde_json::from_value(serde_json::json!({
+            "kind": "publish_app_home",
+            "payload": { "workspace_id": "T123", "user_id": "U456" },
+        }))
+        .unwrap();
+        assert!(matches!(
+            queued,
+            JobPayload::PublishAppHome {
+                team_id: None,
+                enterprise_id: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_job_payload_unknown_kind() {
+        let job = Job {
+            id: uuid::Uuid::new_v4(),
+            kind: "does_not_exist".to_string(),
+            payload: serde_json::json!({}),
+            status: "running".to_string(),
+            attempts: 1,
+            max_attempts: 5,
+            run_at: Utc::now(),
+            locked_until: None,
+            last_error: None,
+            completed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        assert!(JobPayload::from_job(&job).is_err());
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let base = Duration::from_secs(5);
+        assert_eq!(retry_backoff(1, base), Duration::from_secs(5));
+        assert_eq!(retry_backoff(2, base), Duration::from_secs(10));
+        assert_eq!(retry_backoff(4, base), Duration::from_secs(40));
+        assert_eq!(retry_backoff(30, base), Duration::from_secs(60 * 60));
+        assert_eq!(retry_backoff(0, base), Duration::from_secs(5));
+    }
+}
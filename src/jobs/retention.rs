@@ -0,0 +1,125 @@
+use crate::config::Config;
+use crate::db::repository::{delete_finished_jobs_before, delete_processed_slack_events_before};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// How often the retention sweep runs
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long finished jobs and processed Slack events are kept
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    /// Completed jobs older than this are deleted
+    pub completed_jobs: chrono::Duration,
+    /// Dead jobs older than this are deleted (kept longer for admin retries)
+    pub dead_jobs: chrono::Duration,
+    /// How long to remember a Slack event_id
+    ///
+    /// Slack only retries an event for a few minutes, so records only need to
+    /// live long enough to catch those retries (and manual replays).
+    pub slack_events: chrono::Duration,
+}
+
+impl RetentionConfig {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            completed_jobs: chrono::Duration::days(config.job_retention_days),
+            dead_jobs: chrono::Duration::days(config.job_dead_retention_days),
+            slack_events: chrono::Duration::hours(config.slack_event_ttl_hours),
+        }
+    }
+}
+
+/// Rows deleted by one retention sweep
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionSweep {
+    pub jobs: u64,
+    pub slack_events: u64,
+}
+
+/// Delete finished jobs and processed Slack events past their retention
+///
+/// # Errors
+/// Returns error if a delete fails
+pub async fn sweep(db: &PgPool, config: &RetentionConfig) -> Result<RetentionSweep, sqlx::Error> {
+    let now = Utc::now();
+    let jobs = delete_finished_jobs_before(db, now - config.completed_jobs, now - config.dead_jobs)
+        .await?;
+    let slack_events = delete_processed_slack_events_before(db, now - config.slack_events).await?;
+
+    Ok(RetentionSweep { jobs, slack_events })
+}
+
+/// Spawn a task that runs the retention sweep periodically
+///
+/// # Arguments
+/// * `db` - Database connection pool
+/// * `config` - Retention periods
+pub fn spawn_retention_task(db: PgPool, config: RetentionConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            match sweep(&db, &config).await {
+                Ok(swept) if swept == RetentionSweep::default() => {}
+                Ok(swept) => tracing::info!(
+                    jobs = swept.jobs,
+                    slack_events = swept.slack_events,
+                    "Purged finished jobs and expired processed Slack events"
+                ),
+                Err(e) => tracing::error!(error = ?e, "Retention sweep failed"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository::{
+        claim_next_job, complete_job, enqueue_job, get_job, record_slack_event,
+    };
+
+    #[sqlx::test]
+    async fn test_sweep_deletes_expired_jobs_and_events(pool: PgPool) -> sqlx::Result<()> {
+        let payload = serde_json::json!({});
+        let old = enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+        let claimed = claim_next_job(&pool, 60.0).await?.unwrap();
+        complete_job(&pool, claimed.id, claimed.attempts).await?;
+        let pending = enqueue_job(&pool, "test_job", &payload, 3, Utc::now()).await?;
+        record_slack_event(&pool, "Ev_OLD", "T123").await?;
+        record_slack_event(&pool, "Ev_NEW", "T123").await?;
+
+        // Backdate past the updated_at trigger
+        sqlx::query("ALTER TABLE jobs DISABLE TRIGGER update_jobs_updated_at")
+            .execute(&pool)
+            .await?;
+        sqlx::query("UPDATE jobs SET updated_at = NOW() - INTERVAL '10 days'")
+            .execute(&pool)
+            .await?;
+        sqlx::query(
+            "UPDATE processed_slack_events SET first_seen_at = NOW() - INTERVAL '2 days' WHERE event_id = 'Ev_OLD'",
+        )
+        .execute(&pool)
+        .await?;
+
+        let config = RetentionConfig::from_config(&crate::config::test_config());
+        let swept = sweep(&pool, &config).await?;
+        assert_eq!(
+            swept,
+            RetentionSweep {
+                jobs: 1,
+                slack_events: 1
+            }
+        );
+        assert!(get_job(&pool, old.id).await?.is_none());
+        assert!(get_job(&pool, pending.id).await?.is_some());
+
+        assert_eq!(sweep(&pool, &config).await?, RetentionSweep::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::config::Config;
+use crate::db::models::Job;
+use crate::db::repository::{claim_next_job, complete_job, dead_letter_job, fail_job};
+use crate::error::AppError;
+use crate::jobs::{JobPayload, retry_backoff};
+use crate::slack::routes::{
+    SlackState, process_mention, publish_app_home, run_workflow_step, save_shared_track,
+    unfurl_links,
+};
+use crate::slack::uninstall::purge_workspace;
+use chrono::Utc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Job worker settings
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Number of concurrent worker tasks
+    pub concurrency: usize,
+    /// How long a claimed job stays locked before another worker may take it
+    pub visibility_timeout: Duration,
+    /// How often idle workers poll for jobs enqueued by other instances
+    pub poll_interval: Duration,
+    /// Base delay for exponential retry backoff
+    pub retry_backoff: Duration,
+}
+
+impl WorkerConfig {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            concurrency: config.job_workers,
+            visibility_timeout: Duration::from_secs(config.job_visibility_timeout_secs),
+            poll_interval: Duration::from_millis(config.job_poll_interval_ms),
+            retry_backoff: Duration::from_secs(config.job_retry_backoff_secs),
+        }
+    }
+
+    /// How long a handler may run before it is aborted
+    ///
+    /// Strictly shorter than the visibility timeout, leaving the last tenth of
+    /// it for recording the outcome before the job can be re-claimed.
+    pub fn handler_timeout(&self) -> Duration {
+        self.visibility_timeout - self.visibility_timeout / 10
+    }
+}
+
+/// Spawn background workers that process jobs from the queue
+///
+/// # Arguments
+/// * `state` - Slack state used by job handlers (includes the job queue)
+/// * `config` - Worker settings
+///
+/// # Returns
+/// Join handles for the spawned worker tasks
+pub fn spawn_workers(state: SlackState, config: WorkerConfig) -> Vec<JoinHandle<()>> {
+    tracing::info!(
+        concurrency = config.concurrency,
+        visibility_timeout_secs = config.visibility_timeout.as_secs(),
+        "Starting job workers"
+    );
+
+    (0..config.concurrency)
+        .map(|worker_id| {
+            let state = state.clone();
+            let config = config.clone();
+            tokio::spawn(async move { run_worker(worker_id, state, config).await })
+        })
+        .collect()
+}
+
+/// Claim and run jobs until the task is aborted
+async fn run_worker(worker_id: usize, state: SlackState, config: WorkerConfig) {
+    loop {
+        match claim_next_job(&state.db, config.visibility_timeout.as_secs_f64()).await {
+            Ok(Some(job)) => run_job(worker_id, &state, &config, job).await,
+            Ok(None) => {
+                tokio::select! {
+                    _ = state.jobs.notified() => {}
+                    _ = tokio::time::sleep(config.poll_interval) => {}
+                }
+            }
+            Err(e) => {
+                tracing::error!(worker_id, error = ?e, "Failed to claim job");
+                tokio::time::sleep(config.poll_interval).await;
+            }
+        }
+    }
+}
+
+/// Run a claimed job and record the outcome
+///
+/// The handler runs in its own task so a panic is reported as a failed attempt,
+/// and is aborted before the visibility timeout elapses so the job is never
+/// processed by two workers at once. The outcome is only recorded if the claim
+/// is still current.
+async fn run_job(worker_id: usize, state: &SlackState, config: &WorkerConfig, job: Job) {
+    tracing::info!(
+        worker_id,
+        job_id = %job.id,
+        kind = %job.kind,
+        attempt = job.attempts,
+        "Running job"
+    );
+
+    // A job reclaimed after its final attempt's lock expired has no attempts left
+    if job.attempts > job.max_attempts {
+        tracing::error!(job_id = %job.id, "Job exceeded max attempts, moving to dead letter");
+        let result = dead_letter_job(
+            &state.db,
+            job.id,
+            job.attempts,
+            "Visibility timeout expired on final attempt",
+        )
+        .await;
+        log_dead_letter(&job, result);
+        return;
+    }
+
+    let payload = match JobPayload::from_job(&job) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::error!(job_id = %job.id, error = ?e, "Invalid job payload");
+            let result = dead_letter_job(
+                &state.db,
+                job.id,
+                job.attempts,
+                &format!("Invalid payload: {}", e),
+            )
+            .await;
+            log_dead_letter(&job, result);
+            return;
+        }
+    };
+
+    let handle = tokio::spawn(dispatch(state.clone(), payload));
+    let abort = handle.abort_handle();
+
+    let error = match tokio::time::timeout(config.handler_timeout(), handle).await {
+        Ok(Ok(Ok(()))) => None,
+        Ok(Ok(Err(e))) => Some(e.to_string()),
+        Ok(Err(join_error)) => Some(format!("Job panicked: {}", join_error)),
+        Err(_) => {
+            abort.abort();
+            Some("Job timed out".to_string())
+        }
+    };
+
+    match error {
+        None => {
+            tracing::info!(job_id = %job.id, kind = %job.kind, "Job completed");
+            match complete_job(&state.db, job.id, job.attempts).await {
+                Ok(true) => {}
+                Ok(false) => log_lost_claim(&job),
+                Err(e) => {
+                    tracing::error!(job_id = %job.id, error = ?e, "Failed to mark job completed")
+                }
+            }
+        }
+        Some(error) => {
+            let delay = retry_backoff(job.attempts, config.retry_backoff);
+            let retry_at = Utc::now()
+                + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::hours(1));
+
+            match fail_job(&state.db, job.id, job.attempts, &error, retry_at).await {
+                Ok(Some(failed)) if failed.status == "dead" => tracing::error!(
+                    job_id = %job.id,
+                    kind = %job.kind,
+                    attempts = failed.attempts,
+                    error = %error,
+                    "Job failed permanently, moved to dead letter"
+                ),
+                Ok(Some(failed)) => tracing::warn!(
+                    job_id = %job.id,
+                    kind = %job.kind,
+                    attempt = failed.attempts,
+                    retry_at = %retry_at,
+                    error = %error,
+                    "Job failed, will retry"
+                ),
+                Ok(None) => log_lost_claim(&job),
+                Err(e) => {
+                    tracing::error!(job_id = %job.id, error = ?e, "Failed to record job failure")
+                }
+            }
+        }
+    }
+}
+
+/// Log the outcome of dead-lettering a claimed job
+fn log_dead_letter(job: &Job, result: Result<bool, sqlx::Error>) {
+    match result {
+        Ok(true) => {}
+        Ok(false) => log_lost_claim(job),
+        Err(e) => tracing::error!(job_id = %job.id, error = ?e, "Failed to dead-letter job"),
+    }
+}
+
+/// Log that a job was re-claimed by another worker before this one finished
+fn log_lost_claim(job: &Job) {
+    tracing::warn!(
+        job_id = %job.id,
+        kind = %job.kind,
+        attempt = job.attempts,
+        "Job was re-claimed before its outcome was recorded, leaving it to the new claim"
+    );
+}
+
+/// Route a job payload to its handler
+async fn dispatch(state: SlackState, payload: JobPayload) -> Result<(), AppError> {
+    match payload {
+        JobPayload::ProcessMention(mention) => process_mention(state, mention).await,
+        JobPayload::UnfurlLinks(shared) => unfurl_links(state, shared).await,
+        JobPayload::SaveSharedTrack(click) => save_shared_track(state, click).await,
+        JobPayload::RunWorkflowStep(step) => run_workflow_step(state, step).await,
+        JobPayload::PublishAppHome {
+            workspace_id,
+            team_id,
+            enterprise_id,
+            user_id,
+        } => publish_app_home(state, workspace_id, team_id, enterprise_id, user_id).await,
+        JobPayload::PurgeWorkspace {
+            workspace_id,
+            uninstalled_at,
+        } => purge_workspace(state, workspace_id, uninstalled_at).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handler_timeout_shorter_than_visibility_timeout() {
+        let mut config = WorkerConfig::from_config(&crate::config::test_config());
+        assert_eq!(config.handler_timeout(), Duration::from_secs(108));
+
+        config.visibility_timeout = Duration::from_secs(1);
+        assert_eq!(config.handler_timeout(), Duration::from_millis(900));
+    }
+}
@@ -1,15 +1,19 @@
+pub mod admin;
 pub mod config;
+pub mod crypto;
 pub mod db;
 pub mod error;
+pub mod http;
+pub mod jobs;
+pub mod metrics;
 pub mod routes;
 pub mod slack;
 pub mod spotify;
 pub mod telemetry;
 
 use axum::Router;
-use std::collections::HashMap;
+use sqlx::PgPool;
 use std::net::SocketAddr;
-use std::sync::{Arc, RwLock};
 use tower_http::trace::TraceLayer;
 
 pub async fn run(config: config::Config) -> anyhow::Result<()> {
@@ -19,15 +23,126 @@ pub async fn run(config: config::Config) -> anyhow::Result<()> {
     let db = db::init_pool(&config.database_url).await?;
     tracing::info!("Database connection pool initialized");
 
+    let app = app(&config, db.clone())?;
+
+    // Move tokens left under older keys (or unencrypted) onto the active key
+    let cipher = crypto::TokenCipher::from_config(&config)?;
+    if cipher.is_enabled() {
+        tokio::spawn(async move {
+            match db::repository::reencrypt_user_tokens(&db, &cipher, REENCRYPT_BATCH_SIZE).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "Re-encrypted stored Spotify tokens"),
+                Err(e) => tracing::error!(error = ?e, "Failed to re-encrypt stored Spotify tokens"),
+            }
+            match db::repository::reencrypt_installation_tokens(&db, &cipher).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "Re-encrypted stored Slack bot tokens"),
+                Err(e) => {
+                    tracing::error!(error = ?e, "Failed to re-encrypt stored Slack bot tokens")
+                }
+            }
+        });
+    }
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    tracing::info!("Starting server on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Re-encrypt stored Spotify tokens under the active key, then exit
+///
+/// Backs the `rotate-token-keys` command. Safe to run while the server is up;
+/// the server also runs the same pass in the background on startup.
+///
+/// # Errors
+/// Returns error if no keys are configured, a row's key is missing from
+/// `TOKEN_ENCRYPTION_KEYS`, or a database operation fails
+pub async fn rotate_token_keys(config: config::Config) -> anyhow::Result<()> {
+    telemetry::init_tracing(&config.rust_log);
+
+    let cipher = crypto::TokenCipher::from_config(&config)?;
+    let Some(active_key_id) = cipher.active_key_id() else {
+        anyhow::bail!("TOKEN_ENCRYPTION_KEYS is not set");
+    };
+
+    let db = db::init_pool(&config.database_url).await?;
+    let count = db::repository::reencrypt_user_tokens(&db, &cipher, REENCRYPT_BATCH_SIZE).await?;
+    tracing::info!(count, active_key_id, "Re-encrypted stored Spotify tokens");
+    let count = db::repository::reencrypt_installation_tokens(&db, &cipher).await?;
+    tracing::info!(count, active_key_id, "Re-encrypted stored Slack bot tokens");
+
+    Ok(())
+}
+
+const REENCRYPT_BATCH_SIZE: i64 = 100;
+
+/// Build the application router
+///
+/// Wires HTTP clients, OAuth client and route state from `config` on top of an
+/// existing database pool, and starts the background job workers. Used by `run`
+/// and by the end-to-end tests, which serve it against fake Slack and Spotify
+/// servers.
+///
+/// # Errors
+/// Returns error if the outbound HTTP client cannot be built, the token
+/// encryption keys are invalid, the OAuth state store, external user policy or
+/// Slack transport is unknown, or Socket Mode is missing its app token
+pub fn app(config: &config::Config, db: PgPool) -> anyhow::Result<Router> {
+    // Initialize shared outbound HTTP clients
+    let http_client = http::build_http_client(config)?;
+    let slack_client =
+        slack::client::SlackClient::new(http_client.clone(), &config.slack_api_base_url);
+    let spotify_client =
+        spotify::client::SpotifyClient::new(http_client, &config.spotify_api_base_url)
+            .with_token_refresh_timeout(std::time::Duration::from_secs(
+                config.spotify_token_refresh_timeout_secs,
+            ));
+    tracing::info!("Initialized HTTP clients");
+
+    // Initialize token encryption
+    let cipher = crypto::TokenCipher::from_config(config)?;
+    if !cipher.is_enabled() {
+        tracing::warn!("TOKEN_ENCRYPTION_KEYS not set, storing Spotify tokens unencrypted");
+    }
+
     // Initialize Spotify OAuth client
-    let oauth_client = spotify::oauth::build_oauth_client(&config);
+    let oauth_client = spotify::oauth::build_oauth_client(config);
     tracing::info!("Initialized Spotify OAuth client");
 
     // Initialize Spotify OAuth state
+    let state_store = spotify::state_store::build_state_store(config, db.clone())?;
+    spotify::state_store::spawn_cleanup_task(state_store.clone());
+    tracing::info!(store = %config.oauth_state_store, "Initialized OAuth state store");
+
+    // Refresh tokens ahead of use and probe idle accounts for revoked grants
+    if config.token_refresh_interval_secs > 0 {
+        spotify::refresher::spawn_refresh_task(spotify::refresher::TokenRefresher {
+            db: db.clone(),
+            cipher: cipher.clone(),
+            oauth_client: oauth_client.clone(),
+            spotify_client: spotify_client.clone(),
+            config: spotify::refresher::RefresherConfig::from_config(config),
+        });
+        tracing::info!("Started background token refresher");
+    }
+
+    // Bot tokens per workspace, falling back to SLACK_BOT_TOKEN
+    let bot_tokens =
+        slack::tokens::BotTokens::new(db.clone(), cipher.clone(), config.slack_bot_token.clone());
+
     let spotify_state = spotify::routes::SpotifyState {
         oauth_client: oauth_client.clone(),
-        state_store: Arc::new(RwLock::new(HashMap::new())),
+        spotify_client: spotify_client.clone(),
+        state_store,
         db: db.clone(),
+        cipher: cipher.clone(),
+        connect_links: spotify::connect_link::ConnectLinks::from_config(config),
+        slack_client: slack_client.clone(),
+        bot_tokens: bot_tokens.clone(),
     };
 
     // Build application router
@@ -35,31 +150,91 @@ pub async fn run(config: config::Config) -> anyhow::Result<()> {
 
     let mut app = Router::new().merge(routes::routes()).merge(spotify_router);
 
-    // Add Slack routes if configured
-    if let (Some(signing_secret), Some(bot_token)) =
-        (&config.slack_signing_secret, &config.slack_bot_token)
+    // Add the Slack install flow if configured
+    let install_configured = if let (Some(client_id), Some(client_secret)) =
+        (&config.slack_client_id, &config.slack_client_secret)
     {
+        let install_state = slack::install::SlackInstallState {
+            client_id: client_id.clone(),
+            client_secret: client_secret.clone(),
+            scopes: config.slack_bot_scopes.clone(),
+            authorize_url: config.slack_authorize_url.clone(),
+            base_url: config.base_url.clone(),
+            db: db.clone(),
+            cipher: cipher.clone(),
+            slack_client: slack_client.clone(),
+        };
+        app = app.merge(routes::slack_install_routes().with_state(install_state));
+        tracing::info!("Initialized Slack install flow");
+        true
+    } else {
+        false
+    };
+
+    // Add Slack handling if configured. Events arrive over HTTP (signed with
+    // the signing secret) or Socket Mode (app token); bot tokens come from
+    // installations or SLACK_BOT_TOKEN
+    let socket_mode = slack::socket_mode::SocketModeConfig::from_config(config)?;
+    let has_bot_tokens = install_configured || config.slack_bot_token.is_some();
+    let has_transport = socket_mode.is_some() || config.slack_signing_secret.is_some();
+    if has_bot_tokens && has_transport {
         let slack_state = slack::routes::SlackState {
-            signing_secret: signing_secret.clone(),
-            bot_token: bot_token.clone(),
+            // Only checked by the HTTP routes, which Socket Mode doesn't mount
+            signature_verifier: slack::verification::SignatureVerifier::from_config(config),
+            bot_tokens,
+            connect_links: spotify::connect_link::ConnectLinks::from_config(config),
             db: db.clone(),
+            cipher,
             oauth_client: oauth_client.clone(),
+            slack_client,
+            spotify_client,
+            app_token: spotify::app_token::AppTokenCache::default(),
+            jobs: jobs::JobQueue::new(db.clone(), config.job_max_attempts),
+            uninstall_grace: chrono::Duration::hours(config.slack_uninstall_grace_hours),
+            external_users: slack::identity::ExternalUserPolicy::from_config(config)?,
+            thread_max_messages: config.slack_thread_max_messages.max(1),
+            channel_lookback: slack::routes::ChannelLookback {
+                window: chrono::Duration::hours(config.slack_channel_lookback_hours),
+                max_messages: config.slack_channel_lookback_messages,
+            },
         };
 
-        let slack_router = routes::slack_routes().with_state(slack_state);
-        app = app.merge(slack_router);
-        tracing::info!("Initialized Slack event handler");
-    } else {
-        tracing::warn!("Slack credentials not configured, skipping Slack integration");
-    }
+        jobs::retention::spawn_retention_task(
+            db.clone(),
+            jobs::retention::RetentionConfig::from_config(config),
+        );
 
-    let app = app.layer(TraceLayer::new_for_http());
+        jobs::worker::spawn_workers(
+            slack_state.clone(),
+            jobs::worker::WorkerConfig::from_config(config),
+        );
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
-    tracing::info!("Starting server on {}", addr);
+        if let Some(socket_mode) = socket_mode {
+            tracing::info!(
+                connections = socket_mode.connections,
+                "Initialized Slack Socket Mode client"
+            );
+            slack::socket_mode::spawn_socket_mode(slack_state, socket_mode);
+        } else {
+            let slack_router = routes::slack_routes().with_state(slack_state);
+            app = app.merge(slack_router);
+            tracing::info!("Initialized Slack event handler");
+        }
+    } else {
+        tracing::warn!(
+            "Slack signing secret (or Socket Mode app token) and bot token or install credentials not configured, skipping Slack integration"
+        );
+    }
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    // Add admin routes if configured
+    if let Some(admin_token) = &config.admin_token {
+        let admin_state = admin::routes::AdminState {
+            db: db.clone(),
+            admin_token: admin_token.clone(),
+        };
+        app = app.merge(routes::admin_routes().with_state(admin_state));
+        tracing::info!("Initialized admin routes");
+    }
 
-    Ok(())
+    Ok(app.layer(TraceLayer::new_for_http()))
 }
@@ -3,5 +3,13 @@ use savethebeat::config::Config;
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let config = Config::from_env()?;
-    savethebeat::run(config).await
+
+    match std::env::args().nth(1).as_deref() {
+        None | Some("serve") => savethebeat::run(config).await,
+        Some("rotate-token-keys") => savethebeat::rotate_token_keys(config).await,
+        Some(command) => anyhow::bail!(
+            "Unknown command {:?} (expected serve or rotate-token-keys)",
+            command
+        ),
+    }
 }
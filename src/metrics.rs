@@ -0,0 +1,216 @@
+use crate::db::models::TokenHealthCounts;
+use crate::spotify::refresher::RefreshPass;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+/// Process-wide counters and gauges exposed on `GET /metrics`
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// In-process counters and gauges, rendered in the Prometheus text exposition format
+#[derive(Debug, Default)]
+pub struct Metrics {
+    slack_events_received: AtomicU64,
+    slack_events_duplicate: AtomicU64,
+    slack_event_retries: Mutex<BTreeMap<String, u64>>,
+    token_refreshes_ok: AtomicU64,
+    token_refreshes_revoked: AtomicU64,
+    token_refreshes_failed: AtomicU64,
+    token_accounts: Mutex<Option<TokenHealthCounts>>,
+}
+
+/// Global metrics instance
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    /// Count an event_callback received on /slack/events
+    pub fn slack_event_received(&self) {
+        self.slack_events_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a delivery Slack marked as a retry (`X-Slack-Retry-Num` present)
+    pub fn slack_event_retry(&self, reason: &str) {
+        let mut retries = self
+            .slack_event_retries
+            .lock()
+            .expect("Failed to acquire metrics lock");
+        *retries.entry(reason.to_string()).or_default() += 1;
+    }
+
+    /// Count an event acknowledged without processing because it was already seen
+    pub fn slack_event_duplicate(&self) {
+        self.slack_events_duplicate.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count the outcomes of a background token refresher pass
+    pub fn token_refresh_pass(&self, pass: &RefreshPass) {
+        self.token_refreshes_ok
+            .fetch_add(pass.refreshed, Ordering::Relaxed);
+        self.token_refreshes_revoked
+            .fetch_add(pass.revoked, Ordering::Relaxed);
+        self.token_refreshes_failed
+            .fetch_add(pass.failed, Ordering::Relaxed);
+    }
+
+    /// Record the latest count of accounts by token state
+    pub fn set_token_health(&self, health: &TokenHealthCounts) {
+        *self
+            .token_accounts
+            .lock()
+            .expect("Failed to acquire metrics lock") = Some(*health);
+    }
+
+    /// Render all counters in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_counter(
+            &mut out,
+            "savethebeat_slack_events_received_total",
+            "Slack event_callback deliveries received",
+            self.slack_events_received.load(Ordering::Relaxed),
+        );
+        write_counter(
+            &mut out,
+            "savethebeat_slack_events_duplicate_total",
+            "Slack events acknowledged as duplicates of an already processed event_id",
+            self.slack_events_duplicate.load(Ordering::Relaxed),
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP savethebeat_slack_event_retries_total Slack event deliveries marked as retries, by X-Slack-Retry-Reason"
+        );
+        let _ = writeln!(out, "# TYPE savethebeat_slack_event_retries_total counter");
+        let retries = self
+            .slack_event_retries
+            .lock()
+            .expect("Failed to acquire metrics lock");
+        for (reason, count) in retries.iter() {
+            let _ = writeln!(
+                out,
+                "savethebeat_slack_event_retries_total{{reason=\"{}\"}} {}",
+                escape_label(reason),
+                count
+            );
+        }
+        drop(retries);
+
+        let _ = writeln!(
+            out,
+            "# HELP savethebeat_token_refreshes_total Background Spotify token refreshes, by result"
+        );
+        let _ = writeln!(out, "# TYPE savethebeat_token_refreshes_total counter");
+        for (result, counter) in [
+            ("ok", &self.token_refreshes_ok),
+            ("revoked", &self.token_refreshes_revoked),
+            ("failed", &self.token_refreshes_failed),
+        ] {
+            let _ = writeln!(
+                out,
+                "savethebeat_token_refreshes_total{{result=\"{}\"}} {}",
+                result,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+
+        // Only known after the refresher's first pass
+        let token_accounts = *self
+            .token_accounts
+            .lock()
+            .expect("Failed to acquire metrics lock");
+        if let Some(health) = token_accounts {
+            let _ = writeln!(
+                out,
+                "# HELP savethebeat_token_accounts Linked Spotify accounts, by token state"
+            );
+            let _ = writeln!(out, "# TYPE savethebeat_token_accounts gauge");
+            for (state, count) in [
+                ("healthy", health.healthy),
+                ("expiring", health.expiring),
+                ("broken", health.broken),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "savethebeat_token_accounts{{state=\"{}\"}} {}",
+                    state, count
+                );
+            }
+        }
+
+        out
+    }
+}
+
+fn write_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counters() {
+        let metrics = Metrics::default();
+        metrics.slack_event_received();
+        metrics.slack_event_received();
+        metrics.slack_event_duplicate();
+        metrics.slack_event_retry("http_timeout");
+        metrics.slack_event_retry("http_timeout");
+        metrics.slack_event_retry("http_error");
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("savethebeat_slack_events_received_total 2\n"));
+        assert!(rendered.contains("savethebeat_slack_events_duplicate_total 1\n"));
+        assert!(
+            rendered.contains("savethebeat_slack_event_retries_total{reason=\"http_timeout\"} 2\n")
+        );
+        assert!(
+            rendered.contains("savethebeat_slack_event_retries_total{reason=\"http_error\"} 1\n")
+        );
+    }
+
+    #[test]
+    fn test_render_token_metrics() {
+        let metrics = Metrics::default();
+        assert!(!metrics.render().contains("savethebeat_token_accounts"));
+
+        metrics.token_refresh_pass(&RefreshPass {
+            refreshed: 3,
+            revoked: 1,
+            failed: 0,
+        });
+        metrics.set_token_health(&TokenHealthCounts {
+            healthy: 5,
+            expiring: 2,
+            broken: 1,
+        });
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("savethebeat_token_refreshes_total{result=\"ok\"} 3\n"));
+        assert!(rendered.contains("savethebeat_token_refreshes_total{result=\"revoked\"} 1\n"));
+        assert!(rendered.contains("savethebeat_token_refreshes_total{result=\"failed\"} 0\n"));
+        assert!(rendered.contains("# TYPE savethebeat_token_accounts gauge\n"));
+        assert!(rendered.contains("savethebeat_token_accounts{state=\"healthy\"} 5\n"));
+        assert!(rendered.contains("savethebeat_token_accounts{state=\"broken\"} 1\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
     Json, Router,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 use serde_json::json;
 
@@ -31,10 +31,62 @@ pub fn spotify_routes() -> Router<crate::spotify::routes::SpotifyState> {
 ///
 /// # Routes
 /// - POST /slack/events - Handle Slack event webhooks
+/// - POST /slack/commands - Handle the slash command
+/// - POST /slack/interactions - Handle App Home buttons
 pub fn slack_routes() -> Router<crate::slack::routes::SlackState> {
-    use crate::slack::routes::handle_slack_events;
+    use crate::slack::routes::{handle_interaction, handle_slack_events, handle_slash_command};
 
-    Router::new().route("/slack/events", post(handle_slack_events))
+    Router::new()
+        .route("/slack/events", post(handle_slack_events))
+        .route("/slack/commands", post(handle_slash_command))
+        .route("/slack/interactions", post(handle_interaction))
+}
+
+/// Build Slack app install routes
+///
+/// Requires SlackInstallState to be provided via with_state
+///
+/// # Routes
+/// - GET /slack/install - Start installing the app in a workspace
+/// - GET /slack/oauth/callback - Store the workspace's bot token
+pub fn slack_install_routes() -> Router<crate::slack::install::SlackInstallState> {
+    use crate::slack::install::{install, oauth_callback};
+
+    Router::new()
+        .route("/slack/install", get(install))
+        .route("/slack/oauth/callback", get(oauth_callback))
+}
+
+/// Build admin routes
+///
+/// Requires AdminState to be provided via with_state. All routes require
+/// `Authorization: Bearer <ADMIN_TOKEN>`.
+///
+/// # Routes
+/// - GET /admin/jobs - List background jobs
+/// - GET /admin/jobs/{id} - Get a background job
+/// - POST /admin/jobs/{id}/retry - Requeue a dead-lettered job
+/// - DELETE /admin/users/{workspace_id}/{user_id} - Disconnect a user from Spotify
+/// - GET /admin/tokens/health - Count Spotify accounts by token state
+/// - GET /admin/audit - List audit trail entries
+/// - GET /metrics - Prometheus metrics
+pub fn admin_routes() -> Router<crate::admin::routes::AdminState> {
+    use crate::admin::routes::{
+        disconnect_user_handler, get_job_handler, list_audit_handler, list_jobs_handler,
+        metrics_handler, retry_job_handler, token_health_handler,
+    };
+
+    Router::new()
+        .route("/admin/jobs", get(list_jobs_handler))
+        .route("/admin/jobs/{id}", get(get_job_handler))
+        .route("/admin/jobs/{id}/retry", post(retry_job_handler))
+        .route(
+            "/admin/users/{workspace_id}/{user_id}",
+            delete(disconnect_user_handler),
+        )
+        .route("/admin/tokens/health", get(token_health_handler))
+        .route("/admin/audit", get(list_audit_handler))
+        .route("/metrics", get(metrics_handler))
 }
 
 async fn health() -> Json<serde_json::Value> {
@@ -1,168 +1,783 @@
-use crate::error::AppError;
-use crate::slack::events::{ConversationsRepliesResponse, SlackMessage};
+use crate::slack::events::{ConversationsMessagesResponse, SlackMessage};
+use crate::slack::install::OAuthV2AccessResponse;
+use serde::de::{DeserializeOwned, IgnoredAny};
 
-/// Fetch all messages in a thread
-///
-/// Calls Slack's `conversations.replies` API to get all messages in a thread.
-/// This is used to find Spotify links shared in the conversation.
-///
-/// # Arguments
-/// * `bot_token` - Slack bot token (xoxb-...)
-/// * `channel_id` - Channel ID where the thread exists
-/// * `thread_ts` - Thread timestamp (thread root message timestamp)
-///
-/// # Returns
-/// Vector of messages in the thread, ordered chronologically
+/// Errors from the Slack Web API
+#[derive(Debug, thiserror::Error)]
+pub enum SlackError {
+    /// The request couldn't be sent or the response couldn't be read
+    #[error("Failed to call {method}: {message}")]
+    Transport {
+        method: &'static str,
+        message: String,
+    },
+
+    /// The response isn't what the method returns
+    #[error("Failed to parse {method} response: {message}")]
+    InvalidResponse {
+        method: &'static str,
+        message: String,
+    },
+
+    /// Slack answered `"ok": false` with an error code (e.g., "not_in_channel")
+    #[error("{method} failed: {error}")]
+    Api { method: &'static str, error: String },
+
+    /// Slack answered without a field the app needs
+    #[error("{method} response is missing {field}")]
+    MissingField {
+        method: &'static str,
+        field: &'static str,
+    },
+
+    /// A Socket Mode connection failed
+    #[error("Socket Mode connection failed: {0}")]
+    Socket(String),
+}
+
+impl SlackError {
+    /// Slack's error code, if Slack answered with one
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            SlackError::Api { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Whether the bot can't read the channel
+    ///
+    /// Slack answers `not_in_channel` for public channels the bot hasn't
+    /// joined, and `channel_not_found` for private channels it isn't a member of.
+    pub fn is_channel_inaccessible(&self) -> bool {
+        matches!(self.code(), Some("not_in_channel" | "channel_not_found"))
+    }
+}
+
+/// Largest page requested from `conversations.replies` / `conversations.history`
+/// (Slack's recommended maximum)
+const MESSAGES_PAGE_SIZE: usize = 200;
+
+/// Which part of a thread to fetch with `fetch_thread_messages`
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadQuery<'a> {
+    /// Channel ID where the thread exists
+    pub channel_id: &'a str,
+    /// Thread timestamp (thread root message timestamp)
+    pub thread_ts: &'a str,
+    /// Only fetch messages up to and including this timestamp
+    pub latest: Option<&'a str>,
+    /// Stop after this many messages
+    pub max_messages: usize,
+}
+
+/// Which part of a channel to fetch with `fetch_channel_history`
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryQuery<'a> {
+    /// Channel ID
+    pub channel_id: &'a str,
+    /// Only fetch messages before this timestamp
+    pub latest: &'a str,
+    /// Only fetch messages at or after this timestamp
+    pub oldest: Option<&'a str>,
+    /// Stop after this many messages
+    pub max_messages: usize,
+}
+
+/// Message whose links `unfurl` previews
+#[derive(Debug, Clone, Copy)]
+pub enum UnfurlTarget<'a> {
+    /// A posted message
+    Message { channel: &'a str, ts: &'a str },
+    /// Links in the message composer, from link_shared's `unfurl_id` and `source`
+    Composer { unfurl_id: &'a str, source: &'a str },
+}
+
+/// How a Web API call's arguments are sent
+#[derive(Debug, Clone, Copy)]
+enum Args<'a> {
+    /// Query string of a GET (the `conversations.*` read methods don't take JSON)
+    Query(&'a [(&'a str, &'a str)]),
+    /// JSON body of a POST
+    Json(&'a serde_json::Value),
+    /// POST without arguments
+    Empty,
+}
+
+/// Slack Web API client
 ///
-/// # Errors
-/// - `SlackApi` if the API call fails or returns an error
-pub async fn fetch_thread_messages(
-    bot_token: &str,
-    channel_id: &str,
-    thread_ts: &str,
-) -> Result<Vec<SlackMessage>, AppError> {
-    tracing::info!(
-        channel_id = channel_id,
-        thread_ts = thread_ts,
-        "Fetching thread messages from Slack"
-    );
-
-    let client = reqwest::Client::new();
-    let url = "https://slack.com/api/conversations.replies";
-
-    let response = client
-        .get(url)
-        .bearer_auth(bot_token)
-        .query(&[("channel", channel_id), ("ts", thread_ts)])
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to call Slack API: {:?}", e);
-            AppError::SlackApi(format!("Failed to call conversations.replies: {}", e))
+/// Wraps a shared `reqwest::Client` (connection pool, timeouts, user agent) and the
+/// API base URL, which defaults to `https://slack.com/api` but can point at a local
+/// stand-in server in tests. Bot tokens are passed per call.
+#[derive(Clone, Debug)]
+pub struct SlackClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl SlackClient {
+    /// Create a new Slack client
+    ///
+    /// # Arguments
+    /// * `http` - Shared HTTP client
+    /// * `base_url` - Slack Web API base URL (e.g., "https://slack.com/api")
+    pub fn new(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Full URL for a Web API method (e.g., "conversations.replies")
+    fn method_url(&self, method: &str) -> String {
+        format!("{}/{}", self.base_url, method)
+    }
+
+    /// Call a Web API method and decode its response
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` if the request fails
+    /// - `SlackError::Api` if Slack answers `"ok": false`
+    /// - `SlackError::InvalidResponse` if the response isn't JSON or doesn't
+    ///   decode as `T`
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        token: &str,
+        args: Args<'_>,
+    ) -> Result<T, SlackError> {
+        let url = self.method_url(method);
+        let request = match args {
+            Args::Query(params) => self.http.get(url).query(params),
+            Args::Json(body) => self.http.post(url).json(body),
+            Args::Empty => self.http.post(url),
+        };
+
+        let response = request.bearer_auth(token).send().await.map_err(|e| {
+            tracing::error!(method = method, "Failed to call Slack API: {:?}", e);
+            SlackError::Transport {
+                method,
+                message: e.to_string(),
+            }
         })?;
 
-    let api_response = response
-        .json::<ConversationsRepliesResponse>()
+        let invalid_response = |message: String| {
+            tracing::error!(
+                method = method,
+                "Failed to parse Slack API response: {}",
+                message
+            );
+            SlackError::InvalidResponse { method, message }
+        };
+        let api_response: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| invalid_response(e.to_string()))?;
+
+        if !api_response["ok"].as_bool().unwrap_or(false) {
+            let error = api_response["error"]
+                .as_str()
+                .unwrap_or("Unknown error")
+                .to_string();
+            // Callers decide how serious it is (e.g., already_reacted is fine)
+            tracing::warn!(method = method, error = %error, "Slack API returned error");
+            return Err(SlackError::Api { method, error });
+        }
+
+        serde_json::from_value(api_response).map_err(|e| invalid_response(e.to_string()))
+    }
+
+    /// Fetch the messages of a thread
+    ///
+    /// Calls Slack's `conversations.replies` API page by page, following
+    /// `response_metadata.next_cursor`, to get the thread's messages. This is
+    /// used to find Spotify links shared in the conversation.
+    ///
+    /// Paging stops at the end of the thread, after `query.max_messages`
+    /// messages, or as soon as `is_enough` returns true for the messages
+    /// fetched so far.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `query` - Thread to fetch and how much of it
+    /// * `is_enough` - Whether the messages fetched so far are all the caller needs
+    ///
+    /// # Returns
+    /// Vector of messages in the thread, ordered chronologically
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn fetch_thread_messages(
+        &self,
+        bot_token: &str,
+        query: ThreadQuery<'_>,
+        is_enough: impl Fn(&[SlackMessage]) -> bool,
+    ) -> Result<Vec<SlackMessage>, SlackError> {
+        tracing::info!(
+            channel_id = query.channel_id,
+            thread_ts = query.thread_ts,
+            latest = query.latest,
+            "Fetching thread messages from Slack"
+        );
+
+        let mut params = vec![("channel", query.channel_id), ("ts", query.thread_ts)];
+        if let Some(latest) = query.latest {
+            params.extend([("latest", latest), ("inclusive", "true")]);
+        }
+
+        self.fetch_message_pages(
+            bot_token,
+            "conversations.replies",
+            &params,
+            query.max_messages,
+            is_enough,
+        )
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to parse Slack API response: {:?}", e);
-            AppError::SlackApi(format!("Failed to parse response: {}", e))
-        })?;
+    }
 
-    if !api_response.ok {
-        let error_msg = api_response
-            .error
-            .unwrap_or_else(|| "Unknown error".to_string());
-        tracing::error!(
-            channel_id = channel_id,
-            thread_ts = thread_ts,
-            error = error_msg,
-            "Slack API returned error"
+    /// Fetch recent messages of a channel
+    ///
+    /// Calls Slack's `conversations.history` API page by page, newest message
+    /// first. Thread replies aren't included, only top-level messages.
+    ///
+    /// Paging stops at `query.oldest`, after `query.max_messages` messages, or
+    /// as soon as `is_enough` returns true for the messages fetched so far.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `query` - Channel and time range to fetch
+    /// * `is_enough` - Whether the messages fetched so far are all the caller needs
+    ///
+    /// # Returns
+    /// Vector of messages, newest first
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn fetch_channel_history(
+        &self,
+        bot_token: &str,
+        query: HistoryQuery<'_>,
+        is_enough: impl Fn(&[SlackMessage]) -> bool,
+    ) -> Result<Vec<SlackMessage>, SlackError> {
+        tracing::info!(
+            channel_id = query.channel_id,
+            latest = query.latest,
+            oldest = query.oldest,
+            "Fetching channel history from Slack"
         );
-        return Err(AppError::SlackApi(format!(
-            "conversations.replies failed: {}",
-            error_msg
-        )));
+
+        let mut params = vec![("channel", query.channel_id), ("latest", query.latest)];
+        if let Some(oldest) = query.oldest {
+            params.push(("oldest", oldest));
+        }
+
+        self.fetch_message_pages(
+            bot_token,
+            "conversations.history",
+            &params,
+            query.max_messages,
+            is_enough,
+        )
+        .await
     }
 
-    let messages = api_response.messages.unwrap_or_default();
+    /// Page through a `conversations.*` method that returns messages
+    ///
+    /// Follows `response_metadata.next_cursor` until the last page,
+    /// `max_messages` messages, or `is_enough` returns true.
+    async fn fetch_message_pages(
+        &self,
+        bot_token: &str,
+        method: &'static str,
+        params: &[(&str, &str)],
+        max_messages: usize,
+        is_enough: impl Fn(&[SlackMessage]) -> bool,
+    ) -> Result<Vec<SlackMessage>, SlackError> {
+        let mut messages: Vec<SlackMessage> = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
 
-    tracing::info!(
-        channel_id = channel_id,
-        thread_ts = thread_ts,
-        message_count = messages.len(),
-        "Successfully fetched thread messages"
-    );
+        loop {
+            let limit = max_messages
+                .saturating_sub(messages.len())
+                .clamp(1, MESSAGES_PAGE_SIZE)
+                .to_string();
+            let mut page_params = params.to_vec();
+            page_params.push(("limit", limit.as_str()));
+            if let Some(cursor) = cursor.as_deref() {
+                page_params.push(("cursor", cursor));
+            }
 
-    Ok(messages)
-}
+            let api_response: ConversationsMessagesResponse = self
+                .call(method, bot_token, Args::Query(&page_params))
+                .await?;
 
-/// Add a reaction to a Slack message
-///
-/// Calls Slack's `reactions.add` API to add an emoji reaction to a message.
-/// Used for visual feedback (✅ success, ♻️ already saved, ❌ error).
-///
-/// # Arguments
-/// * `bot_token` - Slack bot token (xoxb-...)
-/// * `channel_id` - Channel ID where the message exists
-/// * `timestamp` - Message timestamp
-/// * `reaction` - Emoji name without colons (e.g., "white_check_mark" for ✅)
-///
-/// # Returns
-/// Ok(()) if reaction was added successfully
-///
-/// # Errors
-/// - `SlackApi` if the API call fails or returns an error
-pub async fn add_reaction(
-    bot_token: &str,
-    channel_id: &str,
-    timestamp: &str,
-    reaction: &str,
-) -> Result<(), AppError> {
-    tracing::info!(
-        channel_id = channel_id,
-        timestamp = timestamp,
-        reaction = reaction,
-        "Adding reaction to message"
-    );
-
-    let client = reqwest::Client::new();
-    let url = "https://slack.com/api/reactions.add";
-
-    let response = client
-        .post(url)
-        .bearer_auth(bot_token)
-        .json(&serde_json::json!({
+            pages += 1;
+            let next_cursor = api_response.next_cursor().map(str::to_string);
+            messages.extend(api_response.messages.unwrap_or_default());
+
+            if is_enough(&messages) {
+                break;
+            }
+            match next_cursor {
+                Some(next) if messages.len() < max_messages => cursor = Some(next),
+                Some(_) => {
+                    tracing::warn!(
+                        method = method,
+                        max_messages = max_messages,
+                        "Stopped fetching messages at the message limit"
+                    );
+                    break;
+                }
+                None => break,
+            }
+        }
+        messages.truncate(max_messages);
+
+        tracing::info!(
+            method = method,
+            message_count = messages.len(),
+            pages = pages,
+            "Successfully fetched messages"
+        );
+
+        Ok(messages)
+    }
+
+    /// Add a reaction to a Slack message
+    ///
+    /// Calls Slack's `reactions.add` API to add an emoji reaction to a message.
+    /// Used for visual feedback (✅ success, ♻️ already saved, ❌ error).
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `channel_id` - Channel ID where the message exists
+    /// * `timestamp` - Message timestamp
+    /// * `reaction` - Emoji name without colons (e.g., "white_check_mark" for ✅)
+    ///
+    /// # Returns
+    /// Ok(()) if reaction was added successfully
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn add_reaction(
+        &self,
+        bot_token: &str,
+        channel_id: &str,
+        timestamp: &str,
+        reaction: &str,
+    ) -> Result<(), SlackError> {
+        tracing::info!(
+            channel_id = channel_id,
+            timestamp = timestamp,
+            reaction = reaction,
+            "Adding reaction to message"
+        );
+
+        let body = serde_json::json!({
             "channel": channel_id,
             "timestamp": timestamp,
             "name": reaction
-        }))
-        .send()
+        });
+        match self
+            .call::<IgnoredAny>("reactions.add", bot_token, Args::Json(&body))
+            .await
+        {
+            Ok(_) => Ok(()),
+            // If the reaction already exists, that's fine
+            Err(e) if e.code() == Some("already_reacted") => {
+                tracing::debug!("Reaction already exists, ignoring");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Post a message to a channel or user
+    ///
+    /// Calls Slack's `chat.postMessage` API. Passing a user ID as `channel`
+    /// sends the message as a DM from the app.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `channel` - Channel ID, or user ID for a DM
+    /// * `text` - Message text (Slack mrkdwn)
+    ///
+    /// # Returns
+    /// Ok(()) if the message was posted successfully
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn post_message(
+        &self,
+        bot_token: &str,
+        channel: &str,
+        text: &str,
+    ) -> Result<(), SlackError> {
+        tracing::info!(channel = channel, "Posting message to Slack");
+
+        let body = serde_json::json!({
+            "channel": channel,
+            "text": text
+        });
+        self.call::<IgnoredAny>("chat.postMessage", bot_token, Args::Json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Post a message only one user in a channel can see
+    ///
+    /// Calls Slack's `chat.postEphemeral` API.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `channel` - Channel ID
+    /// * `user_id` - User who sees the message
+    /// * `text` - Message text (Slack mrkdwn)
+    ///
+    /// # Returns
+    /// Ok(()) if the message was posted successfully
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn post_ephemeral(
+        &self,
+        bot_token: &str,
+        channel: &str,
+        user_id: &str,
+        text: &str,
+    ) -> Result<(), SlackError> {
+        tracing::info!(
+            channel = channel,
+            user_id = user_id,
+            "Posting ephemeral message"
+        );
+
+        let body = serde_json::json!({
+            "channel": channel,
+            "user": user_id,
+            "text": text
+        });
+        self.call::<IgnoredAny>("chat.postEphemeral", bot_token, Args::Json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Join a public channel
+    ///
+    /// Calls Slack's `conversations.join` API (needs the `channels:join`
+    /// scope). Joining a channel the bot is already in succeeds.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `channel_id` - Channel to join
+    ///
+    /// # Returns
+    /// Ok(()) if the bot is now in the channel
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error (e.g.,
+    ///   `method_not_supported_for_channel_type` for private channels)
+    pub async fn join_channel(&self, bot_token: &str, channel_id: &str) -> Result<(), SlackError> {
+        tracing::info!(channel_id = channel_id, "Joining channel");
+
+        let body = serde_json::json!({ "channel": channel_id });
+        self.call::<IgnoredAny>("conversations.join", bot_token, Args::Json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Attach link previews to a message
+    ///
+    /// Calls Slack's `chat.unfurl` API (needs the `links:write` scope) in
+    /// response to a link_shared event.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `target` - Message the links were shared in
+    /// * `unfurls` - Preview per link URL (`{"<url>": {"blocks": [...]}}`)
+    ///
+    /// # Returns
+    /// Ok(()) if the previews were attached
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error (e.g.,
+    ///   `cannot_unfurl_url` for a link that wasn't shared)
+    pub async fn unfurl(
+        &self,
+        bot_token: &str,
+        target: UnfurlTarget<'_>,
+        unfurls: &serde_json::Value,
+    ) -> Result<(), SlackError> {
+        let mut body = match target {
+            UnfurlTarget::Message { channel, ts } => {
+                serde_json::json!({ "channel": channel, "ts": ts })
+            }
+            UnfurlTarget::Composer { unfurl_id, source } => {
+                serde_json::json!({ "unfurl_id": unfurl_id, "source": source })
+            }
+        };
+        body["unfurls"] = unfurls.clone();
+
+        tracing::info!(target = ?target, "Unfurling links");
+
+        self.call::<IgnoredAny>("chat.unfurl", bot_token, Args::Json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Complete a workflow step successfully
+    ///
+    /// Calls Slack's `functions.completeSuccess` API so the workflow moves on
+    /// with the step's outputs.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `function_execution_id` - From the function_executed event
+    /// * `outputs` - Output values by name, as declared for the step
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error (e.g., the outputs don't
+    ///   match the step's definition)
+    pub async fn complete_function_success(
+        &self,
+        bot_token: &str,
+        function_execution_id: &str,
+        outputs: &serde_json::Value,
+    ) -> Result<(), SlackError> {
+        self.complete_function(
+            bot_token,
+            "functions.completeSuccess",
+            serde_json::json!({
+                "function_execution_id": function_execution_id,
+                "outputs": outputs
+            }),
+        )
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to call Slack API: {:?}", e);
-            AppError::SlackApi(format!("Failed to call reactions.add: {}", e))
-        })?;
+    }
 
-    let api_response: serde_json::Value = response.json().await.map_err(|e| {
-        tracing::error!("Failed to parse Slack API response: {:?}", e);
-        AppError::SlackApi(format!("Failed to parse response: {}", e))
-    })?;
+    /// Complete a workflow step with an error
+    ///
+    /// Calls Slack's `functions.completeError`; the workflow stops and shows
+    /// `error` to its builder.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `function_execution_id` - From the function_executed event
+    /// * `error` - Why the step failed
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn complete_function_error(
+        &self,
+        bot_token: &str,
+        function_execution_id: &str,
+        error: &str,
+    ) -> Result<(), SlackError> {
+        self.complete_function(
+            bot_token,
+            "functions.completeError",
+            serde_json::json!({
+                "function_execution_id": function_execution_id,
+                "error": error
+            }),
+        )
+        .await
+    }
 
-    let ok = api_response["ok"].as_bool().unwrap_or(false);
-    if !ok {
-        let error_msg = api_response["error"]
-            .as_str()
-            .unwrap_or("Unknown error")
-            .to_string();
+    async fn complete_function(
+        &self,
+        bot_token: &str,
+        method: &'static str,
+        body: serde_json::Value,
+    ) -> Result<(), SlackError> {
+        tracing::info!(
+            method = method,
+            function_execution_id = %body["function_execution_id"],
+            "Completing workflow step"
+        );
+
+        self.call::<IgnoredAny>(method, bot_token, Args::Json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Publish a user's App Home view
+    ///
+    /// Calls Slack's `views.publish` API, replacing whatever the Home tab
+    /// currently shows for that user.
+    ///
+    /// # Arguments
+    /// * `bot_token` - Slack bot token (xoxb-...)
+    /// * `user_id` - User whose Home tab to update
+    /// * `view` - Home tab view (`{"type": "home", "blocks": [...]}`)
+    ///
+    /// # Returns
+    /// Ok(()) if the view was published successfully
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn publish_view(
+        &self,
+        bot_token: &str,
+        user_id: &str,
+        view: &serde_json::Value,
+    ) -> Result<(), SlackError> {
+        tracing::info!(user_id = user_id, "Publishing App Home view");
+
+        let body = serde_json::json!({
+            "user_id": user_id,
+            "view": view
+        });
+        self.call::<IgnoredAny>("views.publish", bot_token, Args::Json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Exchange an install authorization code for a bot token
+    ///
+    /// Calls Slack's `oauth.v2.access` API, authenticating with the app's
+    /// client credentials.
+    ///
+    /// # Arguments
+    /// * `client_id` - Slack app client ID
+    /// * `client_secret` - Slack app client secret
+    /// * `code` - Authorization code from the install redirect
+    /// * `redirect_uri` - Redirect URI used to start the install
+    ///
+    /// # Returns
+    /// The bot token, bot user, scopes and workspace of the installation
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn oauth_v2_access(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<OAuthV2AccessResponse, SlackError> {
+        tracing::info!("Exchanging Slack install code");
+
+        let response = self
+            .http
+            .post(self.method_url("oauth.v2.access"))
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[("code", code), ("redirect_uri", redirect_uri)])
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to call Slack API: {:?}", e);
+                SlackError::Transport {
+                    method: "oauth.v2.access",
+                    message: e.to_string(),
+                }
+            })?;
+
+        let api_response = response
+            .json::<OAuthV2AccessResponse>()
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to parse Slack API response: {:?}", e);
+                SlackError::InvalidResponse {
+                    method: "oauth.v2.access",
+                    message: e.to_string(),
+                }
+            })?;
 
-        // If the reaction already exists, that's fine
-        if error_msg == "already_reacted" {
-            tracing::debug!("Reaction already exists, ignoring");
-            return Ok(());
+        if !api_response.ok {
+            let error_msg = api_response.error.as_deref().unwrap_or("Unknown error");
+            tracing::error!(error = error_msg, "Slack API returned error");
+            return Err(SlackError::Api {
+                method: "oauth.v2.access",
+                error: error_msg.to_string(),
+            });
         }
 
-        tracing::error!(
-            channel_id = channel_id,
-            timestamp = timestamp,
-            reaction = reaction,
-            error = error_msg,
-            "Slack API returned error"
-        );
-        return Err(AppError::SlackApi(format!(
-            "reactions.add failed: {}",
-            error_msg
-        )));
+        Ok(api_response)
     }
 
-    tracing::info!(
-        channel_id = channel_id,
-        timestamp = timestamp,
-        reaction = reaction,
-        "Successfully added reaction"
-    );
+    /// Open a Socket Mode connection
+    ///
+    /// Calls Slack's `apps.connections.open` API with the app-level token.
+    /// Each call returns a fresh single-use WebSocket URL.
+    ///
+    /// # Arguments
+    /// * `app_token` - App-level token with `connections:write` (xapp-...)
+    ///
+    /// # Returns
+    /// The WebSocket URL to connect to
+    ///
+    /// # Errors
+    /// - `SlackError::Transport` / `InvalidResponse` if the API call fails
+    /// - `SlackError::Api` if Slack returns an error
+    pub async fn open_socket_connection(&self, app_token: &str) -> Result<String, SlackError> {
+        let api_response: serde_json::Value = self
+            .call("apps.connections.open", app_token, Args::Empty)
+            .await?;
 
-    Ok(())
+        api_response["url"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or(SlackError::MissingField {
+                method: "apps.connections.open",
+                field: "url",
+            })
+    }
 }
 
-// Note: Actual API testing would require mocking or integration tests with real Slack API
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_url() {
+        let client = SlackClient::new(reqwest::Client::new(), "https://slack.com/api");
+        assert_eq!(
+            client.method_url("conversations.replies"),
+            "https://slack.com/api/conversations.replies"
+        );
+    }
+
+    #[test]
+    fn test_method_url_trailing_slash() {
+        let client = SlackClient::new(reqwest::Client::new(), "http://127.0.0.1:8080/api/");
+        assert_eq!(
+            client.method_url("reactions.add"),
+            "http://127.0.0.1:8080/api/reactions.add"
+        );
+    }
+
+    #[test]
+    fn test_channel_inaccessible_errors() {
+        let api = |error: &str| SlackError::Api {
+            method: "conversations.replies",
+            error: error.to_string(),
+        };
+        assert!(api("not_in_channel").is_channel_inaccessible());
+        assert!(api("channel_not_found").is_channel_inaccessible());
+        assert!(!api("thread_not_found").is_channel_inaccessible());
+        assert_eq!(api("ratelimited").code(), Some("ratelimited"));
+
+        let transport = SlackError::Transport {
+            method: "conversations.replies",
+            message: "connection refused".to_string(),
+        };
+        assert!(transport.code().is_none());
+        assert!(!transport.is_channel_inaccessible());
+        assert_eq!(
+            transport.to_string(),
+            "Failed to call conversations.replies: connection refused"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use crate::error::AppError;
+use crate::slack::identity::identity_workspace;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// App Home button that disconnects Spotify
+pub const DISCONNECT_ACTION_ID: &str = "disconnect_spotify";
+
+/// App Home button that disconnects Spotify and deletes the save history
+pub const DISCONNECT_PURGE_ACTION_ID: &str = "disconnect_spotify_purge";
+
+/// Button on a track unfurl that saves the track for whoever clicks it
+pub const SAVE_TRACK_ACTION_ID: &str = "save_track";
+
+/// Slash command invocation
+///
+/// Slack posts slash commands as `application/x-www-form-urlencoded`; only the
+/// fields we use are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlashCommand {
+    pub team_id: String,
+    /// Enterprise Grid org of the workspace, if any
+    pub enterprise_id: Option<String>,
+    pub user_id: String,
+    pub command: String,
+    pub text: String,
+}
+
+impl SlashCommand {
+    /// Parse a slash command request body
+    ///
+    /// # Errors
+    /// - `BadRequest` if `team_id` or `user_id` is missing
+    pub fn from_form(body: &[u8]) -> Result<Self, AppError> {
+        Self::from_fields(
+            oauth2::url::form_urlencoded::parse(body)
+                .into_owned()
+                .collect(),
+        )
+    }
+
+    /// Parse a slash command delivered over Socket Mode
+    ///
+    /// The envelope payload holds the same fields as the HTTP form, as a JSON
+    /// object.
+    ///
+    /// # Errors
+    /// - `BadRequest` if `team_id` or `user_id` is missing
+    pub fn from_json(payload: &serde_json::Value) -> Result<Self, AppError> {
+        let fields = payload
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect();
+        Self::from_fields(fields)
+    }
+
+    fn from_fields(mut fields: HashMap<String, String>) -> Result<Self, AppError> {
+        let mut field = |name: &str| fields.remove(name).unwrap_or_default();
+
+        let command = SlashCommand {
+            team_id: field("team_id"),
+            enterprise_id: Some(field("enterprise_id")).filter(|id| !id.is_empty()),
+            user_id: field("user_id"),
+            command: field("command"),
+            text: field("text"),
+        };
+
+        if command.team_id.is_empty() || command.user_id.is_empty() {
+            return Err(AppError::BadRequest(
+                "Slash command is missing team_id or user_id".to_string(),
+            ));
+        }
+
+        Ok(command)
+    }
+
+    /// The subcommand requested in the command text
+    pub fn action(&self) -> CommandAction {
+        CommandAction::parse(&self.text)
+    }
+
+    /// Workspace key of the invoking user's identity
+    pub fn identity_workspace(&self) -> String {
+        identity_workspace(&self.team_id, self.enterprise_id.as_deref())
+    }
+}
+
+/// Subcommands of the slash command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandAction {
+    /// Reply with a link that starts the Spotify connect flow
+    Connect,
+    /// Delete the user's tokens, and their save history if requested
+    Disconnect { purge_history: bool },
+    /// Reply with usage
+    Help,
+}
+
+impl CommandAction {
+    /// Parse slash command text, e.g. `disconnect --purge`
+    pub fn parse(text: &str) -> Self {
+        let mut words = text.split_whitespace().map(str::to_lowercase);
+
+        match words.next().as_deref() {
+            Some("connect") => CommandAction::Connect,
+            Some("disconnect") => CommandAction::Disconnect {
+                purge_history: words.any(|word| word == "purge" || word == "--purge"),
+            },
+            _ => CommandAction::Help,
+        }
+    }
+}
+
+/// Interactive component payload, sent as the `payload` form field
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum InteractionPayload {
+    #[serde(rename = "block_actions")]
+    BlockActions {
+        user: InteractionUser,
+        team: InteractionTeam,
+        /// Enterprise Grid org, if any
+        #[serde(default)]
+        enterprise: Option<InteractionTeam>,
+        #[serde(default)]
+        actions: Vec<BlockAction>,
+        /// Where the clicked element is (e.g., the message of an unfurl)
+        #[serde(default)]
+        container: Option<Box<InteractionContainer>>,
+        /// Message the clicked element is in, if it's in one
+        #[serde(default)]
+        message: Option<InteractionMessage>,
+    },
+
+    /// Interactions we don't handle (shortcuts, view submissions, ...)
+    #[serde(other)]
+    Other,
+}
+
+impl InteractionPayload {
+    /// Parse an interaction request body
+    ///
+    /// # Errors
+    /// - `BadRequest` if the `payload` field is missing or isn't valid JSON
+    pub fn from_form(body: &[u8]) -> Result<Self, AppError> {
+        let payload = oauth2::url::form_urlencoded::parse(body)
+            .find(|(key, _)| key == "payload")
+            .map(|(_, value)| value.into_owned())
+            .ok_or_else(|| AppError::BadRequest("Missing payload field".to_string()))?;
+
+        serde_json::from_str(&payload)
+            .map_err(|e| AppError::BadRequest(format!("Invalid interaction payload: {}", e)))
+    }
+
+    /// Parse an interaction delivered over Socket Mode
+    ///
+    /// # Errors
+    /// - `BadRequest` if the payload isn't a valid interaction
+    pub fn from_json(payload: serde_json::Value) -> Result<Self, AppError> {
+        serde_json::from_value(payload)
+            .map_err(|e| AppError::BadRequest(format!("Invalid interaction payload: {}", e)))
+    }
+}
+
+/// User who triggered an interaction
+#[derive(Debug, Deserialize)]
+pub struct InteractionUser {
+    pub id: String,
+}
+
+/// Workspace or Enterprise Grid org an interaction happened in
+#[derive(Debug, Deserialize)]
+pub struct InteractionTeam {
+    pub id: String,
+}
+
+/// Container of an interactive element
+#[derive(Debug, Deserialize)]
+pub struct InteractionContainer {
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub message_ts: Option<String>,
+    #[serde(default)]
+    pub thread_ts: Option<String>,
+}
+
+/// Message an interactive element is in
+#[derive(Debug, Deserialize)]
+pub struct InteractionMessage {
+    pub ts: String,
+    #[serde(default)]
+    pub thread_ts: Option<String>,
+}
+
+/// A button click or other block element action
+#[derive(Debug, Deserialize)]
+pub struct BlockAction {
+    pub action_id: String,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// A click on a track unfurl's Save button, queued as a `save_shared_track` job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveButtonClick {
+    /// Workspace key of the user's identity (see `identity_workspace`)
+    pub workspace_id: String,
+    /// Workspace the click came from, whose bot token replies
+    pub team_id: String,
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    pub user_id: String,
+    pub channel_id: String,
+    /// The message whose link was unfurled
+    pub message_ts: String,
+    /// Thread of that message (its own ts if it's not in a thread)
+    pub thread_ts: String,
+    pub track_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_action() {
+        assert_eq!(CommandAction::parse("connect"), CommandAction::Connect);
+        assert_eq!(
+            CommandAction::parse("disconnect"),
+            CommandAction::Disconnect {
+                purge_history: false
+            }
+        );
+        assert_eq!(
+            CommandAction::parse("  Disconnect --purge "),
+            CommandAction::Disconnect {
+                purge_history: true
+            }
+        );
+        assert_eq!(CommandAction::parse(""), CommandAction::Help);
+        assert_eq!(CommandAction::parse("help"), CommandAction::Help);
+    }
+
+    #[test]
+    fn test_slash_command_from_form() {
+        let body =
+            b"token=x&team_id=T123&user_id=U456&command=%2Fsavethebeat&text=disconnect+purge";
+        let command = SlashCommand::from_form(body).unwrap();
+        assert_eq!(command.team_id, "T123");
+        assert_eq!(command.user_id, "U456");
+        assert_eq!(command.command, "/savethebeat");
+        assert_eq!(command.enterprise_id, None);
+        assert_eq!(command.identity_workspace(), "T123");
+        assert_eq!(
+            command.action(),
+            CommandAction::Disconnect {
+                purge_history: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_slash_command_in_grid_org() {
+        let body =
+            b"team_id=T123&enterprise_id=E789&user_id=U456&command=%2Fsavethebeat&text=connect";
+        let command = SlashCommand::from_form(body).unwrap();
+        assert_eq!(command.enterprise_id.as_deref(), Some("E789"));
+        assert_eq!(command.identity_workspace(), "E789");
+    }
+
+    #[test]
+    fn test_slash_command_from_json() {
+        let payload = serde_json::json!({
+            "team_id": "T123",
+            "user_id": "U456",
+            "command": "/savethebeat",
+            "text": "connect",
+            "is_enterprise_install": "false"
+        });
+        let command = SlashCommand::from_json(&payload).unwrap();
+        assert_eq!(command.team_id, "T123");
+        assert_eq!(command.action(), CommandAction::Connect);
+
+        assert!(matches!(
+            SlashCommand::from_json(&serde_json::json!({ "team_id": "T123" })),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_slash_command_missing_user() {
+        assert!(matches!(
+            SlashCommand::from_form(b"team_id=T123&text=connect"),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_interaction_payload_block_actions() {
+        let json = r#"{
+            "type": "block_actions",
+            "user": {"id": "U456", "team_id": "T123"},
+            "team": {"id": "T123", "domain": "example"},
+            "enterprise": {"id": "E789", "name": "Example Org"},
+            "actions": [{"action_id": "disconnect_spotify", "block_id": "b", "type": "button"}]
+        }"#;
+        let body = oauth2::url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("payload", json)
+            .finish();
+
+        match InteractionPayload::from_form(body.as_bytes()).unwrap() {
+            InteractionPayload::BlockActions {
+                user,
+                team,
+                enterprise,
+                actions,
+                container,
+                ..
+            } => {
+                assert!(container.is_none());
+                assert_eq!(user.id, "U456");
+                assert_eq!(team.id, "T123");
+                assert_eq!(enterprise.unwrap().id, "E789");
+                assert_eq!(actions[0].action_id, DISCONNECT_ACTION_ID);
+            }
+            InteractionPayload::Other => panic!("Expected BlockActions"),
+        }
+    }
+
+    #[test]
+    fn test_interaction_payload_unfurl_button() {
+        let payload = serde_json::json!({
+            "type": "block_actions",
+            "user": {"id": "U456"},
+            "team": {"id": "T123"},
+            "container": {
+                "type": "message_attachment",
+                "message_ts": "1234567890.123456",
+                "channel_id": "C789",
+                "is_app_unfurl": true
+            },
+            "message": {"ts": "1234567890.123456", "thread_ts": "1234567890.000000"},
+            "actions": [{"action_id": "save_track", "type": "button", "value": "4iV5W9uYEdYUVa79Axb7Rh"}]
+        });
+
+        match InteractionPayload::from_json(payload).unwrap() {
+            InteractionPayload::BlockActions {
+                actions,
+                container,
+                message,
+                ..
+            } => {
+                assert_eq!(actions[0].action_id, SAVE_TRACK_ACTION_ID);
+                assert_eq!(actions[0].value.as_deref(), Some("4iV5W9uYEdYUVa79Axb7Rh"));
+                let container = container.unwrap();
+                assert_eq!(container.channel_id.as_deref(), Some("C789"));
+                assert_eq!(container.message_ts.as_deref(), Some("1234567890.123456"));
+                assert_eq!(
+                    message.unwrap().thread_ts.as_deref(),
+                    Some("1234567890.000000")
+                );
+            }
+            InteractionPayload::Other => panic!("Expected BlockActions"),
+        }
+    }
+
+    #[test]
+    fn test_interaction_payload_other_type() {
+        let body = "payload=%7B%22type%22%3A%22shortcut%22%7D";
+        assert!(matches!(
+            InteractionPayload::from_form(body.as_bytes()).unwrap(),
+            InteractionPayload::Other
+        ));
+    }
+}
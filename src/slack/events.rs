@@ -1,3 +1,5 @@
+use crate::slack::identity::EventTeams;
+use crate::spotify::parser::extract_track_id;
 use serde::{Deserialize, Serialize};
 
 /// Top-level Slack event request
@@ -12,12 +14,70 @@ pub enum SlackEventRequest {
     UrlVerification { challenge: String },
 
     #[serde(rename = "event_callback")]
-    EventCallback {
-        team_id: String,
-        event: SlackEvent,
-        event_id: String,
-        event_time: i64,
-    },
+    EventCallback(Box<EventCallback>),
+}
+
+/// An `event_callback` envelope
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EventCallback {
+    pub team_id: String,
+    /// Enterprise Grid org the event came from
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    /// Workspace that owns the channel (set in Slack Connect channels)
+    #[serde(default)]
+    pub context_team_id: Option<String>,
+    #[serde(default)]
+    pub context_enterprise_id: Option<String>,
+    /// Whether the channel is shared with another organization
+    #[serde(default)]
+    pub is_ext_shared_channel: bool,
+    /// Installations the event was delivered for
+    #[serde(default)]
+    pub authorizations: Vec<Authorization>,
+    pub event: SlackEvent,
+    pub event_id: String,
+    pub event_time: i64,
+}
+
+impl EventCallback {
+    /// Workspaces involved in the event
+    ///
+    /// The installation comes from `authorizations` when present: in Slack
+    /// Connect channels the envelope's `team_id` can be the other organization's
+    /// workspace.
+    pub fn teams(&self) -> EventTeams {
+        let authorization = self.authorizations.first();
+
+        EventTeams {
+            team_id: authorization
+                .and_then(|auth| auth.team_id.clone())
+                .unwrap_or_else(|| self.team_id.clone()),
+            enterprise_id: authorization
+                .and_then(|auth| auth.enterprise_id.clone())
+                .or_else(|| self.enterprise_id.clone()),
+            is_enterprise_install: authorization.is_some_and(|auth| auth.is_enterprise_install),
+            context_team_id: self.context_team_id.clone(),
+            is_ext_shared_channel: self.is_ext_shared_channel,
+            user_enterprise_id: None,
+        }
+    }
+}
+
+/// An installation an event was delivered for
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Authorization {
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    /// Null for org-wide Enterprise Grid installs
+    #[serde(default)]
+    pub team_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub is_bot: bool,
+    #[serde(default)]
+    pub is_enterprise_install: bool,
 }
 
 /// Slack event types we handle
@@ -32,47 +92,272 @@ pub enum SlackEvent {
         channel: String,
         #[serde(default)]
         thread_ts: Option<String>,
+        /// User's own workspace (differs for Grid and Slack Connect users)
+        #[serde(default)]
+        user_team: Option<String>,
+    },
+
+    #[serde(rename = "app_home_opened")]
+    AppHomeOpened {
+        user: String,
+        #[serde(default)]
+        tab: Option<String>,
+    },
+
+    /// The app was removed from the workspace
+    #[serde(rename = "app_uninstalled")]
+    AppUninstalled {},
+
+    /// OAuth or bot tokens for the workspace were revoked
+    #[serde(rename = "tokens_revoked")]
+    TokensRevoked { tokens: RevokedTokens },
+
+    /// A message (or a message being composed) links to one of the app's
+    /// unfurl domains
+    #[serde(rename = "link_shared")]
+    LinkShared {
+        channel: String,
+        user: String,
+        message_ts: String,
+        #[serde(default)]
+        thread_ts: Option<String>,
+        links: Vec<SharedLink>,
+        /// Identifies the links in the message composer
+        #[serde(default)]
+        unfurl_id: Option<String>,
+        /// `conversations_history` or `composer`
+        #[serde(default)]
+        source: Option<String>,
+    },
+
+    /// A workflow reached one of the app's custom steps
+    ///
+    /// The event's short-lived `bot_access_token` isn't kept, since jobs are
+    /// stored unencrypted; the installation's bot token completes the step.
+    #[serde(rename = "function_executed")]
+    FunctionExecuted {
+        function: FunctionDefinition,
+        /// Step inputs by name, as the workflow filled them in
+        #[serde(default)]
+        inputs: serde_json::Map<String, serde_json::Value>,
+        function_execution_id: String,
+        #[serde(default)]
+        workflow_execution_id: Option<String>,
+        event_ts: String,
     },
 }
 
+/// The custom step a `function_executed` event is for
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FunctionDefinition {
+    pub callback_id: String,
+}
+
+/// A link in a `link_shared` event
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SharedLink {
+    pub domain: String,
+    pub url: String,
+}
+
+/// Tokens revoked in a `tokens_revoked` event, as the user IDs they belong to
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RevokedTokens {
+    /// Users whose user tokens were revoked
+    #[serde(default)]
+    pub oauth: Vec<String>,
+    /// Bot users whose bot tokens were revoked
+    #[serde(default)]
+    pub bot: Vec<String>,
+}
+
 /// Response for url_verification challenge
 #[derive(Debug, Serialize)]
 pub struct UrlVerificationResponse {
     pub challenge: String,
 }
 
-/// Slack API response for conversations.replies
+/// Slack API response for conversations.replies and conversations.history
 #[derive(Debug, Deserialize)]
-pub struct ConversationsRepliesResponse {
+pub struct ConversationsMessagesResponse {
     pub ok: bool,
     pub messages: Option<Vec<SlackMessage>>,
     pub error: Option<String>,
+    /// Whether more messages follow this page
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default)]
+    pub response_metadata: Option<ResponseMetadata>,
+}
+
+impl ConversationsMessagesResponse {
+    /// Cursor for the next page, if there is one
+    ///
+    /// Slack sends an empty `next_cursor` on the last page.
+    pub fn next_cursor(&self) -> Option<&str> {
+        self.response_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.next_cursor.as_deref())
+            .filter(|cursor| self.has_more && !cursor.is_empty())
+    }
+}
+
+/// Pagination metadata of a Web API response
+#[derive(Debug, Deserialize)]
+pub struct ResponseMetadata {
+    #[serde(default)]
+    pub next_cursor: Option<String>,
 }
 
 /// Slack message structure
+///
+/// Links can be in the `text`, in rich-text `blocks`, in `attachments` (bot
+/// messages, unfurls and forwarded messages) or in shared `files`; see
+/// `slack::links` for how they are collected.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SlackMessage {
     pub ts: String,
     pub user: Option<String>,
+    #[serde(default)]
     pub text: String,
     #[serde(default)]
     pub thread_ts: Option<String>,
+    #[serde(default)]
+    pub blocks: Vec<MessageBlock>,
+    #[serde(default)]
+    pub attachments: Vec<MessageAttachment>,
+    #[serde(default)]
+    pub files: Vec<MessageFile>,
+}
+
+/// A Block Kit block, or an element nested in one
+///
+/// Blocks and rich-text elements share this shape: a type, optional text or
+/// URL, and nested `elements`. Unmodeled fields are ignored.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct MessageBlock {
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// Plain string in rich-text elements, a text object in layout blocks
+    #[serde(default)]
+    pub text: Option<BlockText>,
+    /// Target of a `link` element or `button`
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub elements: Vec<MessageBlock>,
+    /// Text fields of a `section` block
+    #[serde(default)]
+    pub fields: Vec<BlockText>,
+    /// Element beside a `section` block's text (e.g., a link button)
+    #[serde(default)]
+    pub accessory: Option<Box<MessageBlock>>,
+}
+
+/// Text of a block: a plain string or a `{"type": "mrkdwn", "text": ...}` object
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum BlockText {
+    Plain(String),
+    Object { text: String },
+}
+
+impl BlockText {
+    pub fn as_str(&self) -> &str {
+        match self {
+            BlockText::Plain(text) | BlockText::Object { text } => text,
+        }
+    }
+}
+
+/// A legacy message attachment
+///
+/// Used by bots, link unfurls and forwarded/shared messages (`from_url` is
+/// the original message's permalink or the unfurled URL).
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct MessageAttachment {
+    #[serde(default)]
+    pub pretext: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub title_link: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub from_url: Option<String>,
+    #[serde(default)]
+    pub original_url: Option<String>,
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// Blocks of a forwarded message
+    #[serde(default)]
+    pub message_blocks: Vec<SharedMessageBlocks>,
+}
+
+/// Blocks of a message shared as an attachment
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SharedMessageBlocks {
+    #[serde(default)]
+    pub message: SharedMessage,
+}
+
+/// The message inside `message_blocks`
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SharedMessage {
+    #[serde(default)]
+    pub blocks: Vec<MessageBlock>,
+}
+
+/// A file shared in a message
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct MessageFile {
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Start of a text file's contents
+    #[serde(default)]
+    pub preview: Option<String>,
+    /// Link of an external file (e.g., a Google Drive document)
+    #[serde(default)]
+    pub external_url: Option<String>,
 }
 
 /// Event metadata extracted from app_mention
-#[derive(Debug, Clone)]
+///
+/// Fields added after the first release default when older queued jobs are
+/// decoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MentionEvent {
+    /// Workspace key of the user's identity (see `identity_workspace`)
     pub workspace_id: String,
     pub user_id: String,
     pub channel_id: String,
     pub thread_ts: String,
     pub mention_ts: String,
     pub text: String,
+    /// Workspace whose installation received the event
+    #[serde(default)]
+    pub team_id: Option<String>,
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    /// User's own workspace
+    #[serde(default)]
+    pub user_team_id: Option<String>,
+    /// Workspace that owns the channel
+    #[serde(default)]
+    pub context_team_id: Option<String>,
+    /// Whether the user is from another organization (Slack Connect)
+    #[serde(default)]
+    pub external: bool,
 }
 
 impl MentionEvent {
     /// Extract metadata from app_mention event
-    pub fn from_event_callback(team_id: String, event: &SlackEvent) -> Option<Self> {
+    ///
+    /// # Arguments
+    /// * `teams` - Workspaces of the event_callback envelope
+    /// * `event` - The event
+    pub fn from_event_callback(teams: &EventTeams, event: &SlackEvent) -> Option<Self> {
         match event {
             SlackEvent::AppMention {
                 user,
@@ -80,22 +365,207 @@ impl MentionEvent {
                 ts,
                 channel,
                 thread_ts,
+                user_team,
             } => Some(MentionEvent {
-                workspace_id: team_id,
+                workspace_id: teams.identity_workspace(user_team.as_deref()),
                 user_id: user.clone(),
                 channel_id: channel.clone(),
                 thread_ts: thread_ts.clone().unwrap_or_else(|| ts.clone()),
                 mention_ts: ts.clone(),
                 text: text.clone(),
+                team_id: Some(teams.team_id.clone()),
+                enterprise_id: teams.enterprise_id.clone(),
+                user_team_id: user_team.clone(),
+                context_team_id: teams.context_team_id.clone(),
+                external: teams.is_external(user_team.as_deref()),
             }),
+            SlackEvent::AppHomeOpened { .. }
+            | SlackEvent::AppUninstalled {}
+            | SlackEvent::TokensRevoked { .. }
+            | SlackEvent::LinkShared { .. }
+            | SlackEvent::FunctionExecuted { .. } => None,
         }
     }
+
+    /// Workspace whose bot token reads the thread and reacts
+    ///
+    /// Jobs queued before `team_id` was recorded fall back to `workspace_id`.
+    pub fn bot_team_id(&self) -> &str {
+        self.team_id.as_deref().unwrap_or(&self.workspace_id)
+    }
+
+    /// Whether the mention was posted in the channel rather than in a thread
+    pub fn is_top_level(&self) -> bool {
+        self.thread_ts == self.mention_ts
+    }
+}
+
+/// Spotify track links to unfurl, extracted from link_shared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkSharedEvent {
+    /// Workspace whose installation received the event
+    pub team_id: String,
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    pub channel_id: String,
+    pub message_ts: String,
+    #[serde(default)]
+    pub unfurl_id: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Track links in the message, with the track ID of each
+    pub tracks: Vec<SharedTrackLink>,
+}
+
+/// A Spotify track link to unfurl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedTrackLink {
+    /// The link as shared; `chat.unfurl` is keyed by it
+    pub url: String,
+    pub track_id: String,
+}
+
+impl LinkSharedEvent {
+    /// Extract the track links from a link_shared event
+    ///
+    /// # Arguments
+    /// * `teams` - Workspaces of the event_callback envelope
+    /// * `event` - The event
+    ///
+    /// # Returns
+    /// None for other events and for link_shared events without track links
+    /// (e.g., album or playlist links)
+    pub fn from_event_callback(teams: &EventTeams, event: &SlackEvent) -> Option<Self> {
+        let SlackEvent::LinkShared {
+            channel,
+            message_ts,
+            links,
+            unfurl_id,
+            source,
+            ..
+        } = event
+        else {
+            return None;
+        };
+
+        let mut tracks: Vec<SharedTrackLink> = Vec::new();
+        for link in links {
+            if tracks.iter().any(|track| track.url == link.url) {
+                continue;
+            }
+            if let Some(track_id) = extract_track_id(&link.url) {
+                tracks.push(SharedTrackLink {
+                    url: link.url.clone(),
+                    track_id,
+                });
+            }
+        }
+        if tracks.is_empty() {
+            return None;
+        }
+
+        Some(LinkSharedEvent {
+            team_id: teams.team_id.clone(),
+            enterprise_id: teams.enterprise_id.clone(),
+            channel_id: channel.clone(),
+            message_ts: message_ts.clone(),
+            unfurl_id: unfurl_id.clone(),
+            source: source.clone(),
+            tracks,
+        })
+    }
+}
+
+/// Callback ID of the "Save Spotify track" workflow step
+pub const SAVE_TRACK_FUNCTION_CALLBACK_ID: &str = "save_spotify_track";
+
+/// A run of the "Save Spotify track" workflow step, from function_executed
+///
+/// Inputs are kept as given; they're validated when the step runs, so a bad
+/// input completes the step with an error instead of failing the event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStepEvent {
+    /// Workspace key of the user's identity (see `identity_workspace`)
+    pub workspace_id: String,
+    /// Workspace whose installation received the event
+    pub team_id: String,
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    pub function_execution_id: String,
+    /// Groups the step's save records; falls back to the function execution
+    pub workflow_execution_id: String,
+    pub event_ts: String,
+    /// `spotify_url` input
+    #[serde(default)]
+    pub spotify_url: Option<String>,
+    /// `user_id` input: whose library the track is saved to
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+impl WorkflowStepEvent {
+    /// Extract the step run from a function_executed event
+    ///
+    /// # Arguments
+    /// * `teams` - Workspaces of the event_callback envelope
+    /// * `event` - The event
+    ///
+    /// # Returns
+    /// None for other events and for other apps' or other steps' functions
+    pub fn from_event_callback(teams: &EventTeams, event: &SlackEvent) -> Option<Self> {
+        let SlackEvent::FunctionExecuted {
+            function,
+            inputs,
+            function_execution_id,
+            workflow_execution_id,
+            event_ts,
+        } = event
+        else {
+            return None;
+        };
+        if function.callback_id != SAVE_TRACK_FUNCTION_CALLBACK_ID {
+            return None;
+        }
+
+        let input = |name: &str| {
+            inputs
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        Some(WorkflowStepEvent {
+            workspace_id: teams.identity_workspace(None),
+            team_id: teams.team_id.clone(),
+            enterprise_id: teams.enterprise_id.clone(),
+            function_execution_id: function_execution_id.clone(),
+            workflow_execution_id: workflow_execution_id
+                .clone()
+                .unwrap_or_else(|| function_execution_id.clone()),
+            event_ts: event_ts.clone(),
+            spotify_url: input("spotify_url"),
+            user_id: input("user_id"),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn teams(team_id: &str) -> EventTeams {
+        EventTeams {
+            team_id: team_id.to_string(),
+            enterprise_id: None,
+            is_enterprise_install: false,
+            context_team_id: None,
+            is_ext_shared_channel: false,
+            user_enterprise_id: None,
+        }
+    }
+
     #[test]
     fn test_deserialize_url_verification() {
         let json = r#"{
@@ -134,36 +604,63 @@ mod tests {
 
         let event: SlackEventRequest = serde_json::from_str(json).unwrap();
         match event {
-            SlackEventRequest::EventCallback {
-                team_id,
-                event,
-                event_id,
-                event_time,
-            } => {
-                assert_eq!(team_id, "T123ABC");
-                assert_eq!(event_id, "Ev123ABC");
-                assert_eq!(event_time, 1234567890);
-
-                match event {
+            SlackEventRequest::EventCallback(callback) => {
+                assert_eq!(callback.team_id, "T123ABC");
+                assert_eq!(callback.event_id, "Ev123ABC");
+                assert_eq!(callback.event_time, 1234567890);
+                assert_eq!(callback.teams().team_id, "T123ABC");
+                assert!(callback.enterprise_id.is_none());
+
+                match callback.event {
                     SlackEvent::AppMention {
                         user,
                         text,
                         ts,
                         channel,
                         thread_ts,
+                        user_team,
                     } => {
                         assert_eq!(user, "U123ABC");
                         assert_eq!(text, "<@U456DEF> save this track");
                         assert_eq!(ts, "1234567890.123456");
                         assert_eq!(channel, "C123ABC");
                         assert_eq!(thread_ts, Some("1234567890.000000".to_string()));
+                        assert_eq!(user_team, None);
                     }
+                    _ => panic!("Expected AppMention"),
                 }
             }
             _ => panic!("Expected EventCallback"),
         }
     }
 
+    #[test]
+    fn test_deserialize_uninstall_events() {
+        let uninstalled: SlackEvent =
+            serde_json::from_str(r#"{"type": "app_uninstalled"}"#).unwrap();
+        assert!(matches!(uninstalled, SlackEvent::AppUninstalled {}));
+
+        let revoked: SlackEvent = serde_json::from_str(
+            r#"{"type": "tokens_revoked", "tokens": {"oauth": ["U123"], "bot": ["UBOT"]}}"#,
+        )
+        .unwrap();
+        match revoked {
+            SlackEvent::TokensRevoked { tokens } => {
+                assert_eq!(tokens.oauth, vec!["U123"]);
+                assert_eq!(tokens.bot, vec!["UBOT"]);
+            }
+            _ => panic!("Expected TokensRevoked"),
+        }
+
+        let bot_only: SlackEvent =
+            serde_json::from_str(r#"{"type": "tokens_revoked", "tokens": {"bot": ["UBOT"]}}"#)
+                .unwrap();
+        assert!(matches!(
+            bot_only,
+            SlackEvent::TokensRevoked { tokens } if tokens.oauth.is_empty()
+        ));
+    }
+
     #[test]
     fn test_mention_event_from_event_callback() {
         let event = SlackEvent::AppMention {
@@ -172,13 +669,17 @@ mod tests {
             ts: "1234567890.123456".to_string(),
             channel: "C123ABC".to_string(),
             thread_ts: Some("1234567890.000000".to_string()),
+            user_team: None,
         };
 
-        let mention = MentionEvent::from_event_callback("T123ABC".to_string(), &event).unwrap();
+        let mention = MentionEvent::from_event_callback(&teams("T123ABC"), &event).unwrap();
         assert_eq!(mention.workspace_id, "T123ABC");
+        assert_eq!(mention.bot_team_id(), "T123ABC");
+        assert!(!mention.external);
         assert_eq!(mention.user_id, "U123ABC");
         assert_eq!(mention.channel_id, "C123ABC");
         assert_eq!(mention.thread_ts, "1234567890.000000");
+        assert!(!mention.is_top_level());
         assert_eq!(mention.mention_ts, "1234567890.123456");
     }
 
@@ -190,11 +691,218 @@ mod tests {
             ts: "1234567890.123456".to_string(),
             channel: "C123ABC".to_string(),
             thread_ts: None,
+            user_team: None,
         };
 
-        let mention = MentionEvent::from_event_callback("T123ABC".to_string(), &event).unwrap();
+        let mention = MentionEvent::from_event_callback(&teams("T123ABC"), &event).unwrap();
         // When no thread_ts, should use message ts as thread_ts
         assert_eq!(mention.thread_ts, "1234567890.123456");
         assert_eq!(mention.mention_ts, "1234567890.123456");
+        assert!(mention.is_top_level());
+    }
+
+    #[test]
+    fn test_link_shared_event_keeps_track_links() {
+        let event: SlackEvent = serde_json::from_str(
+            r#"{
+                "type": "link_shared",
+                "channel": "C123ABC",
+                "user": "U123ABC",
+                "message_ts": "1234567890.123456",
+                "unfurl_id": "C123ABC.1234567890.123456.abc",
+                "source": "conversations_history",
+                "links": [
+                    {"domain": "open.spotify.com", "url": "https://open.spotify.com/album/1DFixLWuPkv3KT3TnV35m3"},
+                    {"domain": "open.spotify.com", "url": "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh?si=abc"},
+                    {"domain": "open.spotify.com", "url": "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh?si=abc"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let shared = LinkSharedEvent::from_event_callback(&teams("T123ABC"), &event).unwrap();
+        assert_eq!(shared.team_id, "T123ABC");
+        assert_eq!(shared.channel_id, "C123ABC");
+        assert_eq!(shared.message_ts, "1234567890.123456");
+        assert_eq!(shared.source.as_deref(), Some("conversations_history"));
+        assert_eq!(
+            shared.tracks,
+            vec![SharedTrackLink {
+                url: "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh?si=abc".to_string(),
+                track_id: "4iV5W9uYEdYUVa79Axb7Rh".to_string(),
+            }]
+        );
+        assert!(MentionEvent::from_event_callback(&teams("T123ABC"), &event).is_none());
+
+        let albums_only: SlackEvent = serde_json::from_str(
+            r#"{
+                "type": "link_shared",
+                "channel": "C123ABC",
+                "user": "U123ABC",
+                "message_ts": "1234567890.123456",
+                "links": [{"domain": "open.spotify.com", "url": "https://open.spotify.com/album/1DFixLWuPkv3KT3TnV35m3"}]
+            }"#,
+        )
+        .unwrap();
+        assert!(LinkSharedEvent::from_event_callback(&teams("T123ABC"), &albums_only).is_none());
+    }
+
+    #[test]
+    fn test_workflow_step_event_from_function_executed() {
+        let event: SlackEvent = serde_json::from_str(
+            r#"{
+                "type": "function_executed",
+                "function": {"id": "Fn123", "callback_id": "save_spotify_track", "title": "Save Spotify track"},
+                "inputs": {"spotify_url": " https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh ", "user_id": "U123ABC"},
+                "function_execution_id": "Fx123",
+                "workflow_execution_id": "Wf123",
+                "event_ts": "1234567890.123456",
+                "bot_access_token": "xwfp-123"
+            }"#,
+        )
+        .unwrap();
+
+        let step = WorkflowStepEvent::from_event_callback(&teams("T123ABC"), &event).unwrap();
+        assert_eq!(step.workspace_id, "T123ABC");
+        assert_eq!(step.function_execution_id, "Fx123");
+        assert_eq!(step.workflow_execution_id, "Wf123");
+        assert_eq!(
+            step.spotify_url.as_deref(),
+            Some("https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh")
+        );
+        assert_eq!(step.user_id.as_deref(), Some("U123ABC"));
+
+        let other_step: SlackEvent = serde_json::from_str(
+            r#"{
+                "type": "function_executed",
+                "function": {"callback_id": "something_else"},
+                "function_execution_id": "Fx456",
+                "event_ts": "1234567890.123456"
+            }"#,
+        )
+        .unwrap();
+        assert!(WorkflowStepEvent::from_event_callback(&teams("T123ABC"), &other_step).is_none());
+    }
+
+    #[test]
+    fn test_deserialize_app_home_opened() {
+        let json = r#"{
+            "type": "event_callback",
+            "team_id": "T123ABC",
+            "event_id": "Ev456DEF",
+            "event_time": 1234567890,
+            "event": {
+                "type": "app_home_opened",
+                "user": "U123ABC",
+                "channel": "D123ABC",
+                "tab": "home",
+                "event_ts": "1234567890.123456"
+            }
+        }"#;
+
+        let event: SlackEventRequest = serde_json::from_str(json).unwrap();
+        match event {
+            SlackEventRequest::EventCallback(callback) => {
+                assert_eq!(callback.team_id, "T123ABC");
+                assert!(
+                    MentionEvent::from_event_callback(&callback.teams(), &callback.event).is_none()
+                );
+                match callback.event {
+                    SlackEvent::AppHomeOpened { user, tab } => {
+                        assert_eq!(user, "U123ABC");
+                        assert_eq!(tab.as_deref(), Some("home"));
+                    }
+                    _ => panic!("Expected AppHomeOpened"),
+                }
+            }
+            _ => panic!("Expected EventCallback"),
+        }
+    }
+
+    #[test]
+    fn test_mention_in_slack_connect_channel() {
+        // Delivered to our installation (T_HOME in org E_HOME) for a channel
+        // owned by a partner workspace
+        let json = r#"{
+            "type": "event_callback",
+            "team_id": "T_PARTNER",
+            "enterprise_id": "E_HOME",
+            "context_team_id": "T_PARTNER",
+            "context_enterprise_id": null,
+            "is_ext_shared_channel": true,
+            "authorizations": [{
+                "enterprise_id": "E_HOME",
+                "team_id": "T_HOME",
+                "user_id": "UBOT",
+                "is_bot": true,
+                "is_enterprise_install": false
+            }],
+            "event_id": "Ev789",
+            "event_time": 1234567890,
+            "event": {
+                "type": "app_mention",
+                "user": "U_GUEST",
+                "user_team": "T_PARTNER",
+                "text": "<@UBOT> save this",
+                "ts": "1234567890.123456",
+                "channel": "C_SHARED"
+            }
+        }"#;
+
+        let SlackEventRequest::EventCallback(callback) = serde_json::from_str(json).unwrap() else {
+            panic!("Expected EventCallback");
+        };
+        let teams = callback.teams();
+        assert_eq!(teams.team_id, "T_HOME");
+        assert_eq!(teams.enterprise_id.as_deref(), Some("E_HOME"));
+        assert_eq!(teams.context_team_id.as_deref(), Some("T_PARTNER"));
+
+        let mention = MentionEvent::from_event_callback(&teams, &callback.event).unwrap();
+        assert!(mention.external);
+        assert_eq!(mention.workspace_id, "T_PARTNER");
+        assert_eq!(mention.user_team_id.as_deref(), Some("T_PARTNER"));
+        assert_eq!(mention.bot_team_id(), "T_HOME");
+    }
+
+    #[test]
+    fn test_mention_event_decodes_without_team_fields() {
+        // Queued before the Grid/Slack Connect fields existed
+        let mention: MentionEvent = serde_json::from_str(
+            r#"{
+                "workspace_id": "T123",
+                "user_id": "U123",
+                "channel_id": "C123",
+                "thread_ts": "1.0",
+                "mention_ts": "1.1",
+                "text": "save"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(mention.bot_team_id(), "T123");
+        assert!(!mention.external);
+    }
+
+    #[test]
+    fn test_replies_next_cursor() {
+        let page: ConversationsMessagesResponse = serde_json::from_str(
+            r#"{
+                "ok": true,
+                "messages": [{"ts": "1.1", "user": "U1", "text": "hi"}],
+                "has_more": true,
+                "response_metadata": {"next_cursor": "bmV4dA=="}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(page.next_cursor(), Some("bmV4dA=="));
+
+        let last: ConversationsMessagesResponse = serde_json::from_str(
+            r#"{"ok": true, "messages": [], "has_more": false, "response_metadata": {"next_cursor": ""}}"#,
+        )
+        .unwrap();
+        assert_eq!(last.next_cursor(), None);
+
+        let unpaged: ConversationsMessagesResponse =
+            serde_json::from_str(r#"{"ok": true, "messages": []}"#).unwrap();
+        assert_eq!(unpaged.next_cursor(), None);
     }
 }
@@ -0,0 +1,113 @@
+use crate::slack::commands::{DISCONNECT_ACTION_ID, DISCONNECT_PURGE_ACTION_ID};
+use serde_json::{Value, json};
+
+/// Build the App Home tab for a user
+///
+/// Connected users get buttons to disconnect (optionally deleting their save
+/// history); everyone else gets a link to connect.
+///
+/// # Arguments
+/// * `connected` - Whether the user has linked Spotify
+/// * `connect_url` - URL that starts the connect flow for this user
+pub fn home_view(connected: bool, connect_url: &str) -> Value {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": "Save the Beat" }
+    })];
+
+    if connected {
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": "Spotify is connected. Mention me in a thread with a track link to save it to your library."
+            }
+        }));
+        blocks.push(json!({
+            "type": "actions",
+            "elements": [
+                {
+                    "type": "button",
+                    "action_id": DISCONNECT_ACTION_ID,
+                    "text": { "type": "plain_text", "text": "Disconnect Spotify" },
+                    "confirm": confirm_dialog(
+                        "Your Spotify tokens will be deleted. Your save history is kept."
+                    )
+                },
+                {
+                    "type": "button",
+                    "action_id": DISCONNECT_PURGE_ACTION_ID,
+                    "style": "danger",
+                    "text": { "type": "plain_text", "text": "Disconnect and delete history" },
+                    "confirm": confirm_dialog(
+                        "Your Spotify tokens and the record of tracks you've saved will be deleted."
+                    )
+                }
+            ]
+        }));
+    } else {
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": "Connect Spotify to save tracks shared in Slack to your library."
+            },
+            "accessory": {
+                "type": "button",
+                "action_id": "connect_spotify",
+                "style": "primary",
+                "text": { "type": "plain_text", "text": "Connect Spotify" },
+                "url": connect_url
+            }
+        }));
+    }
+
+    json!({ "type": "home", "blocks": blocks })
+}
+
+fn confirm_dialog(text: &str) -> Value {
+    json!({
+        "title": { "type": "plain_text", "text": "Disconnect Spotify?" },
+        "text": { "type": "mrkdwn", "text": text },
+        "confirm": { "type": "plain_text", "text": "Disconnect" },
+        "deny": { "type": "plain_text", "text": "Cancel" }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action_ids(view: &Value) -> Vec<String> {
+        view["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|block| {
+                let elements = block["elements"].as_array().cloned().unwrap_or_default();
+                elements.into_iter().chain(block.get("accessory").cloned())
+            })
+            .filter_map(|element| element["action_id"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_home_view_connected() {
+        let view = home_view(true, "https://example.com/connect");
+        assert_eq!(view["type"], "home");
+        assert_eq!(
+            action_ids(&view),
+            vec![DISCONNECT_ACTION_ID, DISCONNECT_PURGE_ACTION_ID]
+        );
+    }
+
+    #[test]
+    fn test_home_view_not_connected() {
+        let view = home_view(false, "https://example.com/connect");
+        assert_eq!(action_ids(&view), vec!["connect_spotify"]);
+        assert_eq!(
+            view["blocks"][1]["accessory"]["url"],
+            "https://example.com/connect"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::config::Config;
+use crate::db::repository;
+use sqlx::{PgExecutor, PgPool};
+
+/// Whether users from other organizations may use the bot
+///
+/// Users in Slack Connect channels can belong to a workspace outside the
+/// installation. Selected by `SLACK_EXTERNAL_USERS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalUserPolicy {
+    /// External users can connect Spotify and save tracks like anyone else
+    Allow,
+    /// Mentions by external users get a 🚫 reaction and are not processed
+    Deny,
+}
+
+impl ExternalUserPolicy {
+    /// Policy selected by `SLACK_EXTERNAL_USERS`
+    ///
+    /// # Errors
+    /// Returns error if the configured policy is unknown
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        match config.slack_external_users.as_str() {
+            "allow" => Ok(ExternalUserPolicy::Allow),
+            "deny" => Ok(ExternalUserPolicy::Deny),
+            other => anyhow::bail!(
+                "Unknown SLACK_EXTERNAL_USERS {:?} (expected allow or deny)",
+                other
+            ),
+        }
+    }
+}
+
+/// Workspace key a Slack user's Spotify link is stored under
+///
+/// Enterprise Grid user IDs are unique across the org, so Grid users are keyed
+/// by enterprise ID and recognized in every workspace of the org. Everyone else
+/// is keyed by their workspace.
+///
+/// # Arguments
+/// * `team_id` - User's workspace
+/// * `enterprise_id` - Enterprise Grid org of the workspace, if any
+pub fn identity_workspace(team_id: &str, enterprise_id: Option<&str>) -> String {
+    enterprise_id.unwrap_or(team_id).to_string()
+}
+
+/// Move a Grid workspace's earlier rows to the enterprise key
+///
+/// Links and saves from before Grid users were keyed by enterprise ID are
+/// stored under the workspace. Called whenever a workspace is seen with its
+/// enterprise ID; does nothing outside Grid or once the workspace was moved.
+///
+/// # Arguments
+/// * `executor` - Database pool, connection or transaction
+/// * `team_id` - Slack workspace ID
+/// * `enterprise_id` - Enterprise Grid org of the workspace, if any
+///
+/// # Errors
+/// Returns error if the database update fails
+pub async fn rekey_grid_workspace<'e>(
+    executor: impl PgExecutor<'e>,
+    team_id: &str,
+    enterprise_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let Some(enterprise_id) = enterprise_id.filter(|enterprise_id| *enterprise_id != team_id)
+    else {
+        return Ok(());
+    };
+
+    if repository::rekey_grid_workspace(executor, team_id, enterprise_id).await? {
+        tracing::info!(
+            team_id = %team_id,
+            enterprise_id = %enterprise_id,
+            "Moved Grid workspace's users to the enterprise key"
+        );
+    }
+    Ok(())
+}
+
+/// Workspaces involved in an event_callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTeams {
+    /// Workspace whose installation received the event
+    pub team_id: String,
+    /// Enterprise Grid org of that workspace
+    pub enterprise_id: Option<String>,
+    /// Whether the app is installed org-wide for the Grid org
+    pub is_enterprise_install: bool,
+    /// Workspace that owns the channel (another org's in Slack Connect)
+    pub context_team_id: Option<String>,
+    /// Whether the channel is shared with another organization
+    pub is_ext_shared_channel: bool,
+    /// Enterprise Grid org of the acting user's workspace, when that isn't
+    /// `team_id` (see `resolve_user_enterprise`)
+    pub user_enterprise_id: Option<String>,
+}
+
+impl EventTeams {
+    /// Key of the installation that received the event
+    ///
+    /// Org-wide installs are stored under the enterprise ID.
+    pub fn installation_id(&self) -> &str {
+        match &self.enterprise_id {
+            Some(enterprise_id) if self.is_enterprise_install => enterprise_id,
+            _ => &self.team_id,
+        }
+    }
+
+    /// Look up the Grid org of the acting user's workspace
+    ///
+    /// Slack doesn't say which org `user_team` belongs to, so it's taken from
+    /// the workspaces seen with an enterprise ID (installs and events, see
+    /// `rekey_grid_workspace`). Only looked up where it matters: for a user
+    /// from another workspace in a channel shared with another organization.
+    ///
+    /// # Errors
+    /// Returns error if the database lookup fails
+    pub async fn resolve_user_enterprise(
+        &mut self,
+        pool: &PgPool,
+        user_team: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let Some(team) = user_team.filter(|team| *team != self.team_id) else {
+            return Ok(());
+        };
+        if self.is_ext_shared_channel {
+            self.user_enterprise_id = repository::get_grid_enterprise(pool, team).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether a user from `user_team` is outside the installation's organization
+    ///
+    /// Only users in channels shared with another organization can be
+    /// external. A member of another workspace of the installation's Grid org
+    /// is not, once `resolve_user_enterprise` found the org; a workspace never
+    /// seen with its enterprise ID counts as external.
+    pub fn is_external(&self, user_team: Option<&str>) -> bool {
+        let same_enterprise = self.enterprise_id.is_some()
+            && self.user_enterprise_id.as_deref() == self.enterprise_id.as_deref();
+        self.is_ext_shared_channel
+            && !same_enterprise
+            && user_team.is_some_and(|team| team != self.team_id)
+    }
+
+    /// Identity workspace key for a user (see `identity_workspace`)
+    ///
+    /// External users are keyed by their own workspace, or by their Grid org
+    /// if it's known.
+    pub fn identity_workspace(&self, user_team: Option<&str>) -> String {
+        match user_team {
+            Some(team) if self.is_external(Some(team)) => {
+                identity_workspace(team, self.user_enterprise_id.as_deref())
+            }
+            _ => identity_workspace(&self.team_id, self.enterprise_id.as_deref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn teams(enterprise_id: Option<&str>, is_ext_shared_channel: bool) -> EventTeams {
+        EventTeams {
+            team_id: "T_HOME".to_string(),
+            enterprise_id: enterprise_id.map(str::to_string),
+            is_enterprise_install: false,
+            context_team_id: None,
+            is_ext_shared_channel,
+            user_enterprise_id: None,
+        }
+    }
+
+    #[test]
+    fn test_identity_workspace_grid_users_keyed_by_enterprise() {
+        assert_eq!(identity_workspace("T1", None), "T1");
+        assert_eq!(identity_workspace("T1", Some("E1")), "E1");
+
+        // Same person, two workspaces of the org
+        let grid = teams(Some("E1"), false);
+        assert_eq!(grid.identity_workspace(Some("T_HOME")), "E1");
+        assert_eq!(grid.identity_workspace(Some("T_SIBLING")), "E1");
+        assert_eq!(grid.identity_workspace(None), "E1");
+    }
+
+    #[test]
+    fn test_external_users_in_shared_channels() {
+        let shared = teams(None, true);
+        assert!(shared.is_external(Some("T_PARTNER")));
+        assert!(!shared.is_external(Some("T_HOME")));
+        assert!(!shared.is_external(None));
+        assert_eq!(shared.identity_workspace(Some("T_PARTNER")), "T_PARTNER");
+        assert_eq!(shared.identity_workspace(Some("T_HOME")), "T_HOME");
+
+        // Another user_team outside a shared channel is the same org
+        assert!(!teams(None, false).is_external(Some("T_PARTNER")));
+    }
+
+    #[test]
+    fn test_grid_sibling_in_shared_channel_is_internal() {
+        // A sibling workspace's member, once its org is known
+        let mut shared = teams(Some("E1"), true);
+        shared.user_enterprise_id = Some("E1".to_string());
+        assert!(!shared.is_external(Some("T_SIBLING")));
+        assert_eq!(shared.identity_workspace(Some("T_SIBLING")), "E1");
+
+        // Another Grid org is still external, keyed by its enterprise
+        shared.user_enterprise_id = Some("E_PARTNER".to_string());
+        assert!(shared.is_external(Some("T_PARTNER")));
+        assert_eq!(shared.identity_workspace(Some("T_PARTNER")), "E_PARTNER");
+
+        // Outside Grid, matching "no enterprise" doesn't make users internal
+        let shared = teams(None, true);
+        assert!(shared.is_external(Some("T_PARTNER")));
+    }
+
+    #[sqlx::test]
+    async fn test_resolve_user_enterprise(pool: PgPool) -> sqlx::Result<()> {
+        repository::rekey_grid_workspace(&pool, "T_SIBLING", "E1").await?;
+
+        let mut shared = teams(Some("E1"), true);
+        shared
+            .resolve_user_enterprise(&pool, Some("T_SIBLING"))
+            .await?;
+        assert_eq!(shared.user_enterprise_id.as_deref(), Some("E1"));
+        assert!(!shared.is_external(Some("T_SIBLING")));
+
+        let mut shared = teams(Some("E1"), true);
+        shared
+            .resolve_user_enterprise(&pool, Some("T_UNKNOWN"))
+            .await?;
+        assert_eq!(shared.user_enterprise_id, None);
+        assert!(shared.is_external(Some("T_UNKNOWN")));
+
+        // Nothing to look up outside shared channels
+        let mut grid = teams(Some("E1"), false);
+        grid.resolve_user_enterprise(&pool, Some("T_SIBLING"))
+            .await?;
+        assert_eq!(grid.user_enterprise_id, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_installation_id() {
+        let mut grid = teams(Some("E1"), false);
+        assert_eq!(grid.installation_id(), "T_HOME");
+        grid.is_enterprise_install = true;
+        assert_eq!(grid.installation_id(), "E1");
+    }
+
+    #[test]
+    fn test_external_user_policy_from_config() {
+        let mut config = crate::config::test_config();
+        assert_eq!(
+            ExternalUserPolicy::from_config(&config).unwrap(),
+            ExternalUserPolicy::Allow
+        );
+        config.slack_external_users = "deny".to_string();
+        assert_eq!(
+            ExternalUserPolicy::from_config(&config).unwrap(),
+            ExternalUserPolicy::Deny
+        );
+        config.slack_external_users = "sometimes".to_string();
+        assert!(ExternalUserPolicy::from_config(&config).is_err());
+    }
+}
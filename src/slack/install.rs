@@ -0,0 +1,373 @@
+use crate::crypto::TokenCipher;
+use crate::db::repository::{InstallationParams, upsert_installation};
+use crate::error::AppError;
+use crate::slack::client::{SlackClient, SlackError};
+use crate::slack::identity::rekey_grid_workspace;
+use crate::spotify::oauth::generate_state_token;
+use crate::spotify::routes::escape_html;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, header},
+    response::{Html, IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+/// Cookie binding an install attempt to the browser that started it
+const STATE_COOKIE: &str = "slack_install_state";
+
+/// How long an install attempt stays valid
+const STATE_COOKIE_MAX_AGE_SECS: u64 = 600;
+
+/// Application state for the Slack install routes
+#[derive(Clone)]
+pub struct SlackInstallState {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Comma-separated bot scopes requested on install
+    pub scopes: String,
+    /// Slack's OAuth authorize page (e.g., "https://slack.com/oauth/v2/authorize")
+    pub authorize_url: String,
+    /// Public base URL, for the OAuth redirect URI
+    pub base_url: String,
+    pub db: PgPool,
+    pub cipher: TokenCipher,
+    pub slack_client: SlackClient,
+}
+
+impl SlackInstallState {
+    /// Redirect URI registered with Slack for the install flow
+    fn redirect_uri(&self) -> String {
+        format!(
+            "{}/slack/oauth/callback",
+            self.base_url.trim_end_matches('/')
+        )
+    }
+
+    /// Slack authorize URL for an install attempt
+    fn install_url(&self, state: &str) -> String {
+        format!(
+            "{}?{}",
+            self.authorize_url,
+            oauth2::url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("client_id", &self.client_id)
+                .append_pair("scope", &self.scopes)
+                .append_pair("redirect_uri", &self.redirect_uri())
+                .append_pair("state", state)
+                .finish()
+        )
+    }
+
+    /// `Set-Cookie` value for the state cookie (`Max-Age=0` clears it)
+    fn state_cookie(&self, value: &str, max_age_secs: u64) -> String {
+        let mut cookie = format!(
+            "{}={}; Path=/slack/oauth; Max-Age={}; HttpOnly; SameSite=Lax",
+            STATE_COOKIE, value, max_age_secs
+        );
+        if self.base_url.starts_with("https://") {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+}
+
+/// Response from Slack's `oauth.v2.access`
+#[derive(Debug, Deserialize)]
+pub struct OAuthV2AccessResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+    /// Bot token (xoxb-...)
+    pub access_token: Option<String>,
+    /// Comma-separated scopes granted to the bot
+    pub scope: Option<String>,
+    pub bot_user_id: Option<String>,
+    pub app_id: Option<String>,
+    /// Workspace installed in (absent for an org-wide install)
+    pub team: Option<OAuthTeam>,
+    /// Enterprise Grid org of the workspace, or the org for an org-wide install
+    pub enterprise: Option<OAuthTeam>,
+    #[serde(default)]
+    pub is_enterprise_install: bool,
+    pub authed_user: Option<OAuthAuthedUser>,
+}
+
+impl OAuthV2AccessResponse {
+    /// Workspace (or, for an org-wide install, Grid org) the app was installed in
+    pub fn installed_team(&self) -> Option<&OAuthTeam> {
+        if self.is_enterprise_install {
+            self.enterprise.as_ref()
+        } else {
+            self.team.as_ref()
+        }
+    }
+}
+
+/// Workspace or Enterprise Grid org the app was installed in
+#[derive(Debug, Deserialize)]
+pub struct OAuthTeam {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+/// User who installed the app
+#[derive(Debug, Deserialize)]
+pub struct OAuthAuthedUser {
+    pub id: String,
+}
+
+/// Start installing the Slack app in a workspace
+///
+/// # Endpoint
+/// GET /slack/install
+///
+/// # Flow
+/// 1. Generate a random state and set it in a short-lived cookie
+/// 2. Redirect to Slack's authorize page with the bot scopes
+///
+/// # Returns
+/// 303 redirect to Slack
+pub async fn install(State(state): State<SlackInstallState>) -> impl IntoResponse {
+    let install_state = generate_state_token();
+
+    tracing::info!("Starting Slack app install flow");
+
+    (
+        [(
+            header::SET_COOKIE,
+            state.state_cookie(&install_state, STATE_COOKIE_MAX_AGE_SECS),
+        )],
+        Redirect::to(&state.install_url(&install_state)),
+    )
+}
+
+/// Query parameters for /slack/oauth/callback
+///
+/// Slack sends `code` on success, or `error` (e.g., "access_denied") if the
+/// installer cancelled.
+#[derive(Debug, Deserialize)]
+pub struct InstallCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Finish installing the Slack app
+///
+/// # Endpoint
+/// GET /slack/oauth/callback?code=<CODE>&state=<STATE>
+///
+/// # Flow
+/// 1. Check `state` against the cookie set by `/slack/install` (CSRF protection)
+/// 2. Exchange the code with `oauth.v2.access`
+/// 3. Store the workspace's bot token, bot user ID and scopes in `installations`
+///    (org-wide Enterprise Grid installs are stored under the enterprise ID)
+///
+/// # Returns
+/// HTML page confirming the install, or explaining that it was cancelled
+///
+/// # Errors
+/// - 400 Bad Request if the state doesn't match or the code is missing
+/// - 502 Bad Gateway if Slack rejects the code
+/// - 500 Internal Server Error if storing the installation fails
+pub async fn oauth_callback(
+    State(state): State<SlackInstallState>,
+    headers: HeaderMap,
+    Query(params): Query<InstallCallbackQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let expected = state_from_cookie(&headers).ok_or(AppError::OAuthStateNotFound)?;
+    if params.state.as_deref() != Some(expected) {
+        return Err(AppError::OAuthStateNotFound);
+    }
+    let clear_cookie = [(header::SET_COOKIE, state.state_cookie("", 0))];
+
+    if let Some(error) = params.error {
+        tracing::info!(error = %error, "Slack app install was not authorized");
+        let page = render_install_page(
+            "⚠️",
+            "Slack App Not Installed",
+            &format!("Slack didn't install savethebeat ({}).", error),
+        );
+        return Ok((clear_cookie, Html(page)));
+    }
+
+    let code = params
+        .code
+        .ok_or_else(|| AppError::BadRequest("Missing code parameter".to_string()))?;
+
+    let access = state
+        .slack_client
+        .oauth_v2_access(
+            &state.client_id,
+            &state.client_secret,
+            &code,
+            &state.redirect_uri(),
+        )
+        .await?;
+
+    let (Some(bot_token), Some(bot_user_id), Some(team)) = (
+        &access.access_token,
+        &access.bot_user_id,
+        access.installed_team(),
+    ) else {
+        return Err(SlackError::MissingField {
+            method: "oauth.v2.access",
+            field: "the bot token or team",
+        }
+        .into());
+    };
+
+    let installation = upsert_installation(
+        &state.db,
+        &state.cipher,
+        InstallationParams {
+            team_id: &team.id,
+            team_name: team.name.as_deref(),
+            enterprise_id: access
+                .enterprise
+                .as_ref()
+                .map(|enterprise| enterprise.id.as_str()),
+            is_enterprise_install: access.is_enterprise_install,
+            app_id: access.app_id.as_deref(),
+            bot_user_id,
+            bot_token,
+            scopes: access.scope.as_deref().unwrap_or_default(),
+            installed_by_user_id: access.authed_user.as_ref().map(|user| user.id.as_str()),
+        },
+    )
+    .await?;
+    rekey_grid_workspace(
+        &state.db,
+        &installation.team_id,
+        installation.enterprise_id.as_deref(),
+    )
+    .await?;
+
+    tracing::info!(
+        team_id = %installation.team_id,
+        enterprise_id = ?installation.enterprise_id,
+        is_enterprise_install = installation.is_enterprise_install,
+        bot_user_id = %installation.bot_user_id,
+        scopes = %installation.scopes,
+        "Installed Slack app"
+    );
+
+    let page = render_install_page(
+        "✅",
+        "Slack App Installed",
+        &format!(
+            "savethebeat is installed in {}. Mention it in a thread with a Spotify track link to save the track.",
+            installation
+                .team_name
+                .as_deref()
+                .unwrap_or(&installation.team_id)
+        ),
+    );
+    Ok((clear_cookie, Html(page)))
+}
+
+/// Value of the install state cookie, if present
+fn state_from_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == STATE_COOKIE)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+/// Render the install result page (values are HTML-escaped)
+fn render_install_page(icon: &str, title: &str, message: &str) -> String {
+    const TEMPLATE: &str = include_str!("../../templates/slack_install.html");
+
+    TEMPLATE
+        .replace("{{ICON}}", icon)
+        .replace("{{TITLE}}", &escape_html(title))
+        .replace("{{MESSAGE}}", &escape_html(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_config;
+
+    fn state(base_url: &str) -> SlackInstallState {
+        let config = test_config();
+        SlackInstallState {
+            client_id: "123.456".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: "app_mentions:read,chat:write".to_string(),
+            authorize_url: "https://slack.com/oauth/v2/authorize".to_string(),
+            base_url: base_url.to_string(),
+            db: sqlx::postgres::PgPoolOptions::new()
+                .connect_lazy(&config.database_url)
+                .unwrap(),
+            cipher: TokenCipher::default(),
+            slack_client: SlackClient::new(reqwest::Client::new(), &config.slack_api_base_url),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_install_url() {
+        let url = state("https://beats.example.com/").install_url("abc");
+        assert_eq!(
+            url,
+            "https://slack.com/oauth/v2/authorize?client_id=123.456&scope=app_mentions%3Aread%2Cchat%3Awrite&redirect_uri=https%3A%2F%2Fbeats.example.com%2Fslack%2Foauth%2Fcallback&state=abc"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_state_cookie_secure_only_over_https() {
+        assert!(
+            state("https://beats.example.com")
+                .state_cookie("abc", 600)
+                .ends_with("; Secure")
+        );
+        assert_eq!(
+            state("http://localhost:3000").state_cookie("abc", 600),
+            "slack_install_state=abc; Path=/slack/oauth; Max-Age=600; HttpOnly; SameSite=Lax"
+        );
+    }
+
+    #[test]
+    fn test_installed_team_for_org_wide_install() {
+        let access: OAuthV2AccessResponse = serde_json::from_str(
+            r#"{
+                "ok": true,
+                "access_token": "xoxb-org",
+                "bot_user_id": "UBOT",
+                "team": null,
+                "enterprise": {"id": "E123", "name": "Example Org"},
+                "is_enterprise_install": true
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(access.installed_team().unwrap().id, "E123");
+
+        let access: OAuthV2AccessResponse = serde_json::from_str(
+            r#"{
+                "ok": true,
+                "team": {"id": "T123"},
+                "enterprise": {"id": "E123"}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(access.installed_team().unwrap().id, "T123");
+    }
+
+    #[test]
+    fn test_state_from_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            "other=1; slack_install_state=abc".parse().unwrap(),
+        );
+        assert_eq!(state_from_cookie(&headers), Some("abc"));
+
+        headers.insert(header::COOKIE, "slack_install_state=".parse().unwrap());
+        assert_eq!(state_from_cookie(&headers), None);
+        assert_eq!(state_from_cookie(&HeaderMap::new()), None);
+    }
+}
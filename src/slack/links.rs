@@ -0,0 +1,236 @@
+use crate::slack::events::{MessageBlock, SlackMessage};
+use crate::spotify::parser::extract_track_id;
+
+/// Collect the text of a message that may contain links, in document order
+///
+/// Walks the message `text`, then its blocks (depth first), attachments and
+/// files. Link URLs come before their labels. The same link often appears
+/// more than once (e.g., in `text` and in the rich-text blocks).
+///
+/// # Arguments
+/// * `message` - Message from `conversations.replies` or `conversations.history`
+///
+/// # Returns
+/// Strings to search for links, e.g. with `extract_track_id`
+pub fn link_candidates(message: &SlackMessage) -> Vec<&str> {
+    let mut candidates = vec![message.text.as_str()];
+
+    for block in &message.blocks {
+        collect_block(block, &mut candidates);
+    }
+
+    for attachment in &message.attachments {
+        candidates.extend(
+            [
+                &attachment.pretext,
+                &attachment.title_link,
+                &attachment.title,
+                &attachment.text,
+                &attachment.from_url,
+                &attachment.original_url,
+                &attachment.fallback,
+            ]
+            .into_iter()
+            .flatten()
+            .map(String::as_str),
+        );
+        for shared in &attachment.message_blocks {
+            for block in &shared.message.blocks {
+                collect_block(block, &mut candidates);
+            }
+        }
+    }
+
+    for file in &message.files {
+        candidates.extend(
+            [&file.title, &file.external_url, &file.preview]
+                .into_iter()
+                .flatten()
+                .map(String::as_str),
+        );
+    }
+
+    candidates.retain(|candidate| !candidate.is_empty());
+    candidates
+}
+
+fn collect_block<'a>(block: &'a MessageBlock, candidates: &mut Vec<&'a str>) {
+    if let Some(url) = &block.url {
+        candidates.push(url);
+    }
+    if let Some(text) = &block.text {
+        candidates.push(text.as_str());
+    }
+    candidates.extend(block.fields.iter().map(|field| field.as_str()));
+    for element in &block.elements {
+        collect_block(element, candidates);
+    }
+    if let Some(accessory) = &block.accessory {
+        collect_block(accessory, candidates);
+    }
+}
+
+/// Spotify track ID of the first track linked in a message
+pub fn message_track(message: &SlackMessage) -> Option<String> {
+    link_candidates(message)
+        .into_iter()
+        .find_map(extract_track_id)
+}
+
+/// Find the first Spotify track linked in a list of messages
+///
+/// Like `find_first_track`, but searches everything `link_candidates` collects.
+///
+/// # Arguments
+/// * `messages` - Messages in chronological order
+///
+/// # Returns
+/// The first track ID found, None if no track links found
+pub fn find_first_track_in_messages(messages: &[SlackMessage]) -> Option<String> {
+    messages.iter().find_map(message_track)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(json: serde_json::Value) -> SlackMessage {
+        let mut message = serde_json::json!({ "ts": "1.1", "user": "U1", "text": "" });
+        for (key, value) in json.as_object().unwrap() {
+            message[key] = value.clone();
+        }
+        serde_json::from_value(message).unwrap()
+    }
+
+    #[test]
+    fn test_link_in_rich_text_block() {
+        let message = message(serde_json::json!({
+            "text": "",
+            "blocks": [{
+                "type": "rich_text",
+                "block_id": "abc",
+                "elements": [{
+                    "type": "rich_text_section",
+                    "elements": [
+                        { "type": "text", "text": "listen to " },
+                        {
+                            "type": "link",
+                            "url": "https://open.spotify.com/track/111?si=x",
+                            "text": "this"
+                        }
+                    ]
+                }]
+            }]
+        }));
+        assert_eq!(message_track(&message), Some("111".to_string()));
+        assert_eq!(
+            link_candidates(&message),
+            vec![
+                "listen to ",
+                "https://open.spotify.com/track/111?si=x",
+                "this"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_in_section_block_and_button() {
+        let message = message(serde_json::json!({
+            "blocks": [{
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": "Now playing" },
+                "accessory": {
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": "Open" },
+                    "url": "https://open.spotify.com/track/222"
+                }
+            }]
+        }));
+        assert_eq!(message_track(&message), Some("222".to_string()));
+    }
+
+    #[test]
+    fn test_link_in_bot_attachment() {
+        let message = message(serde_json::json!({
+            "text": "Track of the day",
+            "attachments": [{
+                "title": "Song",
+                "title_link": "https://open.spotify.com/track/333",
+                "fallback": "Song by Artist"
+            }]
+        }));
+        assert_eq!(message_track(&message), Some("333".to_string()));
+    }
+
+    #[test]
+    fn test_link_in_forwarded_message() {
+        let message = message(serde_json::json!({
+            "attachments": [{
+                "is_share": true,
+                "from_url": "https://example.slack.com/archives/C1/p1",
+                "message_blocks": [{
+                    "team": "T1",
+                    "channel": "C1",
+                    "ts": "1.0",
+                    "message": {
+                        "blocks": [{
+                            "type": "rich_text",
+                            "elements": [{
+                                "type": "rich_text_section",
+                                "elements": [{
+                                    "type": "link",
+                                    "url": "https://open.spotify.com/track/444"
+                                }]
+                            }]
+                        }]
+                    }
+                }]
+            }]
+        }));
+        assert_eq!(message_track(&message), Some("444".to_string()));
+    }
+
+    #[test]
+    fn test_link_in_unfurl_and_file() {
+        let unfurl = message(serde_json::json!({
+            "attachments": [{ "from_url": "https://open.spotify.com/track/555" }]
+        }));
+        assert_eq!(message_track(&unfurl), Some("555".to_string()));
+
+        let file = message(serde_json::json!({
+            "files": [{ "title": "notes", "preview": "spotify:track:666" }]
+        }));
+        assert_eq!(message_track(&file), Some("666".to_string()));
+    }
+
+    #[test]
+    fn test_document_order() {
+        // Text comes first, then blocks, attachments and files
+        let message = message(serde_json::json!({
+            "text": "no link",
+            "blocks": [{ "type": "rich_text", "elements": [{ "type": "link", "url": "https://open.spotify.com/track/B" }] }],
+            "attachments": [{ "title_link": "https://open.spotify.com/track/C" }],
+            "files": [{ "external_url": "https://open.spotify.com/track/D" }]
+        }));
+        assert_eq!(message_track(&message), Some("B".to_string()));
+
+        let messages = vec![
+            message.clone(),
+            serde_json::from_str(
+                r#"{"ts": "1.2", "user": "U1", "text": "https://open.spotify.com/track/A"}"#,
+            )
+            .unwrap(),
+        ];
+        assert_eq!(
+            find_first_track_in_messages(&messages),
+            Some("B".to_string())
+        );
+    }
+
+    #[test]
+    fn test_no_links() {
+        let message = message(serde_json::json!({ "text": "hello" }));
+        assert_eq!(message_track(&message), None);
+        assert_eq!(find_first_track_in_messages(&[]), None);
+    }
+}
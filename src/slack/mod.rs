@@ -1,4 +1,13 @@
 pub mod client;
+pub mod commands;
 pub mod events;
+pub mod home;
+pub mod identity;
+pub mod install;
+pub mod links;
 pub mod routes;
+pub mod socket_mode;
+pub mod tokens;
+pub mod unfurl;
+pub mod uninstall;
 pub mod verification;
@@ -0,0 +1,323 @@
+use crate::config::Config;
+use crate::error::AppError;
+use crate::slack::client::SlackError;
+use crate::slack::commands::{InteractionPayload, SlashCommand};
+use crate::slack::events::SlackEventRequest;
+use crate::slack::routes::{
+    EventDelivery, SlackState, dispatch_event, run_interaction, run_slash_command,
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Delay before the first reconnect after a failed connection
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Socket Mode settings
+#[derive(Debug, Clone)]
+pub struct SocketModeConfig {
+    /// App-level token with `connections:write` (xapp-...)
+    pub app_token: String,
+    /// Number of concurrent connections (Slack spreads envelopes across them)
+    pub connections: usize,
+    /// Longest delay between reconnect attempts
+    pub max_backoff: Duration,
+}
+
+impl SocketModeConfig {
+    /// Socket Mode settings if `SLACK_TRANSPORT=socket`
+    ///
+    /// # Returns
+    /// None when events arrive over HTTP
+    ///
+    /// # Errors
+    /// Returns error if the transport is unknown, or Socket Mode is selected
+    /// without `SLACK_APP_TOKEN`
+    pub fn from_config(config: &Config) -> anyhow::Result<Option<Self>> {
+        match config.slack_transport.as_str() {
+            "http" => Ok(None),
+            "socket" => {
+                let Some(app_token) = config.slack_app_token.clone() else {
+                    anyhow::bail!("SLACK_TRANSPORT=socket requires SLACK_APP_TOKEN");
+                };
+                Ok(Some(Self {
+                    app_token,
+                    connections: config.slack_socket_connections.max(1),
+                    max_backoff: Duration::from_secs(config.slack_socket_max_backoff_secs)
+                        .max(MIN_BACKOFF),
+                }))
+            }
+            other => anyhow::bail!(
+                "Unknown SLACK_TRANSPORT {:?} (expected http or socket)",
+                other
+            ),
+        }
+    }
+}
+
+/// A message received over a Socket Mode connection
+///
+/// `hello` and `disconnect` are connection control messages; everything else
+/// is an envelope that must be acknowledged with its `envelope_id`.
+#[derive(Debug, Deserialize)]
+pub struct SocketEnvelope {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub envelope_id: Option<String>,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    /// 0 on first delivery
+    #[serde(default)]
+    pub retry_attempt: u32,
+    #[serde(default)]
+    pub retry_reason: Option<String>,
+    /// Why Slack is closing the connection (`disconnect` only)
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl SocketEnvelope {
+    /// Redelivery details, in the same shape as the HTTP retry headers
+    fn delivery(&self) -> EventDelivery {
+        EventDelivery {
+            retry_num: (self.retry_attempt > 0).then(|| self.retry_attempt.to_string()),
+            retry_reason: self
+                .retry_reason
+                .clone()
+                .filter(|reason| !reason.is_empty()),
+        }
+    }
+}
+
+/// Start the Socket Mode connections
+///
+/// Each connection runs in its own task and reconnects on its own: right away
+/// when Slack asks (a `disconnect` message), with exponential backoff up to
+/// `max_backoff` when connecting fails.
+pub fn spawn_socket_mode(state: SlackState, config: SocketModeConfig) -> Vec<JoinHandle<()>> {
+    (0..config.connections)
+        .map(|connection| tokio::spawn(run_connection(state.clone(), config.clone(), connection)))
+        .collect()
+}
+
+/// Keep one Socket Mode connection open
+async fn run_connection(state: SlackState, config: SocketModeConfig, connection: usize) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        match serve_connection(&state, &config.app_token, connection).await {
+            Ok(()) => {
+                backoff = MIN_BACKOFF;
+                tracing::info!(connection, "Socket Mode connection closed, reconnecting");
+            }
+            Err(e) => {
+                tracing::warn!(
+                    connection,
+                    error = ?e,
+                    retry_in = ?backoff,
+                    "Socket Mode connection failed"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+            }
+        }
+    }
+}
+
+/// Open a connection and handle envelopes until it closes
+///
+/// # Errors
+/// - `SlackError` if no connection could be opened, it failed, or it closed
+///   before Slack's `hello`
+async fn serve_connection(
+    state: &SlackState,
+    app_token: &str,
+    connection: usize,
+) -> Result<(), SlackError> {
+    let url = state.slack_client.open_socket_connection(app_token).await?;
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .map_err(|e| SlackError::Socket(format!("failed to connect: {}", e)))?;
+
+    let mut greeted = false;
+    while let Some(message) = socket.next().await {
+        let message = message.map_err(|e| SlackError::Socket(e.to_string()))?;
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            // Pings are answered by the WebSocket layer
+            _ => continue,
+        };
+
+        let envelope: SocketEnvelope = match serde_json::from_str(text.as_str()) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::warn!(connection, error = %e, "Ignoring malformed Socket Mode message");
+                continue;
+            }
+        };
+
+        match envelope.kind.as_str() {
+            "hello" => {
+                greeted = true;
+                tracing::info!(connection, "Socket Mode connected");
+            }
+            "disconnect" => {
+                tracing::info!(connection, reason = ?envelope.reason, "Slack closed Socket Mode connection");
+                break;
+            }
+            _ => {
+                if let Some(ack) = handle_envelope(state, envelope).await {
+                    socket
+                        .send(Message::Text(ack.to_string().into()))
+                        .await
+                        .map_err(|e| {
+                            SlackError::Socket(format!("failed to acknowledge envelope: {}", e))
+                        })?;
+                }
+            }
+        }
+    }
+
+    if greeted {
+        Ok(())
+    } else {
+        Err(SlackError::Socket("closed before hello".to_string()))
+    }
+}
+
+/// Handle an envelope and build its acknowledgement
+///
+/// Envelopes that fail for a reason a redelivery could fix (e.g., the job
+/// couldn't be queued) are not acknowledged, so Slack delivers them again, as
+/// it does when the HTTP endpoint returns an error. Malformed payloads are
+/// acknowledged and dropped.
+///
+/// # Returns
+/// The acknowledgement to send, or None to leave the envelope unacknowledged
+pub async fn handle_envelope(
+    state: &SlackState,
+    envelope: SocketEnvelope,
+) -> Option<serde_json::Value> {
+    let envelope_id = envelope.envelope_id.clone()?;
+
+    let response = match process_envelope(state, envelope).await {
+        Ok(response) => response,
+        Err(AppError::BadRequest(message)) => {
+            tracing::warn!(envelope_id = %envelope_id, error = %message, "Dropping invalid envelope");
+            None
+        }
+        Err(e) => {
+            tracing::error!(
+                envelope_id = %envelope_id,
+                error = ?e,
+                "Failed to handle envelope, leaving it for redelivery"
+            );
+            return None;
+        }
+    };
+
+    let mut ack = serde_json::json!({ "envelope_id": envelope_id });
+    if let Some(response) = response {
+        ack["payload"] = response;
+    }
+    Some(ack)
+}
+
+/// Route an envelope to the handler shared with the HTTP endpoints
+///
+/// # Returns
+/// Response payload for the acknowledgement, if the envelope type has one
+async fn process_envelope(
+    state: &SlackState,
+    envelope: SocketEnvelope,
+) -> Result<Option<serde_json::Value>, AppError> {
+    match envelope.kind.as_str() {
+        "events_api" => {
+            let delivery = envelope.delivery();
+            let event_request: SlackEventRequest = serde_json::from_value(envelope.payload)
+                .map_err(|e| AppError::BadRequest(format!("Invalid event: {}", e)))?;
+            dispatch_event(state, event_request, delivery).await?;
+            Ok(None)
+        }
+        "slash_commands" => {
+            let command = SlashCommand::from_json(&envelope.payload)?;
+            Ok(Some(run_slash_command(state, command).await?))
+        }
+        "interactive" => {
+            let payload = InteractionPayload::from_json(envelope.payload)?;
+            run_interaction(state, payload).await?;
+            Ok(None)
+        }
+        other => {
+            tracing::debug!(kind = other, "Ignoring Socket Mode envelope");
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_config;
+
+    #[test]
+    fn test_socket_mode_config_from_config() {
+        let mut config = test_config();
+        assert!(SocketModeConfig::from_config(&config).unwrap().is_none());
+
+        config.slack_transport = "socket".to_string();
+        assert!(SocketModeConfig::from_config(&config).is_err());
+
+        config.slack_app_token = Some("xapp-1".to_string());
+        config.slack_socket_connections = 0;
+        let socket = SocketModeConfig::from_config(&config).unwrap().unwrap();
+        assert_eq!(socket.app_token, "xapp-1");
+        assert_eq!(socket.connections, 1);
+        assert_eq!(socket.max_backoff, Duration::from_secs(60));
+
+        config.slack_transport = "carrier-pigeon".to_string();
+        assert!(SocketModeConfig::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_envelope_delivery() {
+        let envelope: SocketEnvelope = serde_json::from_str(
+            r#"{
+                "envelope_id": "env-1",
+                "type": "events_api",
+                "payload": {},
+                "accepts_response_payload": false,
+                "retry_attempt": 2,
+                "retry_reason": "timeout"
+            }"#,
+        )
+        .unwrap();
+        let delivery = envelope.delivery();
+        assert_eq!(delivery.retry_num.as_deref(), Some("2"));
+        assert_eq!(delivery.retry_reason.as_deref(), Some("timeout"));
+
+        let first: SocketEnvelope = serde_json::from_str(
+            r#"{"envelope_id": "env-2", "type": "events_api", "retry_attempt": 0, "retry_reason": ""}"#,
+        )
+        .unwrap();
+        let delivery = first.delivery();
+        assert!(delivery.retry_num.is_none());
+        assert!(delivery.retry_reason.is_none());
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let hello: SocketEnvelope =
+            serde_json::from_str(r#"{"type": "hello", "num_connections": 1}"#).unwrap();
+        assert_eq!(hello.kind, "hello");
+        assert!(hello.envelope_id.is_none());
+
+        let disconnect: SocketEnvelope =
+            serde_json::from_str(r#"{"type": "disconnect", "reason": "refresh_requested"}"#)
+                .unwrap();
+        assert_eq!(disconnect.reason.as_deref(), Some("refresh_requested"));
+    }
+}
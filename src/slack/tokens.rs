@@ -0,0 +1,162 @@
+use crate::crypto::TokenCipher;
+use crate::db::repository::get_installation;
+use crate::error::AppError;
+use sqlx::PgPool;
+
+/// Resolves the bot token to use for a workspace
+///
+/// Workspaces that installed the app through `/slack/install` use their own
+/// bot token from `installations`, or their Enterprise Grid org's token if the
+/// app was installed org-wide. `SLACK_BOT_TOKEN`, if set, is the fallback
+/// for a single workspace where the app was installed by hand. Cheap to clone.
+#[derive(Clone)]
+pub struct BotTokens {
+    db: PgPool,
+    cipher: TokenCipher,
+    fallback: Option<String>,
+}
+
+impl BotTokens {
+    /// Create a resolver
+    ///
+    /// # Arguments
+    /// * `db` - Database connection pool
+    /// * `cipher` - Token cipher holding the installations' keys
+    /// * `fallback` - Bot token for workspaces without an installation row
+    pub fn new(db: PgPool, cipher: TokenCipher, fallback: Option<String>) -> Self {
+        Self {
+            db,
+            cipher,
+            fallback,
+        }
+    }
+
+    /// Bot token for a workspace
+    ///
+    /// # Arguments
+    /// * `team_id` - Slack workspace ID (or enterprise ID of an org-wide install)
+    /// * `enterprise_id` - Enterprise Grid org of the workspace, if any
+    ///
+    /// # Errors
+    /// - `SlackNotInstalled` if the workspace has no installation and no
+    ///   fallback token is configured
+    /// - `Database` if the lookup fails
+    pub async fn for_team(
+        &self,
+        team_id: &str,
+        enterprise_id: Option<&str>,
+    ) -> Result<String, AppError> {
+        if let Some(installation) =
+            get_installation(&self.db, &self.cipher, team_id, enterprise_id).await?
+        {
+            return Ok(installation.bot_token);
+        }
+
+        self.fallback
+            .clone()
+            .ok_or_else(|| AppError::SlackNotInstalled(team_id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_cipher;
+    use crate::db::repository::{InstallationParams, upsert_installation};
+
+    #[sqlx::test]
+    async fn test_for_team_prefers_installation(pool: PgPool) {
+        let cipher = test_cipher(&["k1"]);
+        upsert_installation(
+            &pool,
+            &cipher,
+            InstallationParams {
+                team_id: "T_INSTALLED",
+                team_name: None,
+                app_id: None,
+                bot_user_id: "UBOT",
+                bot_token: "xoxb-installed",
+                scopes: "chat:write",
+                installed_by_user_id: None,
+                enterprise_id: None,
+                is_enterprise_install: false,
+            },
+        )
+        .await
+        .unwrap();
+        upsert_installation(
+            &pool,
+            &cipher,
+            InstallationParams {
+                team_id: "E_ORG",
+                team_name: None,
+                app_id: None,
+                bot_user_id: "UBOT",
+                bot_token: "xoxb-org",
+                scopes: "chat:write",
+                installed_by_user_id: None,
+                enterprise_id: Some("E_ORG"),
+                is_enterprise_install: true,
+            },
+        )
+        .await
+        .unwrap();
+
+        let tokens = BotTokens::new(
+            pool.clone(),
+            cipher.clone(),
+            Some("xoxb-fallback".to_string()),
+        );
+        assert_eq!(
+            tokens.for_team("T_INSTALLED", None).await.unwrap(),
+            "xoxb-installed"
+        );
+        assert_eq!(
+            tokens.for_team("T_OTHER", None).await.unwrap(),
+            "xoxb-fallback"
+        );
+
+        // Workspaces in an org-wide install use the org's token
+        assert_eq!(
+            tokens.for_team("T_GRID", Some("E_ORG")).await.unwrap(),
+            "xoxb-org"
+        );
+        assert_eq!(tokens.for_team("E_ORG", None).await.unwrap(), "xoxb-org");
+
+        // Another workspace's install in the same org doesn't serve this one
+        upsert_installation(
+            &pool,
+            &cipher,
+            InstallationParams {
+                team_id: "T_SIBLING",
+                team_name: None,
+                app_id: None,
+                bot_user_id: "UBOT",
+                bot_token: "xoxb-sibling",
+                scopes: "chat:write",
+                installed_by_user_id: None,
+                enterprise_id: Some("E_SPLIT"),
+                is_enterprise_install: false,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            tokens
+                .for_team("T_UNINSTALLED", Some("E_SPLIT"))
+                .await
+                .unwrap(),
+            "xoxb-fallback"
+        );
+        assert_eq!(
+            tokens.for_team("E_SPLIT", None).await.unwrap(),
+            "xoxb-fallback"
+        );
+
+        let without_fallback = BotTokens::new(pool, cipher, None);
+        assert!(matches!(
+            without_fallback.for_team("T_OTHER", None).await,
+            Err(AppError::SlackNotInstalled(team_id)) if team_id == "T_OTHER"
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::slack::commands::SAVE_TRACK_ACTION_ID;
+use crate::spotify::client::SpotifyTrack;
+use serde_json::{Value, json};
+
+/// Build the preview of a Spotify track link
+///
+/// Shows the title (linking to the track), artists, album and duration next to
+/// the album art, with a button that saves the track for whoever clicks it.
+///
+/// # Arguments
+/// * `url` - The shared link
+/// * `track` - Track details from Spotify
+///
+/// # Returns
+/// An unfurl for `chat.unfurl` (`{"blocks": [...]}`)
+pub fn track_unfurl(url: &str, track: &SpotifyTrack) -> Value {
+    let mut details = json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": format!(
+                "*<{}|{}>*\n{}\n{} · {}",
+                url,
+                escape(&track.name),
+                escape(&track.artist_names()),
+                escape(&track.album.name),
+                track.duration()
+            )
+        }
+    });
+    if let Some(art_url) = track.album_art_url() {
+        details["accessory"] = json!({
+            "type": "image",
+            "image_url": art_url,
+            "alt_text": track.album.name
+        });
+    }
+
+    json!({
+        "blocks": [
+            details,
+            {
+                "type": "actions",
+                "elements": [
+                    {
+                        "type": "button",
+                        "action_id": SAVE_TRACK_ACTION_ID,
+                        "style": "primary",
+                        "text": { "type": "plain_text", "text": "Save to my Spotify" },
+                        "value": track.id
+                    }
+                ]
+            }
+        ]
+    })
+}
+
+/// Escape text for mrkdwn, where `&`, `<` and `>` are control characters
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spotify::client::{SpotifyAlbum, SpotifyArtist, SpotifyImage};
+
+    fn track() -> SpotifyTrack {
+        SpotifyTrack {
+            id: "4iV5W9uYEdYUVa79Axb7Rh".to_string(),
+            name: "Never Gonna <Give> You Up".to_string(),
+            artists: vec![
+                SpotifyArtist {
+                    name: "Rick Astley".to_string(),
+                },
+                SpotifyArtist {
+                    name: "Stock & Waterman".to_string(),
+                },
+            ],
+            album: SpotifyAlbum {
+                name: "Whenever You Need Somebody".to_string(),
+                images: vec![
+                    SpotifyImage {
+                        url: "https://i.scdn.co/image/640".to_string(),
+                        width: Some(640),
+                    },
+                    SpotifyImage {
+                        url: "https://i.scdn.co/image/300".to_string(),
+                        width: Some(300),
+                    },
+                    SpotifyImage {
+                        url: "https://i.scdn.co/image/64".to_string(),
+                        width: Some(64),
+                    },
+                ],
+            },
+            duration_ms: 213_573,
+        }
+    }
+
+    #[test]
+    fn test_track_unfurl() {
+        let unfurl = track_unfurl(
+            "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+            &track(),
+        );
+
+        let details = &unfurl["blocks"][0];
+        assert_eq!(
+            details["text"]["text"],
+            "*<https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh|Never Gonna &lt;Give&gt; You Up>*\nRick Astley, Stock &amp; Waterman\nWhenever You Need Somebody · 3:33"
+        );
+        assert_eq!(
+            details["accessory"]["image_url"],
+            "https://i.scdn.co/image/300"
+        );
+
+        let button = &unfurl["blocks"][1]["elements"][0];
+        assert_eq!(button["action_id"], SAVE_TRACK_ACTION_ID);
+        assert_eq!(button["value"], "4iV5W9uYEdYUVa79Axb7Rh");
+    }
+
+    #[test]
+    fn test_track_unfurl_without_album_art() {
+        let mut track = track();
+        track.album.images.clear();
+
+        let unfurl = track_unfurl(
+            "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+            &track,
+        );
+        assert!(unfurl["blocks"][0].get("accessory").is_none());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::db::repository::{
+    delete_installation, get_grid_enterprise, insert_audit_log, purge_workspace_data,
+    workspace_active_since,
+};
+use crate::error::AppError;
+use crate::jobs::{JobPayload, JobQueue};
+use crate::slack::identity::EventTeams;
+use crate::slack::routes::SlackState;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+/// Handle the app being uninstalled from a workspace, or its bot token revoked
+///
+/// Deletes the installation that received the event (the bot token no longer
+/// works), records an audit entry and schedules a `purge_workspace` job for
+/// when the grace period ends. The purge covers the data stored under the
+/// workspace's identity key, which for an Enterprise Grid workspace is shared
+/// by the whole org. Runs in the caller's transaction, alongside recording the
+/// event.
+///
+/// # Arguments
+/// * `conn` - Connection with an open transaction
+/// * `jobs` - Job queue for the purge job
+/// * `teams` - Workspaces the uninstall event was delivered for
+/// * `event_type` - Slack event that reported it (for the audit entry)
+/// * `grace` - How long to keep the workspace's data in case it reinstalls
+///
+/// # Returns
+/// When the data will be purged
+///
+/// # Errors
+/// Returns error if a database operation fails
+pub async fn schedule_workspace_purge(
+    conn: &mut PgConnection,
+    jobs: &JobQueue,
+    teams: &EventTeams,
+    event_type: &str,
+    grace: chrono::Duration,
+) -> Result<DateTime<Utc>, AppError> {
+    let uninstalled_at = Utc::now();
+    let purge_at = uninstalled_at + grace;
+    let installation_id = teams.installation_id();
+    let data_workspace_id = teams.identity_workspace(None);
+
+    let had_installation = delete_installation(&mut *conn, installation_id).await?;
+    insert_audit_log(
+        &mut *conn,
+        "app_uninstalled",
+        installation_id,
+        &serde_json::json!({
+            "event": event_type,
+            "had_installation": had_installation,
+            "data_workspace_id": data_workspace_id,
+            "purge_at": purge_at,
+        }),
+    )
+    .await?;
+
+    jobs.enqueue_in_at(
+        conn,
+        &JobPayload::PurgeWorkspace {
+            workspace_id: data_workspace_id.clone(),
+            uninstalled_at,
+        },
+        purge_at,
+    )
+    .await?;
+
+    tracing::info!(
+        workspace_id = installation_id,
+        data_workspace_id = %data_workspace_id,
+        event_type = event_type,
+        purge_at = %purge_at,
+        "Slack app uninstalled, scheduled workspace data purge"
+    );
+
+    Ok(purge_at)
+}
+
+/// Delete an uninstalled workspace's Spotify tokens and save history
+///
+/// Runs as a `purge_workspace` job once the grace period is over, for the
+/// identity key the data is stored under. The purge is cancelled if the app
+/// was reinstalled in the meantime or, for a Grid org, is still installed in
+/// another of its workspaces.
+pub async fn purge_workspace(
+    state: SlackState,
+    workspace_id: String,
+    uninstalled_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    // Jobs queued before Grid users were keyed by enterprise ID name the workspace
+    let workspace_id = get_grid_enterprise(&state.db, &workspace_id)
+        .await?
+        .unwrap_or(workspace_id);
+
+    if workspace_active_since(&state.db, &workspace_id, uninstalled_at).await? {
+        tracing::info!(
+            workspace_id = %workspace_id,
+            "App still installed for the workspace, not purging its data"
+        );
+        insert_audit_log(
+            &state.db,
+            "workspace_purge_cancelled",
+            &workspace_id,
+            &serde_json::json!({ "uninstalled_at": uninstalled_at }),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let counts = purge_workspace_data(&state.db, &workspace_id).await?;
+    insert_audit_log(
+        &state.db,
+        "workspace_data_purged",
+        &workspace_id,
+        &serde_json::to_value(counts)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode counts: {}", e)))?,
+    )
+    .await?;
+
+    tracing::info!(
+        workspace_id = %workspace_id,
+        user_auth = counts.user_auth,
+        track_saves = counts.track_saves,
+        save_actions = counts.save_actions,
+        "Purged data of uninstalled workspace"
+    );
+
+    Ok(())
+}
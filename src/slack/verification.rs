@@ -1,147 +1,240 @@
+use crate::config::Config;
 use crate::error::AppError;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
+use std::fmt;
 
 type HmacSha256 = Hmac<Sha256>;
 
-/// Verify Slack request signature
+/// Verifies Slack request signatures against the accepted signing secrets
 ///
-/// Slack signs all requests with HMAC-SHA256 using the signing secret.
-/// This prevents replay attacks and ensures requests come from Slack.
-///
-/// # Algorithm
-/// 1. Concatenate version, timestamp, and request body: `v0:{timestamp}:{body}`
-/// 2. Compute HMAC-SHA256 with signing secret
-/// 3. Compare with provided signature
-///
-/// # Arguments
-/// * `signing_secret` - Slack app signing secret
-/// * `timestamp` - Request timestamp header (X-Slack-Request-Timestamp)
-/// * `body` - Raw request body
-/// * `signature` - Signature from header (X-Slack-Signature)
-///
-/// # Returns
-/// Ok(()) if signature is valid, Err otherwise
-///
-/// # Errors
-/// - `SignatureInvalid` if signature doesn't match
-/// - `SignatureMissing` if headers are missing
-/// - `SignatureExpired` if timestamp is too old (>5 minutes)
-pub fn verify_slack_signature(
-    signing_secret: &str,
-    timestamp: &str,
-    body: &[u8],
-    signature: &str,
-) -> Result<(), AppError> {
-    // Check timestamp to prevent replay attacks (max 5 minutes old)
-    let request_timestamp = timestamp
-        .parse::<i64>()
-        .map_err(|_| AppError::SignatureInvalid("Invalid timestamp format".to_string()))?;
-
-    let current_timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-
-    if (current_timestamp - request_timestamp).abs() > 60 * 5 {
-        return Err(AppError::SignatureExpired(
-            "Request timestamp too old".to_string(),
-        ));
+/// Several secrets can be accepted at once so the secret can be rotated
+/// without rejecting requests signed with the previous one.
+#[derive(Clone, Default)]
+pub struct SignatureVerifier {
+    /// Accepted signing secrets, current first
+    secrets: Vec<String>,
+    /// Largest accepted difference between the request timestamp and now
+    max_skew_secs: i64,
+}
+
+// Secrets never appear in logs
+impl fmt::Debug for SignatureVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignatureVerifier")
+            .field("secrets", &self.secrets.len())
+            .field("max_skew_secs", &self.max_skew_secs)
+            .finish()
     }
+}
 
-    // Build signature base string: v0:{timestamp}:{body}
-    let sig_basestring = format!("v0:{}:", timestamp);
-    let mut basestring_bytes = sig_basestring.into_bytes();
-    basestring_bytes.extend_from_slice(body);
+impl SignatureVerifier {
+    /// Build a verifier from `SLACK_SIGNING_SECRET` and `SLACK_SIGNATURE_MAX_SKEW_SECS`
+    ///
+    /// `SLACK_SIGNING_SECRET` is a comma-separated list, current secret first.
+    /// A verifier without secrets rejects every request.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.slack_signing_secret.as_deref().unwrap_or_default(),
+            config.slack_signature_max_skew_secs,
+        )
+    }
+
+    /// Build a verifier from a comma-separated list of signing secrets
+    ///
+    /// # Arguments
+    /// * `secrets` - Accepted signing secrets (blank entries are ignored)
+    /// * `max_skew_secs` - Largest accepted request age (or clock drift)
+    pub fn new(secrets: &str, max_skew_secs: i64) -> Self {
+        Self {
+            secrets: secrets
+                .split(',')
+                .map(str::trim)
+                .filter(|secret| !secret.is_empty())
+                .map(str::to_string)
+                .collect(),
+            max_skew_secs,
+        }
+    }
 
-    // Compute HMAC-SHA256
-    let mut mac = HmacSha256::new_from_slice(signing_secret.as_bytes())
-        .map_err(|e| AppError::SignatureInvalid(format!("Invalid key: {}", e)))?;
-    mac.update(&basestring_bytes);
-    let result = mac.finalize();
-    let computed_signature = format!("v0={}", hex::encode(result.into_bytes()));
+    /// Verify Slack request signature
+    ///
+    /// Slack signs all requests with HMAC-SHA256 using the signing secret.
+    /// This prevents replay attacks and ensures requests come from Slack.
+    ///
+    /// # Algorithm
+    /// 1. Concatenate version, timestamp, and request body: `v0:{timestamp}:{body}`
+    /// 2. Compute HMAC-SHA256 with each accepted signing secret
+    /// 3. Compare with provided signature in constant time
+    ///
+    /// # Arguments
+    /// * `timestamp` - Request timestamp header (X-Slack-Request-Timestamp)
+    /// * `body` - Raw request body
+    /// * `signature` - Signature from header (X-Slack-Signature)
+    ///
+    /// # Returns
+    /// Ok(()) if signature is valid, Err otherwise
+    ///
+    /// # Errors
+    /// - `SignatureInvalid` if signature doesn't match any accepted secret
+    /// - `SignatureExpired` if timestamp is further from now than the allowed skew
+    pub fn verify(&self, timestamp: &str, body: &[u8], signature: &str) -> Result<(), AppError> {
+        // Check timestamp to prevent replay attacks
+        let request_timestamp = timestamp
+            .parse::<i64>()
+            .map_err(|_| AppError::SignatureInvalid("Invalid timestamp format".to_string()))?;
+
+        let current_timestamp = chrono::Utc::now().timestamp();
+        if (current_timestamp - request_timestamp).abs() > self.max_skew_secs {
+            return Err(AppError::SignatureExpired(
+                "Request timestamp too old".to_string(),
+            ));
+        }
+
+        let provided = signature
+            .strip_prefix("v0=")
+            .and_then(|hex_signature| hex::decode(hex_signature).ok())
+            .ok_or_else(|| AppError::SignatureInvalid("Malformed signature".to_string()))?;
+
+        for (index, secret) in self.secrets.iter().enumerate() {
+            // Build signature base string: v0:{timestamp}:{body}
+            let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+                .map_err(|e| AppError::SignatureInvalid(format!("Invalid key: {}", e)))?;
+            mac.update(format!("v0:{}:", timestamp).as_bytes());
+            mac.update(body);
+
+            // Constant-time comparison to prevent timing attacks
+            if mac.verify_slice(&provided).is_ok() {
+                tracing::debug!(
+                    secret_index = index,
+                    "Slack signature verified successfully"
+                );
+                return Ok(());
+            }
+        }
 
-    // Constant-time comparison to prevent timing attacks
-    if signature != computed_signature {
         tracing::warn!(
-            provided = signature,
-            computed = computed_signature,
+            secrets = self.secrets.len(),
             "Slack signature verification failed"
         );
-        return Err(AppError::SignatureInvalid(
+        Err(AppError::SignatureInvalid(
             "Signature does not match".to_string(),
-        ));
+        ))
     }
-
-    tracing::debug!("Slack signature verified successfully");
-    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn now() -> String {
+        chrono::Utc::now().timestamp().to_string()
+    }
+
+    fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:{}:", timestamp).as_bytes());
+        mac.update(body);
+        format!("v0={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
     #[test]
     fn test_verify_valid_signature() {
-        let secret = "test_secret";
-        let current_timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let timestamp = current_timestamp.to_string();
+        let verifier = SignatureVerifier::new("test_secret", 300);
+        let timestamp = now();
         let body = b"test body";
+        let signature = sign("test_secret", &timestamp, body);
 
-        // Compute expected signature
-        let sig_basestring = format!("v0:{}:", timestamp);
-        let mut basestring_bytes = sig_basestring.into_bytes();
-        basestring_bytes.extend_from_slice(body);
-
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
-        mac.update(&basestring_bytes);
-        let result_bytes = mac.finalize();
-        let signature = format!("v0={}", hex::encode(result_bytes.into_bytes()));
-
-        let result = verify_slack_signature(secret, &timestamp, body, &signature);
+        let result = verifier.verify(&timestamp, body, &signature);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_verify_invalid_signature() {
-        let secret = "test_secret";
-        let current_timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let timestamp = current_timestamp.to_string();
+        let verifier = SignatureVerifier::new("test_secret", 300);
+        let timestamp = now();
+        let body = b"test body";
+
+        for signature in [
+            "v0=invalid_signature_here".to_string(),
+            "v0=0123abcd".to_string(),
+            sign("other_secret", &timestamp, body),
+            sign("test_secret", &timestamp, b"tampered body"),
+        ] {
+            let result = verifier.verify(&timestamp, body, &signature);
+            assert!(matches!(result.unwrap_err(), AppError::SignatureInvalid(_)));
+        }
+    }
+
+    #[test]
+    fn test_verify_rotated_secrets() {
+        let verifier = SignatureVerifier::new("new_secret, old_secret", 300);
+        let timestamp = now();
         let body = b"test body";
-        let signature = "v0=invalid_signature_here";
 
-        let result = verify_slack_signature(secret, &timestamp, body, signature);
+        for secret in ["new_secret", "old_secret"] {
+            let signature = sign(secret, &timestamp, body);
+            assert!(verifier.verify(&timestamp, body, &signature).is_ok());
+        }
+
+        let retired = sign("retired_secret", &timestamp, body);
+        assert!(verifier.verify(&timestamp, body, &retired).is_err());
+    }
+
+    #[test]
+    fn test_verify_without_secrets_rejects() {
+        let verifier = SignatureVerifier::default();
+        let timestamp = now();
+        let signature = sign("", &timestamp, b"test body");
+
+        let result = verifier.verify(&timestamp, b"test body", &signature);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), AppError::SignatureInvalid(_)));
     }
 
     #[test]
     fn test_verify_expired_timestamp() {
-        let secret = "test_secret";
+        let verifier = SignatureVerifier::new("test_secret", 300);
         let timestamp = "1000000000"; // Very old timestamp
         let body = b"test body";
-        let signature = "v0=anything";
+        let signature = sign("test_secret", timestamp, body);
 
-        let result = verify_slack_signature(secret, timestamp, body, signature);
-        assert!(result.is_err());
+        let result = verifier.verify(timestamp, body, &signature);
         assert!(matches!(result.unwrap_err(), AppError::SignatureExpired(_)));
     }
 
+    #[test]
+    fn test_verify_clock_skew_tolerance() {
+        let body = b"test body";
+        let timestamp = (chrono::Utc::now().timestamp() - 120).to_string();
+        let signature = sign("test_secret", &timestamp, body);
+
+        let strict = SignatureVerifier::new("test_secret", 60);
+        assert!(matches!(
+            strict.verify(&timestamp, body, &signature).unwrap_err(),
+            AppError::SignatureExpired(_)
+        ));
+
+        let lenient = SignatureVerifier::new("test_secret", 300);
+        assert!(lenient.verify(&timestamp, body, &signature).is_ok());
+    }
+
     #[test]
     fn test_verify_invalid_timestamp_format() {
-        let secret = "test_secret";
+        let verifier = SignatureVerifier::new("test_secret", 300);
         let timestamp = "not_a_number";
         let body = b"test body";
         let signature = "v0=anything";
 
-        let result = verify_slack_signature(secret, timestamp, body, signature);
+        let result = verifier.verify(timestamp, body, signature);
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), AppError::SignatureInvalid(_)));
     }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let verifier = SignatureVerifier::new("new_secret,old_secret", 300);
+        let debug = format!("{:?}", verifier);
+        assert!(debug.contains("secrets: 2"));
+        assert!(!debug.contains("new_secret"));
+    }
 }
@@ -0,0 +1,77 @@
+use crate::error::AppError;
+use crate::spotify::client::SpotifyClient;
+use chrono::{DateTime, Duration, Utc};
+use oauth2::TokenResponse;
+use oauth2::basic::BasicClient;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The app's own Spotify access token, from the client credentials flow
+///
+/// Reading track metadata (e.g., for link unfurls) doesn't need a user, so it
+/// uses this token. It is cached and shared by clones until five minutes
+/// before it expires.
+#[derive(Clone, Default)]
+pub struct AppTokenCache {
+    cached: Arc<Mutex<Option<CachedToken>>>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl fmt::Debug for AppTokenCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppTokenCache").finish_non_exhaustive()
+    }
+}
+
+impl AppTokenCache {
+    /// Get the app access token, requesting a new one if needed
+    ///
+    /// Concurrent callers wait for a single token request.
+    ///
+    /// # Arguments
+    /// * `oauth_client` - Configured OAuth2 client for Spotify
+    /// * `spotify_client` - Spotify client used to send the token request
+    ///
+    /// # Errors
+    /// - `SpotifyApi` if the token request fails or has no expiry
+    pub async fn access_token(
+        &self,
+        oauth_client: &BasicClient,
+        spotify_client: &SpotifyClient,
+    ) -> Result<String, AppError> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref()
+            && token.expires_at > Utc::now()
+        {
+            return Ok(token.access_token.clone());
+        }
+
+        let token_result = oauth_client
+            .exchange_client_credentials()
+            .request_async(|request| spotify_client.oauth_http(request))
+            .await
+            .map_err(|e| {
+                tracing::error!(error = ?e, "Spotify app token request failed");
+                AppError::SpotifyApi(format!("Failed to get app access token: {}", e))
+            })?;
+
+        let expires_in = token_result.expires_in().ok_or_else(|| {
+            AppError::SpotifyApi("No expiry time in app token response".to_string())
+        })?;
+        let token = CachedToken {
+            access_token: token_result.access_token().secret().to_string(),
+            expires_at: Utc::now() + Duration::seconds(expires_in.as_secs() as i64)
+                - Duration::minutes(5),
+        };
+        tracing::debug!(expires_at = %token.expires_at, "Fetched Spotify app token");
+
+        let access_token = token.access_token.clone();
+        *cached = Some(token);
+        Ok(access_token)
+    }
+}
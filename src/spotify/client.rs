@@ -1,34 +1,198 @@
-use crate::db::models::UserAuth;
-use crate::db::repository::{get_user_auth, update_tokens};
+use crate::crypto::TokenCipher;
+use crate::db::repository::{
+    SpotifyProfileParams, get_user_auth, lock_user_auth, mark_needs_reauth, update_tokens,
+};
 use crate::error::AppError;
-use chrono::{Duration, Utc};
-use oauth2::{RefreshToken, TokenResponse, basic::BasicClient, reqwest::async_http_client};
+use chrono::{DateTime, Duration, Utc};
+use oauth2::basic::{BasicClient, BasicErrorResponse, BasicErrorResponseType};
+use oauth2::{HttpRequest, HttpResponse, RefreshToken, RequestTokenError, TokenResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Spotify Web API client
+///
+/// Wraps a shared `reqwest::Client` (connection pool, timeouts, user agent) and the
+/// Web API base URL, which defaults to `https://api.spotify.com/v1` but can point at
+/// a local stand-in server in tests. Access tokens are passed per call.
+#[derive(Clone, Debug)]
+pub struct SpotifyClient {
+    http: reqwest::Client,
+    api_base_url: String,
+    token_refresh_timeout: std::time::Duration,
+}
+
+/// Default timeout for token refresh requests
+///
+/// Shorter than the shared client's timeout, since a refresh holds a row lock
+/// and a pooled connection while it waits for Spotify.
+pub const DEFAULT_TOKEN_REFRESH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How much longer than the refresh request the refresh lock may be held
+const REFRESH_LOCK_MARGIN: std::time::Duration = std::time::Duration::from_secs(5);
+
+impl SpotifyClient {
+    /// Create a new Spotify client
+    ///
+    /// # Arguments
+    /// * `http` - Shared HTTP client
+    /// * `api_base_url` - Spotify Web API base URL (e.g., "https://api.spotify.com/v1")
+    pub fn new(http: reqwest::Client, api_base_url: impl Into<String>) -> Self {
+        Self {
+            http,
+            api_base_url: api_base_url.into().trim_end_matches('/').to_string(),
+            token_refresh_timeout: DEFAULT_TOKEN_REFRESH_TIMEOUT,
+        }
+    }
+
+    /// Set the timeout for token refresh requests
+    pub fn with_token_refresh_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.token_refresh_timeout = timeout;
+        self
+    }
+
+    /// How long a token refresh may wait for, or hold, the user's row lock
+    fn refresh_lock_timeout(&self) -> std::time::Duration {
+        self.token_refresh_timeout + REFRESH_LOCK_MARGIN
+    }
+
+    /// Full URL for a Web API path (e.g., "/me/tracks")
+    fn api_url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base_url, path)
+    }
+
+    /// Execute an OAuth2 token request using the shared HTTP client
+    ///
+    /// Passed to `oauth2` request builders (`request_async`) in place of
+    /// `oauth2::reqwest::async_http_client`, so token exchanges get the same
+    /// pooling and timeouts as regular API calls (refreshes use
+    /// `oauth_refresh_http`).
+    ///
+    /// # Errors
+    /// Returns the underlying `reqwest::Error` if the request cannot be sent or the
+    /// response body cannot be read
+    pub async fn oauth_http(&self, request: HttpRequest) -> Result<HttpResponse, reqwest::Error> {
+        self.send_oauth_request(request, None).await
+    }
+
+    /// Execute an OAuth2 token refresh with the token refresh timeout
+    ///
+    /// # Errors
+    /// Same as `oauth_http`, plus a timeout error if Spotify doesn't answer in time
+    pub async fn oauth_refresh_http(
+        &self,
+        request: HttpRequest,
+    ) -> Result<HttpResponse, reqwest::Error> {
+        self.send_oauth_request(request, Some(self.token_refresh_timeout))
+            .await
+    }
+
+    async fn send_oauth_request(
+        &self,
+        request: HttpRequest,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<HttpResponse, reqwest::Error> {
+        let method = reqwest::Method::from_bytes(request.method.as_str().as_bytes())
+            .unwrap_or(reqwest::Method::POST);
+
+        let mut builder = self
+            .http
+            .request(method, request.url.as_str())
+            .body(request.body);
+        for (name, value) in request.headers.iter() {
+            builder = builder.header(name.as_str(), value.as_bytes());
+        }
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        let response = builder.send().await?;
+
+        let status_code = oauth2::http::StatusCode::from_u16(response.status().as_u16())
+            .unwrap_or(oauth2::http::StatusCode::BAD_GATEWAY);
+        let mut headers = oauth2::http::HeaderMap::new();
+        for (name, value) in response.headers() {
+            if let (Ok(name), Ok(value)) = (
+                oauth2::http::HeaderName::from_bytes(name.as_str().as_bytes()),
+                oauth2::http::HeaderValue::from_bytes(value.as_bytes()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        let body = response.bytes().await?.to_vec();
+
+        Ok(HttpResponse {
+            status_code,
+            headers,
+            body,
+        })
+    }
+}
 
 /// Refresh an expired Spotify access token
 ///
 /// Exchanges the refresh token for a new access token using the OAuth2 client.
 /// Updates the database with the new token and expiry time.
 ///
+/// Holds a row lock on the user's record for the whole exchange, so only one
+/// refresh per user runs at a time across all instances. Callers that waited
+/// for the lock re-check the stored expiry and reuse the token the winner
+/// stored instead of spending a (possibly rotated) refresh token again. The
+/// exchange is bounded by the client's token refresh timeout, and the lock
+/// transaction by a slightly longer lock and idle timeout, so a slow Spotify
+/// can't pin pooled connections and row locks.
+///
 /// # Arguments
 /// * `pool` - Database connection pool
+/// * `cipher` - Token cipher used to encrypt the new tokens
 /// * `oauth_client` - Configured OAuth2 client for Spotify
-/// * `user_auth` - User authentication record containing refresh token
+/// * `spotify_client` - Spotify client used to send the token request
+/// * `user_auth_id` - ID of the user authentication record to refresh
 ///
 /// # Returns
-/// The new access token as a String
+/// The new access token, or the one stored by a concurrent refresh
 ///
 /// # Errors
 /// Returns error if:
-/// - Token refresh request fails (network, invalid refresh token)
+/// - Spotify rejects the refresh token (`SpotifyReauthRequired`; the record is
+///   flagged with `needs_reauth`)
+/// - Token refresh request fails otherwise
 /// - Database update fails
 /// - Response missing required fields
 pub async fn refresh_access_token(
     pool: &PgPool,
+    cipher: &TokenCipher,
     oauth_client: &BasicClient,
-    user_auth: &UserAuth,
+    spotify_client: &SpotifyClient,
+    user_auth_id: Uuid,
 ) -> Result<String, AppError> {
+    // Serialize refreshes of this user across tasks and instances: the row lock
+    // is held until the new tokens are stored
+    let mut tx = pool.begin().await?;
+    let user_auth = lock_user_auth(
+        &mut tx,
+        cipher,
+        user_auth_id,
+        spotify_client.refresh_lock_timeout(),
+    )
+    .await?
+    .ok_or(AppError::SpotifyNotConnected)?;
+
+    if user_auth.needs_reauth {
+        return Err(AppError::SpotifyReauthRequired);
+    }
+
+    // Another refresh may have finished while we waited for the lock
+    if !needs_refresh(user_auth.expires_at) {
+        tracing::debug!(
+            user_auth_id = %user_auth.id,
+            expires_at = %user_auth.expires_at,
+            "Token was refreshed concurrently, using it"
+        );
+        tx.commit().await?;
+        return Ok(user_auth.access_token);
+    }
+
     tracing::info!(
         user_auth_id = %user_auth.id,
         slack_workspace_id = %user_auth.slack_workspace_id,
@@ -39,18 +203,34 @@ pub async fn refresh_access_token(
     // Exchange refresh token for new access token
     let refresh_token = RefreshToken::new(user_auth.refresh_token.clone());
 
-    let token_result = oauth_client
+    let token_result = match oauth_client
         .exchange_refresh_token(&refresh_token)
-        .request_async(async_http_client)
+        .request_async(|request| spotify_client.oauth_refresh_http(request))
         .await
-        .map_err(|e| {
+    {
+        Ok(token_result) => token_result,
+        Err(e) if is_invalid_grant(&e) => {
+            tracing::warn!(
+                user_auth_id = %user_auth.id,
+                error = ?e,
+                "Spotify rejected the refresh token, marking account for reauthorization"
+            );
+            mark_needs_reauth(&mut *tx, user_auth.id).await?;
+            tx.commit().await?;
+            return Err(AppError::SpotifyReauthRequired);
+        }
+        Err(e) => {
             tracing::error!(
                 user_auth_id = %user_auth.id,
                 error = ?e,
                 "Token refresh request failed"
             );
-            AppError::SpotifyApi(format!("Failed to refresh access token: {}", e))
-        })?;
+            return Err(AppError::SpotifyApi(format!(
+                "Failed to refresh access token: {}",
+                e
+            )));
+        }
+    };
 
     let new_access_token = token_result.access_token().secret().to_string();
 
@@ -81,7 +261,8 @@ pub async fn refresh_access_token(
 
     // Update database with new tokens
     update_tokens(
-        pool,
+        &mut *tx,
+        cipher,
         user_auth.id,
         &new_access_token,
         &new_refresh_token,
@@ -96,6 +277,7 @@ pub async fn refresh_access_token(
         );
         AppError::Database(e)
     })?;
+    tx.commit().await?;
 
     tracing::info!(
         user_auth_id = %user_auth.id,
@@ -107,14 +289,41 @@ pub async fn refresh_access_token(
     Ok(new_access_token)
 }
 
+/// How long before `expires_at` a token is refreshed
+pub const TOKEN_REFRESH_BUFFER: Duration = Duration::minutes(5);
+
+/// Whether a token expiring at `expires_at` should be refreshed now
+///
+/// Tokens are refreshed once they are within `TOKEN_REFRESH_BUFFER` of expiry.
+fn needs_refresh(expires_at: DateTime<Utc>) -> bool {
+    expires_at <= Utc::now() + TOKEN_REFRESH_BUFFER
+}
+
+/// Whether a token request failed because Spotify rejected the grant
+///
+/// `invalid_grant` on a refresh means the refresh token was revoked (the user
+/// removed the app's access) or is otherwise unusable; retrying can't help.
+fn is_invalid_grant<RE: std::error::Error + 'static>(
+    error: &RequestTokenError<RE, BasicErrorResponse>,
+) -> bool {
+    matches!(
+        error,
+        RequestTokenError::ServerResponse(response)
+            if *response.error() == BasicErrorResponseType::InvalidGrant
+    )
+}
+
 /// Ensure a valid access token, refreshing if necessary
 ///
 /// Checks if the current access token is still valid (not expired).
-/// If expired or close to expiry, refreshes the token automatically.
+/// If expired or close to expiry, refreshes the token automatically. Accounts
+/// flagged with `needs_reauth` fail without calling Spotify.
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
+/// * `cipher` - Token cipher used to decrypt and re-encrypt tokens
 /// * `oauth_client` - Configured OAuth2 client for Spotify
+/// * `spotify_client` - Spotify client used for token refresh
 /// * `workspace_id` - Slack workspace ID
 /// * `user_id` - Slack user ID
 ///
@@ -123,17 +332,20 @@ pub async fn refresh_access_token(
 ///
 /// # Errors
 /// Returns error if:
-/// - User not found in database
+/// - User not found in database (`SpotifyNotConnected`)
+/// - The account needs reauthorization (`SpotifyReauthRequired`)
 /// - Token refresh fails
 /// - Database operations fail
 pub async fn ensure_valid_token(
     pool: &PgPool,
+    cipher: &TokenCipher,
     oauth_client: &BasicClient,
+    spotify_client: &SpotifyClient,
     workspace_id: &str,
     user_id: &str,
 ) -> Result<String, AppError> {
     // Fetch user authentication record
-    let user_auth = get_user_auth(pool, workspace_id, user_id)
+    let user_auth = get_user_auth(pool, cipher, workspace_id, user_id)
         .await
         .map_err(AppError::Database)?
         .ok_or_else(|| {
@@ -142,23 +354,27 @@ pub async fn ensure_valid_token(
                 slack_user_id = user_id,
                 "User not found in database"
             );
-            AppError::BadRequest("User not authenticated with Spotify".to_string())
+            AppError::SpotifyNotConnected
         })?;
 
-    // Check if token is expired or will expire soon (within 5 minutes)
-    let now = Utc::now();
-    let buffer = Duration::minutes(5);
-    let needs_refresh = user_auth.expires_at <= now + buffer;
+    if user_auth.needs_reauth {
+        tracing::info!(
+            user_auth_id = %user_auth.id,
+            needs_reauth_at = ?user_auth.needs_reauth_at,
+            "Account needs reauthorization, not calling Spotify"
+        );
+        return Err(AppError::SpotifyReauthRequired);
+    }
 
-    if needs_refresh {
+    // Check if token is expired or will expire soon (within 5 minutes)
+    if needs_refresh(user_auth.expires_at) {
         tracing::info!(
             user_auth_id = %user_auth.id,
             expires_at = %user_auth.expires_at,
-            now = %now,
             "Access token expired or expiring soon, refreshing"
         );
 
-        refresh_access_token(pool, oauth_client, &user_auth).await
+        refresh_access_token(pool, cipher, oauth_client, spotify_client, user_auth.id).await
     } else {
         tracing::debug!(
             user_auth_id = %user_auth.id,
@@ -171,185 +387,332 @@ pub async fn ensure_valid_token(
 }
 
 /// Spotify user profile information
+///
+/// `country` and `product` are only returned with the `user-read-private` scope.
 #[derive(Debug, Deserialize)]
 pub struct SpotifyUser {
     pub id: String,
     pub display_name: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    /// Subscription tier ("premium", "free", ...)
+    #[serde(default)]
+    pub product: Option<String>,
 }
 
-/// Get current user's Spotify profile
-///
-/// Makes a call to Spotify's /v1/me endpoint to verify token works
-/// and retrieve user information.
-///
-/// # Arguments
-/// * `access_token` - Valid Spotify access token
-///
-/// # Returns
-/// SpotifyUser with profile information
-///
-/// # Errors
-/// Returns error if:
-/// - HTTP request fails
-/// - Token is invalid
-/// - Response parsing fails
-pub async fn get_current_user(access_token: &str) -> Result<SpotifyUser, AppError> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get("https://api.spotify.com/v1/me")
-        .bearer_auth(access_token)
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("Spotify API request failed: {:?}", e);
-            AppError::SpotifyApi(format!("Failed to call Spotify API: {}", e))
-        })?;
+impl SpotifyUser {
+    /// Profile fields as stored on the user auth record
+    pub fn profile_params(&self) -> SpotifyProfileParams<'_> {
+        SpotifyProfileParams {
+            spotify_user_id: &self.id,
+            display_name: self.display_name.as_deref(),
+            country: self.country.as_deref(),
+            product: self.product.as_deref(),
+        }
+    }
+}
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        tracing::error!(
-            status = %status,
-            body = %body,
-            "Spotify API returned error"
-        );
-        return Err(AppError::SpotifyApi(format!(
-            "Spotify API error: {} - {}",
-            status, body
-        )));
+/// Track details from Spotify's /v1/tracks endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpotifyTrack {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub artists: Vec<SpotifyArtist>,
+    pub album: SpotifyAlbum,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpotifyArtist {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpotifyAlbum {
+    pub name: String,
+    /// Cover art, widest first
+    #[serde(default)]
+    pub images: Vec<SpotifyImage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpotifyImage {
+    pub url: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+}
+
+impl SpotifyTrack {
+    /// Artist names, comma-separated
+    pub fn artist_names(&self) -> String {
+        self.artists
+            .iter()
+            .map(|artist| artist.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    response.json::<SpotifyUser>().await.map_err(|e| {
-        tracing::error!("Failed to parse Spotify API response: {:?}", e);
-        AppError::SpotifyApi(format!("Failed to parse response: {}", e))
-    })
+    /// Duration as `m:ss`
+    pub fn duration(&self) -> String {
+        let seconds = self.duration_ms / 1000;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    /// Smallest cover art that is still at least 200px wide (or the only one)
+    pub fn album_art_url(&self) -> Option<&str> {
+        let images = &self.album.images;
+        images
+            .iter()
+            .filter(|image| image.width.is_some_and(|width| width >= 200))
+            .min_by_key(|image| image.width)
+            .or_else(|| images.first())
+            .map(|image| image.url.as_str())
+    }
 }
 
-/// Save a track to user's Spotify library
-///
-/// Adds a track to the user's "Liked Songs" (saved tracks).
-///
-/// # Arguments
-/// * `access_token` - Valid Spotify access token
-/// * `track_id` - Spotify track ID to save
-///
-/// # Returns
-/// Ok(()) if track was saved successfully
-///
-/// # Errors
-/// Returns error if:
-/// - HTTP request fails
-/// - Token is invalid
-/// - Track ID is invalid
-/// - User has reached library limit
-pub async fn save_track(access_token: &str, track_id: &str) -> Result<(), AppError> {
-    tracing::info!(track_id = track_id, "Saving track to Spotify library");
-
-    let client = reqwest::Client::new();
-    let url = "https://api.spotify.com/v1/me/tracks";
-
-    let response = client
-        .put(url)
-        .bearer_auth(access_token)
-        .query(&[("ids", track_id)])
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("Spotify API request failed: {:?}", e);
-            AppError::SpotifyApi(format!("Failed to save track: {}", e))
-        })?;
+impl SpotifyClient {
+    /// Get a track's details
+    ///
+    /// Calls Spotify's /v1/tracks/{id} endpoint. Any access token works,
+    /// including the app's client credentials token.
+    ///
+    /// # Arguments
+    /// * `access_token` - Valid Spotify access token
+    /// * `track_id` - Spotify track ID
+    ///
+    /// # Returns
+    /// SpotifyTrack with name, artists, album and duration
+    ///
+    /// # Errors
+    /// Returns `NotFound` if Spotify has no such track (404, or 400 for a
+    /// malformed ID). Returns `SpotifyApi` if:
+    /// - HTTP request fails
+    /// - Token is invalid or Spotify returns another error
+    /// - Response parsing fails
+    pub async fn get_track(
+        &self,
+        access_token: &str,
+        track_id: &str,
+    ) -> Result<SpotifyTrack, AppError> {
+        let response = self
+            .http
+            .get(self.api_url(&format!("/tracks/{}", track_id)))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("Spotify API request failed: {:?}", e);
+                AppError::SpotifyApi(format!("Failed to call Spotify API: {}", e))
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(
+                track_id = track_id,
+                status = %status,
+                body = %body,
+                "Spotify API returned error"
+            );
+            if matches!(
+                status,
+                reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::BAD_REQUEST
+            ) {
+                return Err(AppError::NotFound(format!("Spotify track {}", track_id)));
+            }
+            return Err(AppError::SpotifyApi(format!(
+                "Failed to get track: {} - {}",
+                status, body
+            )));
+        }
+
+        response.json::<SpotifyTrack>().await.map_err(|e| {
+            tracing::error!("Failed to parse Spotify API response: {:?}", e);
+            AppError::SpotifyApi(format!("Failed to parse response: {}", e))
+        })
+    }
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        tracing::error!(
-            track_id = track_id,
-            status = %status,
-            body = %body,
-            "Spotify API returned error"
-        );
-        return Err(AppError::SpotifyApi(format!(
-            "Failed to save track: {} - {}",
-            status, body
-        )));
+    /// Get current user's Spotify profile
+    ///
+    /// Makes a call to Spotify's /v1/me endpoint to verify token works
+    /// and retrieve user information.
+    ///
+    /// # Arguments
+    /// * `access_token` - Valid Spotify access token
+    ///
+    /// # Returns
+    /// SpotifyUser with profile information
+    ///
+    /// # Errors
+    /// Returns error if:
+    /// - HTTP request fails
+    /// - Token is invalid
+    /// - Response parsing fails
+    pub async fn get_current_user(&self, access_token: &str) -> Result<SpotifyUser, AppError> {
+        let response = self
+            .http
+            .get(self.api_url("/me"))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("Spotify API request failed: {:?}", e);
+                AppError::SpotifyApi(format!("Failed to call Spotify API: {}", e))
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(
+                status = %status,
+                body = %body,
+                "Spotify API returned error"
+            );
+            return Err(AppError::SpotifyApi(format!(
+                "Spotify API error: {} - {}",
+                status, body
+            )));
+        }
+
+        response.json::<SpotifyUser>().await.map_err(|e| {
+            tracing::error!("Failed to parse Spotify API response: {:?}", e);
+            AppError::SpotifyApi(format!("Failed to parse response: {}", e))
+        })
     }
 
-    tracing::info!(track_id = track_id, "Successfully saved track");
-    Ok(())
+    /// Save a track to user's Spotify library
+    ///
+    /// Adds a track to the user's "Liked Songs" (saved tracks).
+    ///
+    /// # Arguments
+    /// * `access_token` - Valid Spotify access token
+    /// * `track_id` - Spotify track ID to save
+    ///
+    /// # Returns
+    /// Ok(()) if track was saved successfully
+    ///
+    /// # Errors
+    /// Returns error if:
+    /// - HTTP request fails
+    /// - Token is invalid
+    /// - Track ID is invalid
+    /// - User has reached library limit
+    pub async fn save_track(&self, access_token: &str, track_id: &str) -> Result<(), AppError> {
+        tracing::info!(track_id = track_id, "Saving track to Spotify library");
+
+        let response = self
+            .http
+            .put(self.api_url("/me/tracks"))
+            .bearer_auth(access_token)
+            .query(&[("ids", track_id)])
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("Spotify API request failed: {:?}", e);
+                AppError::SpotifyApi(format!("Failed to save track: {}", e))
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(
+                track_id = track_id,
+                status = %status,
+                body = %body,
+                "Spotify API returned error"
+            );
+            return Err(AppError::SpotifyApi(format!(
+                "Failed to save track: {} - {}",
+                status, body
+            )));
+        }
+
+        tracing::info!(track_id = track_id, "Successfully saved track");
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
-    use crate::db::repository::upsert_user_auth;
+    use crate::config::test_config;
+    use crate::crypto::test_cipher;
+    use crate::db::repository::{UserAuthParams, upsert_user_auth};
     use crate::spotify::oauth::build_oauth_client;
     use chrono::Utc;
 
     #[sqlx::test]
     async fn test_ensure_valid_token_not_found(pool: PgPool) -> sqlx::Result<()> {
-        let config = Config {
-            port: 3000,
-            host: "0.0.0.0".to_string(),
-            database_url: "postgresql://localhost/test".to_string(),
-            spotify_client_id: "test_client_id".to_string(),
-            spotify_client_secret: "test_client_secret".to_string(),
-            spotify_redirect_uri: "http://localhost:3000/spotify/callback".to_string(),
-            base_url: "http://localhost:3000".to_string(),
-            slack_signing_secret: None,
-            slack_bot_token: None,
-            rust_log: "info".to_string(),
-        };
+        let config = test_config();
+        let cipher = test_cipher(&["k1"]);
 
         let oauth_client = build_oauth_client(&config);
+        let spotify_client =
+            SpotifyClient::new(reqwest::Client::new(), &config.spotify_api_base_url);
 
-        let result = ensure_valid_token(&pool, &oauth_client, "T123", "U456").await;
+        let result = ensure_valid_token(
+            &pool,
+            &cipher,
+            &oauth_client,
+            &spotify_client,
+            "T123",
+            "U456",
+        )
+        .await;
 
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), AppError::BadRequest(_)));
+        assert!(matches!(result.unwrap_err(), AppError::SpotifyNotConnected));
 
         Ok(())
     }
 
     #[sqlx::test]
     async fn test_ensure_valid_token_still_valid(pool: PgPool) -> sqlx::Result<()> {
-        let config = Config {
-            port: 3000,
-            host: "0.0.0.0".to_string(),
-            database_url: "postgresql://localhost/test".to_string(),
-            spotify_client_id: "test_client_id".to_string(),
-            spotify_client_secret: "test_client_secret".to_string(),
-            spotify_redirect_uri: "http://localhost:3000/spotify/callback".to_string(),
-            base_url: "http://localhost:3000".to_string(),
-            slack_signing_secret: None,
-            slack_bot_token: None,
-            rust_log: "info".to_string(),
-        };
+        let config = test_config();
+        let cipher = test_cipher(&["k1"]);
 
         let oauth_client = build_oauth_client(&config);
+        let spotify_client =
+            SpotifyClient::new(reqwest::Client::new(), &config.spotify_api_base_url);
 
         // Create user with token that expires in 1 hour (still valid)
         let expires_at = Utc::now() + Duration::hours(1);
         let user_auth = upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify_user_id".to_string()),
-            "valid_access_token",
-            "valid_refresh_token",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify_user_id",
+                    ..Default::default()
+                }),
+                access_token: "valid_access_token",
+                refresh_token: "valid_refresh_token",
+                expires_at,
+            },
         )
         .await?;
 
-        let result = ensure_valid_token(&pool, &oauth_client, "T123", "U456").await;
+        let result = ensure_valid_token(
+            &pool,
+            &cipher,
+            &oauth_client,
+            &spotify_client,
+            "T123",
+            "U456",
+        )
+        .await;
 
         // Should return existing token without refreshing
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "valid_access_token");
 
         // Verify token wasn't updated in database
-        let updated_user = get_user_auth(&pool, "T123", "U456").await?.unwrap();
+        let updated_user = get_user_auth(&pool, &cipher, "T123", "U456")
+            .await?
+            .unwrap();
         assert_eq!(updated_user.access_token, user_auth.access_token);
         assert_eq!(updated_user.expires_at, user_auth.expires_at);
 
@@ -358,35 +721,41 @@ mod tests {
 
     #[sqlx::test]
     async fn test_ensure_valid_token_expired(pool: PgPool) -> sqlx::Result<()> {
-        let config = Config {
-            port: 3000,
-            host: "0.0.0.0".to_string(),
-            database_url: "postgresql://localhost/test".to_string(),
-            spotify_client_id: "test_client_id".to_string(),
-            spotify_client_secret: "test_client_secret".to_string(),
-            spotify_redirect_uri: "http://localhost:3000/spotify/callback".to_string(),
-            base_url: "http://localhost:3000".to_string(),
-            slack_signing_secret: None,
-            slack_bot_token: None,
-            rust_log: "info".to_string(),
-        };
+        let config = test_config();
+        let cipher = test_cipher(&["k1"]);
 
         let oauth_client = build_oauth_client(&config);
+        let spotify_client =
+            SpotifyClient::new(reqwest::Client::new(), &config.spotify_api_base_url);
 
         // Create user with expired token
         let expires_at = Utc::now() - Duration::hours(1);
         upsert_user_auth(
             &pool,
-            "T123",
-            "U456",
-            Some("spotify_user_id".to_string()),
-            "expired_access_token",
-            "valid_refresh_token",
-            expires_at,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: Some(SpotifyProfileParams {
+                    spotify_user_id: "spotify_user_id",
+                    ..Default::default()
+                }),
+                access_token: "expired_access_token",
+                refresh_token: "valid_refresh_token",
+                expires_at,
+            },
         )
         .await?;
 
-        let result = ensure_valid_token(&pool, &oauth_client, "T123", "U456").await;
+        let result = ensure_valid_token(
+            &pool,
+            &cipher,
+            &oauth_client,
+            &spotify_client,
+            "T123",
+            "U456",
+        )
+        .await;
 
         // Will fail because we can't actually refresh with test credentials
         // But we can verify it attempted to refresh
@@ -395,4 +764,65 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_is_invalid_grant() {
+        let response = |kind| {
+            RequestTokenError::<std::io::Error, _>::ServerResponse(BasicErrorResponse::new(
+                kind, None, None,
+            ))
+        };
+
+        assert!(is_invalid_grant(&response(
+            BasicErrorResponseType::InvalidGrant
+        )));
+        assert!(!is_invalid_grant(&response(
+            BasicErrorResponseType::InvalidClient
+        )));
+        assert!(!is_invalid_grant(&RequestTokenError::<
+            std::io::Error,
+            BasicErrorResponse,
+        >::Other(
+            "unexpected".to_string()
+        )));
+    }
+
+    #[sqlx::test]
+    async fn test_ensure_valid_token_needs_reauth(pool: PgPool) -> sqlx::Result<()> {
+        let config = test_config();
+        let cipher = test_cipher(&["k1"]);
+
+        let oauth_client = build_oauth_client(&config);
+        let spotify_client =
+            SpotifyClient::new(reqwest::Client::new(), &config.spotify_api_base_url);
+
+        let user_auth = upsert_user_auth(
+            &pool,
+            &cipher,
+            UserAuthParams {
+                workspace_id: "T123",
+                user_id: "U456",
+                profile: None,
+                access_token: "valid_access_token",
+                refresh_token: "revoked_refresh_token",
+                expires_at: Utc::now() + Duration::hours(1),
+            },
+        )
+        .await?;
+        mark_needs_reauth(&pool, user_auth.id).await?;
+
+        // Fails even though the access token hasn't expired
+        let result = ensure_valid_token(
+            &pool,
+            &cipher,
+            &oauth_client,
+            &spotify_client,
+            "T123",
+            "U456",
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::SpotifyReauthRequired)));
+
+        Ok(())
+    }
 }
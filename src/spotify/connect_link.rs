@@ -0,0 +1,277 @@
+//! Links that start the Spotify connect flow
+//!
+//! `/spotify/connect` takes the Slack user to link from its query, so anyone
+//! can start the flow for any user. Links the bot sends are signed; only a
+//! flow started from a signed link may message the user in Slack about how it
+//! ended. Unsigned links (hand-made, or sent before links were signed) still
+//! connect Spotify.
+
+use crate::config::Config;
+use crate::spotify::oauth::OAuthState;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Separates connect link signatures from other uses of the key
+const SIGNATURE_CONTEXT: &[u8] = b"savethebeat-connect-link-v1";
+
+/// Builds and checks connect links. Cheap to clone.
+#[derive(Clone)]
+pub struct ConnectLinks {
+    /// Public base URL of this app
+    base_url: String,
+    /// Key signing the links
+    key: Vec<u8>,
+}
+
+// The key never appears in logs
+impl fmt::Debug for ConnectLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectLinks")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Slack user a connect link is for
+struct LinkTarget<'a> {
+    workspace_id: &'a str,
+    user_id: &'a str,
+    team_id: Option<&'a str>,
+    enterprise_id: Option<&'a str>,
+}
+
+impl ConnectLinks {
+    /// Links under `BASE_URL`, signed with a key derived from
+    /// `SPOTIFY_CLIENT_SECRET`
+    ///
+    /// Rotating the client secret leaves earlier links working, unsigned.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.base_url, config.spotify_client_secret.as_bytes())
+    }
+
+    /// Create a link builder
+    ///
+    /// # Arguments
+    /// * `base_url` - Public base URL of this app
+    /// * `key` - Key signing the links
+    pub fn new(base_url: &str, key: &[u8]) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            key: key.to_vec(),
+        }
+    }
+
+    /// Signed URL that starts the connect flow for a Slack user
+    ///
+    /// # Arguments
+    /// * `workspace_id` - Identity workspace of the user
+    /// * `user_id` - Slack user ID
+    /// * `team_id` - Workspace whose bot sends the link, kept so the callback
+    ///   can message the user through the same installation
+    /// * `enterprise_id` - Enterprise Grid org of that workspace
+    pub fn url(
+        &self,
+        workspace_id: &str,
+        user_id: &str,
+        team_id: Option<&str>,
+        enterprise_id: Option<&str>,
+    ) -> String {
+        let target = LinkTarget {
+            workspace_id,
+            user_id,
+            team_id: team_id.filter(|team_id| *team_id != workspace_id),
+            enterprise_id,
+        };
+        let signature = self.signature(&target);
+        self.build(&target, Some(&signature))
+    }
+
+    /// URL that starts the flow again after it failed
+    ///
+    /// Signed only if the failed flow started from a signed link, so the
+    /// failure page can't turn a hand-made link into a signed one.
+    pub fn retry_url(&self, oauth_state: &OAuthState) -> String {
+        let target = LinkTarget {
+            workspace_id: &oauth_state.slack_workspace_id,
+            user_id: &oauth_state.slack_user_id,
+            team_id: oauth_state.slack_team_id.as_deref(),
+            enterprise_id: oauth_state.slack_enterprise_id.as_deref(),
+        };
+        let signature = oauth_state.link_signed.then(|| self.signature(&target));
+        self.build(&target, signature.as_deref())
+    }
+
+    /// Whether `signature` is this app's signature of a link's parameters
+    ///
+    /// # Arguments
+    /// * `workspace_id`, `user_id`, `team_id`, `enterprise_id` - Link
+    ///   parameters, as received
+    /// * `signature` - The link's `sig` parameter, if any
+    pub fn verify(
+        &self,
+        workspace_id: &str,
+        user_id: &str,
+        team_id: Option<&str>,
+        enterprise_id: Option<&str>,
+        signature: Option<&str>,
+    ) -> bool {
+        let Some(signature) = signature.and_then(|signature| hex::decode(signature).ok()) else {
+            return false;
+        };
+        let target = LinkTarget {
+            workspace_id,
+            user_id,
+            team_id,
+            enterprise_id,
+        };
+        self.mac(&target).verify_slice(&signature).is_ok()
+    }
+
+    fn build(&self, target: &LinkTarget<'_>, signature: Option<&str>) -> String {
+        let mut query = oauth2::url::form_urlencoded::Serializer::new(String::new());
+        query
+            .append_pair("slack_workspace_id", target.workspace_id)
+            .append_pair("slack_user_id", target.user_id);
+        if let Some(team_id) = target.team_id {
+            query.append_pair("slack_team_id", team_id);
+        }
+        if let Some(enterprise_id) = target.enterprise_id {
+            query.append_pair("slack_enterprise_id", enterprise_id);
+        }
+        if let Some(signature) = signature {
+            query.append_pair("sig", signature);
+        }
+
+        format!("{}/spotify/connect?{}", self.base_url, query.finish())
+    }
+
+    fn signature(&self, target: &LinkTarget<'_>) -> String {
+        hex::encode(self.mac(target).finalize().into_bytes())
+    }
+
+    fn mac(&self, target: &LinkTarget<'_>) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(SIGNATURE_CONTEXT);
+        // Absent and empty optional parameters sign differently
+        for field in [
+            Some(target.workspace_id),
+            Some(target.user_id),
+            target.team_id,
+            target.enterprise_id,
+        ] {
+            match field {
+                Some(value) => {
+                    mac.update(&[1]);
+                    mac.update(&(value.len() as u64).to_be_bytes());
+                    mac.update(value.as_bytes());
+                }
+                None => mac.update(&[0]),
+            }
+        }
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn links() -> ConnectLinks {
+        ConnectLinks::new("http://localhost:3000/", b"test_key")
+    }
+
+    fn params(url: &str) -> Vec<(String, String)> {
+        reqwest::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect()
+    }
+
+    fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn verify(links: &ConnectLinks, url: &str) -> bool {
+        let params = params(url);
+        links.verify(
+            param(&params, "slack_workspace_id").unwrap(),
+            param(&params, "slack_user_id").unwrap(),
+            param(&params, "slack_team_id"),
+            param(&params, "slack_enterprise_id"),
+            param(&params, "sig"),
+        )
+    }
+
+    #[test]
+    fn test_url_keeps_sending_workspace() {
+        let url = links().url("T123", "U456", Some("T123"), None);
+        assert!(url.starts_with(
+            "http://localhost:3000/spotify/connect?slack_workspace_id=T123&slack_user_id=U456&sig="
+        ));
+
+        let url = links().url("E1", "U456", Some("T123"), Some("E1"));
+        assert!(url.starts_with(
+            "http://localhost:3000/spotify/connect?slack_workspace_id=E1&slack_user_id=U456&slack_team_id=T123&slack_enterprise_id=E1&sig="
+        ));
+    }
+
+    #[test]
+    fn test_signed_links_verify() {
+        let links = links();
+        assert!(verify(&links, &links.url("T123", "U456", None, None)));
+        assert!(verify(
+            &links,
+            &links.url("E1", "U456", Some("T123"), Some("E1"))
+        ));
+
+        // Another key's links don't
+        let other = ConnectLinks::new("http://localhost:3000", b"other_key");
+        assert!(!verify(&other, &links.url("T123", "U456", None, None)));
+    }
+
+    #[test]
+    fn test_tampered_or_unsigned_links_do_not_verify() {
+        let links = links();
+        let url = links.url("T123", "U456", None, None);
+        let signature = param(&params(&url), "sig").unwrap().to_string();
+
+        assert!(!links.verify("T123", "U_VICTIM", None, None, Some(&signature)));
+        assert!(!links.verify("T123", "U456", Some("T999"), None, Some(&signature)));
+        assert!(!links.verify("T123", "U456", None, Some(""), Some(&signature)));
+        assert!(!links.verify("T123", "U456", None, None, None));
+        assert!(!links.verify("T123", "U456", None, None, Some("not hex")));
+    }
+
+    #[test]
+    fn test_retry_url_signed_only_for_signed_flows() {
+        let links = links();
+        let mut oauth_state = OAuthState {
+            slack_workspace_id: "E1".to_string(),
+            slack_user_id: "U456".to_string(),
+            created_at: Utc::now(),
+            pkce_verifier: "verifier".to_string(),
+            slack_team_id: Some("T123".to_string()),
+            slack_enterprise_id: Some("E1".to_string()),
+            link_signed: true,
+        };
+        let retry_url = links.retry_url(&oauth_state);
+        assert_eq!(retry_url, links.url("E1", "U456", Some("T123"), Some("E1")));
+        assert!(verify(&links, &retry_url));
+
+        oauth_state.link_signed = false;
+        let retry_url = links.retry_url(&oauth_state);
+        assert_eq!(
+            retry_url,
+            "http://localhost:3000/spotify/connect?slack_workspace_id=E1&slack_user_id=U456&slack_team_id=T123&slack_enterprise_id=E1"
+        );
+        assert!(!verify(&links, &retry_url));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::db::repository::{delete_user_auth, purge_save_history};
+use crate::error::AppError;
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// Spotify's page for removing third-party app access
+///
+/// Spotify has no token revocation endpoint, so deleting our copy of the tokens
+/// is all we can do; the grant itself is removed from this page.
+pub const SPOTIFY_APPS_URL: &str = "https://www.spotify.com/account/apps/";
+
+/// Result of unlinking a Slack user from Spotify
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DisconnectOutcome {
+    /// Whether the user had linked Spotify
+    pub was_connected: bool,
+    /// Track saves deleted with their history (0 unless history was purged)
+    pub saves_purged: u64,
+}
+
+/// Unlink a Slack user from Spotify
+///
+/// Deletes the stored tokens and, if `purge_history` is set, the user's track
+/// saves and attempt log. Shared by the slash command, the App Home button and
+/// the admin endpoint.
+///
+/// # Arguments
+/// * `db` - Database connection pool
+/// * `workspace_id` - Slack workspace ID
+/// * `user_id` - Slack user ID
+/// * `purge_history` - Also delete the user's save history
+///
+/// # Errors
+/// Returns error if a database operation fails
+pub async fn disconnect_user(
+    db: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+    purge_history: bool,
+) -> Result<DisconnectOutcome, AppError> {
+    let was_connected = delete_user_auth(db, workspace_id, user_id).await?;
+    let saves_purged = if purge_history {
+        purge_save_history(db, workspace_id, user_id).await?
+    } else {
+        0
+    };
+
+    tracing::info!(
+        workspace_id = workspace_id,
+        user_id = user_id,
+        was_connected,
+        purge_history,
+        saves_purged,
+        "Disconnected Spotify"
+    );
+
+    Ok(DisconnectOutcome {
+        was_connected,
+        saves_purged,
+    })
+}
+
+/// Slack message confirming a disconnect
+///
+/// # Arguments
+/// * `outcome` - Result of `disconnect_user`
+/// * `purge_history` - Whether history deletion was requested
+pub fn confirmation_message(outcome: &DisconnectOutcome, purge_history: bool) -> String {
+    let mut text = if outcome.was_connected {
+        "Spotify is disconnected and your tokens have been deleted.".to_string()
+    } else {
+        "Spotify wasn't connected, so there were no tokens to delete.".to_string()
+    };
+
+    if purge_history {
+        text.push_str(&format!(
+            " Deleted your save history ({} {}).",
+            outcome.saves_purged,
+            if outcome.saves_purged == 1 {
+                "track"
+            } else {
+                "tracks"
+            }
+        ));
+    }
+
+    text.push_str(&format!(
+        " To revoke the app's access on Spotify's side too, remove it at <{}|your Spotify account>.",
+        SPOTIFY_APPS_URL
+    ));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_message_connected() {
+        let outcome = DisconnectOutcome {
+            was_connected: true,
+            saves_purged: 0,
+        };
+        let text = confirmation_message(&outcome, false);
+        assert!(text.starts_with("Spotify is disconnected"));
+        assert!(!text.contains("history"));
+        assert!(text.contains(SPOTIFY_APPS_URL));
+    }
+
+    #[test]
+    fn test_confirmation_message_purged() {
+        let outcome = DisconnectOutcome {
+            was_connected: false,
+            saves_purged: 1,
+        };
+        let text = confirmation_message(&outcome, true);
+        assert!(text.starts_with("Spotify wasn't connected"));
+        assert!(text.contains("(1 track)"));
+        assert!(text.contains(SPOTIFY_APPS_URL));
+    }
+}
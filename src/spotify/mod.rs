@@ -1,4 +1,9 @@
+pub mod app_token;
 pub mod client;
+pub mod connect_link;
+pub mod disconnect;
 pub mod oauth;
 pub mod parser;
+pub mod refresher;
 pub mod routes;
+pub mod state_store;
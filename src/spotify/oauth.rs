@@ -1,14 +1,13 @@
 use crate::config::Config;
 use crate::error::AppError;
+use crate::spotify::state_store::StateStore;
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::{DateTime, Duration, Utc};
 use oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl, basic::BasicClient};
 use rand::Rng;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
 
-/// OAuth state store for CSRF protection
-pub type StateStore = Arc<RwLock<HashMap<String, OAuthState>>>;
+/// How long a state token stays valid after `/spotify/connect`
+pub const STATE_TTL_MINUTES: i64 = 10;
 
 /// OAuth state metadata
 #[derive(Debug, Clone)]
@@ -16,19 +15,31 @@ pub struct OAuthState {
     pub slack_workspace_id: String,
     pub slack_user_id: String,
     pub created_at: DateTime<Utc>,
+    /// PKCE code verifier, sent with the code exchange in the callback
+    pub pkce_verifier: String,
+    /// Workspace whose bot sent the connect link, when it isn't
+    /// `slack_workspace_id` (Grid and Slack Connect users)
+    pub slack_team_id: Option<String>,
+    /// Enterprise Grid org of `slack_team_id`
+    pub slack_enterprise_id: Option<String>,
+    /// Whether the flow started from a connect link the bot signed, so the
+    /// callback may message the user in Slack
+    pub link_signed: bool,
 }
 
 impl OAuthState {
     /// Check if the state token has expired (older than 10 minutes)
     pub fn is_expired(&self) -> bool {
         let now = Utc::now();
-        let expiry = self.created_at + Duration::minutes(10);
+        let expiry = self.created_at + Duration::minutes(STATE_TTL_MINUTES);
         now >= expiry
     }
 }
 
 /// Build OAuth2 client for Spotify
 ///
+/// Authorization and token endpoints are derived from `spotify_accounts_base_url`.
+///
 /// # Arguments
 /// * `config` - Application configuration with Spotify credentials
 ///
@@ -37,10 +48,11 @@ impl OAuthState {
 pub fn build_oauth_client(config: &Config) -> BasicClient {
     let client_id = ClientId::new(config.spotify_client_id.clone());
     let client_secret = ClientSecret::new(config.spotify_client_secret.clone());
-    let auth_url = AuthUrl::new("https://accounts.spotify.com/authorize".to_string())
-        .expect("Invalid auth URL");
-    let token_url = TokenUrl::new("https://accounts.spotify.com/api/token".to_string())
-        .expect("Invalid token URL");
+    let accounts_base_url = config.spotify_accounts_base_url.trim_end_matches('/');
+    let auth_url =
+        AuthUrl::new(format!("{}/authorize", accounts_base_url)).expect("Invalid auth URL");
+    let token_url =
+        TokenUrl::new(format!("{}/api/token", accounts_base_url)).expect("Invalid token URL");
     let redirect_url =
         RedirectUrl::new(config.spotify_redirect_uri.clone()).expect("Invalid redirect URL");
 
@@ -58,30 +70,56 @@ pub fn generate_state_token() -> String {
     URL_SAFE_NO_PAD.encode(random_bytes)
 }
 
+/// Slack user a connect flow links, as named by the connect link
+#[derive(Debug, Clone)]
+pub struct StateParams {
+    /// Slack workspace ID
+    pub workspace_id: String,
+    /// Slack user ID
+    pub user_id: String,
+    /// Workspace whose bot sent the connect link, if known
+    pub team_id: Option<String>,
+    /// Enterprise Grid org of that workspace
+    pub enterprise_id: Option<String>,
+    /// Whether the connect link was signed by the bot
+    pub link_signed: bool,
+}
+
 /// Store OAuth state with metadata
 ///
 /// # Arguments
 /// * `store` - State store
 /// * `token` - State token (generated by generate_state_token)
-/// * `workspace_id` - Slack workspace ID
-/// * `user_id` - Slack user ID
-pub fn store_state(store: &StateStore, token: String, workspace_id: String, user_id: String) {
+/// * `pkce_verifier` - PKCE code verifier for this authorization
+/// * `params` - Slack user the flow links
+///
+/// # Errors
+/// Returns error if the store is full or unavailable
+pub async fn store_state(
+    store: &dyn StateStore,
+    token: String,
+    pkce_verifier: String,
+    params: StateParams,
+) -> Result<(), AppError> {
+    tracing::debug!(
+        "Storing OAuth state for workspace={}, user={}",
+        params.workspace_id,
+        params.user_id
+    );
+
     let state = OAuthState {
-        slack_workspace_id: workspace_id.clone(),
-        slack_user_id: user_id.clone(),
+        slack_workspace_id: params.workspace_id,
+        slack_user_id: params.user_id,
         created_at: Utc::now(),
+        pkce_verifier,
+        slack_team_id: params.team_id,
+        slack_enterprise_id: params.enterprise_id,
+        link_signed: params.link_signed,
     };
 
-    let mut map = store
-        .write()
-        .expect("Failed to acquire write lock on state store");
-    map.insert(token, state);
+    store.insert(token, state).await?;
 
-    tracing::debug!(
-        "Stored OAuth state for workspace={}, user={}",
-        workspace_id,
-        user_id
-    );
+    Ok(())
 }
 
 /// Validate and consume OAuth state token
@@ -94,22 +132,22 @@ pub fn store_state(store: &StateStore, token: String, workspace_id: String, user
 /// * `token` - State token to validate
 ///
 /// # Returns
-/// Tuple of (workspace_id, user_id) if valid
+/// The stored OAuthState (Slack IDs and PKCE verifier) if valid
 ///
 /// # Errors
 /// Returns error if:
 /// - State token not found
 /// - State token has expired (> 10 minutes old)
-pub fn validate_and_consume_state(
-    store: &StateStore,
+/// - The store is unavailable
+pub async fn validate_and_consume_state(
+    store: &dyn StateStore,
     token: &str,
-) -> Result<(String, String), AppError> {
-    let mut map = store
-        .write()
-        .expect("Failed to acquire write lock on state store");
-
+) -> Result<OAuthState, AppError> {
     // Remove the state (one-time use)
-    let state = map.remove(token).ok_or(AppError::OAuthStateNotFound)?;
+    let state = store
+        .consume(token)
+        .await?
+        .ok_or(AppError::OAuthStateNotFound)?;
 
     // Check if expired
     if state.is_expired() {
@@ -123,12 +161,21 @@ pub fn validate_and_consume_state(
         state.slack_user_id
     );
 
-    Ok((state.slack_workspace_id, state.slack_user_id))
+    Ok(state)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::test_config;
+    use crate::spotify::state_store::{MemoryStateStore, StateLimits};
+
+    fn memory_store() -> MemoryStateStore {
+        MemoryStateStore::new(StateLimits {
+            max_pending: 100,
+            max_per_user: 5,
+        })
+    }
 
     #[test]
     fn test_generate_state_token() {
@@ -147,37 +194,49 @@ mod tests {
         assert!(!token2.contains('='));
     }
 
-    #[test]
-    fn test_store_and_validate_state() {
-        let store = Arc::new(RwLock::new(HashMap::new()));
+    #[tokio::test]
+    async fn test_store_and_validate_state() {
+        let store = memory_store();
         let token = generate_state_token();
 
         // Store state
         store_state(
             &store,
             token.clone(),
-            "T123".to_string(),
-            "U456".to_string(),
-        );
+            "test_verifier".to_string(),
+            StateParams {
+                workspace_id: "T123".to_string(),
+                user_id: "U456".to_string(),
+                team_id: Some("T_SENT".to_string()),
+                enterprise_id: Some("E789".to_string()),
+                link_signed: true,
+            },
+        )
+        .await
+        .unwrap();
 
         // Validate and consume
-        let result = validate_and_consume_state(&store, &token);
+        let result = validate_and_consume_state(&store, &token).await;
         assert!(result.is_ok());
 
-        let (workspace_id, user_id) = result.unwrap();
-        assert_eq!(workspace_id, "T123");
-        assert_eq!(user_id, "U456");
+        let oauth_state = result.unwrap();
+        assert_eq!(oauth_state.slack_workspace_id, "T123");
+        assert_eq!(oauth_state.slack_user_id, "U456");
+        assert_eq!(oauth_state.pkce_verifier, "test_verifier");
+        assert_eq!(oauth_state.slack_team_id.as_deref(), Some("T_SENT"));
+        assert_eq!(oauth_state.slack_enterprise_id.as_deref(), Some("E789"));
+        assert!(oauth_state.link_signed);
 
         // Token should be consumed (removed from store)
-        let result2 = validate_and_consume_state(&store, &token);
+        let result2 = validate_and_consume_state(&store, &token).await;
         assert!(result2.is_err());
         assert!(matches!(result2.unwrap_err(), AppError::OAuthStateNotFound));
     }
 
-    #[test]
-    fn test_validate_state_not_found() {
-        let store = Arc::new(RwLock::new(HashMap::new()));
-        let result = validate_and_consume_state(&store, "nonexistent_token");
+    #[tokio::test]
+    async fn test_validate_state_not_found() {
+        let store = memory_store();
+        let result = validate_and_consume_state(&store, "nonexistent_token").await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), AppError::OAuthStateNotFound));
@@ -192,6 +251,10 @@ mod tests {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
             created_at: now,
+            pkce_verifier: "test_verifier".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            link_signed: false,
         };
         assert!(!fresh_state.is_expired());
 
@@ -200,6 +263,10 @@ mod tests {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
             created_at: now - Duration::minutes(11),
+            pkce_verifier: "test_verifier".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            link_signed: false,
         };
         assert!(old_state.is_expired());
 
@@ -208,13 +275,17 @@ mod tests {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
             created_at: now - Duration::minutes(10),
+            pkce_verifier: "test_verifier".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            link_signed: false,
         };
         assert!(edge_state.is_expired());
     }
 
-    #[test]
-    fn test_validate_expired_state() {
-        let store = Arc::new(RwLock::new(HashMap::new()));
+    #[tokio::test]
+    async fn test_validate_expired_state() {
+        let store = memory_store();
         let token = generate_state_token();
 
         // Create an expired state
@@ -222,34 +293,24 @@ mod tests {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
             created_at: Utc::now() - Duration::minutes(11),
+            pkce_verifier: "test_verifier".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            link_signed: false,
         };
 
-        // Manually insert expired state
-        {
-            let mut map = store.write().unwrap();
-            map.insert(token.clone(), expired_state);
-        }
+        // Insert expired state directly
+        store.insert(token.clone(), expired_state).await.unwrap();
 
         // Validate should fail with expired error
-        let result = validate_and_consume_state(&store, &token);
+        let result = validate_and_consume_state(&store, &token).await;
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), AppError::OAuthStateExpired));
     }
 
     #[test]
     fn test_build_oauth_client() {
-        let config = Config {
-            port: 3000,
-            host: "0.0.0.0".to_string(),
-            database_url: "postgresql://localhost/test".to_string(),
-            spotify_client_id: "test_client_id".to_string(),
-            spotify_client_secret: "test_client_secret".to_string(),
-            spotify_redirect_uri: "http://localhost:3000/spotify/callback".to_string(),
-            base_url: "http://localhost:3000".to_string(),
-            slack_signing_secret: None,
-            slack_bot_token: None,
-            rust_log: "info".to_string(),
-        };
+        let config = test_config();
 
         let client = build_oauth_client(&config);
 
@@ -257,4 +318,21 @@ mod tests {
         // but if it doesn't panic, it's good)
         assert!(std::mem::size_of_val(&client) > 0);
     }
+
+    #[test]
+    fn test_build_oauth_client_custom_accounts_url() {
+        let mut config = test_config();
+        config.spotify_accounts_base_url = "http://127.0.0.1:9999/".to_string();
+
+        let client = build_oauth_client(&config);
+
+        assert_eq!(
+            client.auth_url().as_str(),
+            "http://127.0.0.1:9999/authorize"
+        );
+        assert_eq!(
+            client.token_url().unwrap().as_str(),
+            "http://127.0.0.1:9999/api/token"
+        );
+    }
 }
@@ -0,0 +1,149 @@
+use crate::config::Config;
+use crate::crypto::TokenCipher;
+use crate::db::models::TokenHealthCounts;
+use crate::db::repository::{count_token_health, list_token_refresh_candidates};
+use crate::error::AppError;
+use crate::metrics::metrics;
+use crate::spotify::client::{SpotifyClient, TOKEN_REFRESH_BUFFER, refresh_access_token};
+use chrono::Utc;
+use oauth2::basic::BasicClient;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Maximum number of tokens refreshed per pass
+const REFRESH_BATCH_SIZE: i64 = 100;
+
+/// Background token refresher settings
+#[derive(Debug, Clone)]
+pub struct RefresherConfig {
+    /// How often to look for tokens due for refresh
+    pub interval: Duration,
+    /// Accounts with a track save this recent are refreshed ahead of expiry
+    pub active_window: chrono::Duration,
+    /// Idle accounts are refreshed at most this often, to detect revoked grants
+    pub probe_interval: chrono::Duration,
+}
+
+impl RefresherConfig {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            interval: Duration::from_secs(config.token_refresh_interval_secs),
+            active_window: chrono::Duration::hours(config.token_active_window_hours),
+            probe_interval: chrono::Duration::hours(config.token_probe_interval_hours),
+        }
+    }
+}
+
+/// Outcome of one refresher pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshPass {
+    /// Tokens refreshed (or found already refreshed by a concurrent caller)
+    pub refreshed: u64,
+    /// Accounts whose refresh token Spotify rejected, now flagged `needs_reauth`
+    pub revoked: u64,
+    /// Refreshes that failed for other reasons and will be retried next pass
+    pub failed: u64,
+}
+
+/// Refreshes Spotify tokens before they're needed
+///
+/// Tokens are otherwise only refreshed when a mention needs one, so the first
+/// save after a quiet spell pays for the refresh and a revoked grant is only
+/// discovered then. Each pass refreshes tokens of recently active accounts
+/// that are about to expire, and probes idle accounts once per probe interval
+/// so revoked grants show up as `needs_reauth` (and in the health counts)
+/// early. Refreshes go through `refresh_access_token`, whose row lock makes
+/// concurrent passes on several instances safe.
+#[derive(Clone)]
+pub struct TokenRefresher {
+    pub db: PgPool,
+    pub cipher: TokenCipher,
+    pub oauth_client: BasicClient,
+    pub spotify_client: SpotifyClient,
+    pub config: RefresherConfig,
+}
+
+impl TokenRefresher {
+    /// Refresh due tokens once and update the token health gauges
+    ///
+    /// # Returns
+    /// Counts of refreshed, revoked and failed tokens
+    ///
+    /// # Errors
+    /// Returns error if the candidate or health queries fail; failures of
+    /// individual refreshes are counted instead
+    pub async fn run_once(&self) -> Result<RefreshPass, AppError> {
+        let now = Utc::now();
+        let candidates = list_token_refresh_candidates(
+            &self.db,
+            now + TOKEN_REFRESH_BUFFER,
+            now - self.config.active_window,
+            now - self.config.probe_interval,
+            REFRESH_BATCH_SIZE,
+        )
+        .await?;
+
+        let mut pass = RefreshPass::default();
+        for user_auth_id in candidates {
+            match refresh_access_token(
+                &self.db,
+                &self.cipher,
+                &self.oauth_client,
+                &self.spotify_client,
+                user_auth_id,
+            )
+            .await
+            {
+                Ok(_) => pass.refreshed += 1,
+                Err(AppError::SpotifyReauthRequired) => pass.revoked += 1,
+                Err(e) => {
+                    tracing::warn!(
+                        user_auth_id = %user_auth_id,
+                        error = ?e,
+                        "Background token refresh failed"
+                    );
+                    pass.failed += 1;
+                }
+            }
+        }
+        metrics().token_refresh_pass(&pass);
+
+        let health = self.health().await?;
+        metrics().set_token_health(&health);
+
+        Ok(pass)
+    }
+
+    /// Count accounts by token state
+    ///
+    /// # Errors
+    /// Returns error if the database query fails
+    pub async fn health(&self) -> Result<TokenHealthCounts, AppError> {
+        Ok(count_token_health(&self.db, Utc::now() + TOKEN_REFRESH_BUFFER).await?)
+    }
+}
+
+/// Spawn a task that runs the token refresher periodically
+///
+/// # Arguments
+/// * `refresher` - Token refresher to run
+pub fn spawn_refresh_task(refresher: TokenRefresher) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresher.config.interval);
+        loop {
+            interval.tick().await;
+
+            match refresher.run_once().await {
+                Ok(pass) if pass == RefreshPass::default() => {}
+                Ok(pass) => tracing::info!(
+                    refreshed = pass.refreshed,
+                    revoked = pass.revoked,
+                    failed = pass.failed,
+                    "Refreshed Spotify tokens in the background"
+                ),
+                Err(e) => tracing::error!(error = ?e, "Background token refresh pass failed"),
+            }
+        }
+    })
+}
@@ -1,9 +1,17 @@
-use crate::db::repository::upsert_user_auth;
+use crate::crypto::TokenCipher;
+use crate::db::repository::{
+    UserAuthParams, count_other_spotify_links, get_grid_enterprise, get_user_auth,
+    update_spotify_profile, upsert_user_auth,
+};
 use crate::error::AppError;
-use crate::spotify::client::{ensure_valid_token, get_current_user};
+use crate::slack::client::SlackClient;
+use crate::slack::tokens::BotTokens;
+use crate::spotify::client::{SpotifyClient, SpotifyUser, ensure_valid_token};
+use crate::spotify::connect_link::ConnectLinks;
 use crate::spotify::oauth::{
-    StateStore, generate_state_token, store_state, validate_and_consume_state,
+    OAuthState, StateParams, generate_state_token, store_state, validate_and_consume_state,
 };
+use crate::spotify::state_store::SharedStateStore;
 use axum::{
     Json,
     extract::{Query, State},
@@ -11,8 +19,8 @@ use axum::{
 };
 use chrono::{Duration, Utc};
 use oauth2::{
-    AuthorizationCode, CsrfToken, Scope, TokenResponse, basic::BasicClient,
-    reqwest::async_http_client,
+    AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, Scope, TokenResponse,
+    basic::BasicClient,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -22,14 +30,29 @@ use sqlx::PgPool;
 pub struct ConnectQuery {
     pub slack_workspace_id: String,
     pub slack_user_id: String,
+    /// Workspace whose bot sent the link, when it isn't `slack_workspace_id`
+    #[serde(default)]
+    pub slack_team_id: Option<String>,
+    #[serde(default)]
+    pub slack_enterprise_id: Option<String>,
+    /// Signature of links sent by the bot (see `ConnectLinks`)
+    #[serde(default)]
+    pub sig: Option<String>,
 }
 
 /// Application state for Spotify routes
 #[derive(Clone)]
 pub struct SpotifyState {
     pub oauth_client: BasicClient,
-    pub state_store: StateStore,
+    pub spotify_client: SpotifyClient,
+    pub state_store: SharedStateStore,
     pub db: PgPool,
+    pub cipher: TokenCipher,
+    /// Builds links back to the connect flow
+    pub connect_links: ConnectLinks,
+    pub slack_client: SlackClient,
+    /// Bot tokens for notifying users in Slack
+    pub bot_tokens: BotTokens,
 }
 
 /// Initiates Spotify OAuth flow
@@ -38,14 +61,18 @@ pub struct SpotifyState {
 /// GET /spotify/connect?slack_workspace_id=<WORKSPACE>&slack_user_id=<USER>
 ///
 /// # Flow
-/// 1. Generate cryptographically secure state token
-/// 2. Store state with Slack user metadata
-/// 3. Build Spotify authorization URL with required scopes
+/// 1. Generate cryptographically secure state token and PKCE verifier
+/// 2. Store state with Slack user metadata and the verifier
+/// 3. Build Spotify authorization URL with required scopes and S256 code challenge
 /// 4. Redirect user to Spotify for authorization
 ///
 /// # Query Parameters
 /// - `slack_workspace_id`: Slack workspace ID (e.g., "T123ABC")
 /// - `slack_user_id`: Slack user ID (e.g., "U456DEF")
+/// - `slack_team_id`, `slack_enterprise_id`: Workspace (and its Grid org)
+///   whose bot sent the link, if other than `slack_workspace_id`
+/// - `sig`: Signature of links sent by the bot; unsigned links still connect,
+///   but the user isn't messaged in Slack if authorization fails
 ///
 /// # Returns
 /// 302 redirect to Spotify authorization page
@@ -62,14 +89,30 @@ pub async fn connect(
         "Starting Spotify OAuth connect flow"
     );
 
-    // Generate and store state token
+    let link_signed = state.connect_links.verify(
+        &params.slack_workspace_id,
+        &params.slack_user_id,
+        params.slack_team_id.as_deref(),
+        params.slack_enterprise_id.as_deref(),
+        params.sig.as_deref(),
+    );
+
+    // Generate and store state token, with the PKCE verifier for the callback
     let state_token = generate_state_token();
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
     store_state(
-        &state.state_store,
+        state.state_store.as_ref(),
         state_token.clone(),
-        params.slack_workspace_id.clone(),
-        params.slack_user_id.clone(),
-    );
+        pkce_verifier.secret().clone(),
+        StateParams {
+            workspace_id: params.slack_workspace_id,
+            user_id: params.slack_user_id,
+            team_id: params.slack_team_id,
+            enterprise_id: params.slack_enterprise_id,
+            link_signed,
+        },
+    )
+    .await?;
 
     tracing::debug!(
         state_token_length = state_token.len(),
@@ -81,6 +124,8 @@ pub async fn connect(
         .oauth_client
         .authorize_url(|| CsrfToken::new(state_token))
         .add_scope(Scope::new("user-library-modify".to_string()))
+        .add_scope(Scope::new("user-read-private".to_string()))
+        .set_pkce_challenge(pkce_challenge)
         .url();
 
     tracing::info!(
@@ -91,51 +136,215 @@ pub async fn connect(
     Ok(Redirect::to(auth_url.as_str()))
 }
 
-/// Render the success page with workspace and user information
+/// Render the success page with workspace, user and Spotify account information
 ///
 /// # Arguments
 /// * `workspace_id` - Slack workspace ID
 /// * `user_id` - Slack user ID
+/// * `spotify_user` - Linked Spotify profile, if it could be fetched
+/// * `other_links` - Other Slack users linked to the same Spotify account
 ///
 /// # Returns
-/// HTML string with placeholders replaced
-fn render_success_page(workspace_id: &str, user_id: &str) -> String {
+/// HTML string with placeholders replaced (values are HTML-escaped)
+fn render_success_page(
+    workspace_id: &str,
+    user_id: &str,
+    spotify_user: Option<&SpotifyUser>,
+    other_links: i64,
+) -> String {
     const TEMPLATE: &str = include_str!("../../templates/spotify_success.html");
 
+    let account = match spotify_user {
+        Some(user) => {
+            let name = match &user.display_name {
+                Some(display_name) => format!("{} ({})", display_name, user.id),
+                None => user.id.clone(),
+            };
+            match &user.product {
+                Some(product) => format!("{}, {}", name, product),
+                None => name,
+            }
+        }
+        None => "unknown".to_string(),
+    };
+
+    let notice = if other_links > 0 {
+        format!(
+            "<p class=\"notice\">This Spotify account is also linked to {} other Slack {}. Tracks they save will go to the same library.</p>",
+            other_links,
+            if other_links == 1 { "user" } else { "users" }
+        )
+    } else {
+        String::new()
+    };
+
     TEMPLATE
-        .replace("{{WORKSPACE_ID}}", workspace_id)
-        .replace("{{USER_ID}}", user_id)
+        .replace("{{WORKSPACE_ID}}", &escape_html(workspace_id))
+        .replace("{{USER_ID}}", &escape_html(user_id))
+        .replace("{{SPOTIFY_ACCOUNT}}", &escape_html(&account))
+        .replace("{{NOTICE}}", &notice)
+}
+
+/// Render the failure page shown when authorization was denied or failed
+///
+/// # Arguments
+/// * `error` - OAuth error code from Spotify (e.g., "access_denied")
+/// * `error_description` - Optional human-readable description from Spotify
+/// * `retry_url` - Link that restarts the connect flow
+///
+/// # Returns
+/// HTML string with placeholders replaced (values are HTML-escaped)
+fn render_error_page(error: &str, error_description: Option<&str>, retry_url: &str) -> String {
+    const TEMPLATE: &str = include_str!("../../templates/spotify_error.html");
+
+    let (title, message) = if error == "access_denied" {
+        (
+            "Spotify Not Connected",
+            "You cancelled connecting your Spotify account, so savethebeat can't save tracks for you yet.".to_string(),
+        )
+    } else {
+        (
+            "Spotify Connection Failed",
+            format!(
+                "Spotify couldn't complete the connection ({}).",
+                error_description.unwrap_or(error)
+            ),
+        )
+    };
+
+    TEMPLATE
+        .replace("{{TITLE}}", &escape_html(title))
+        .replace("{{MESSAGE}}", &escape_html(&message))
+        .replace("{{RETRY_URL}}", &escape_html(retry_url))
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Slack message for an authorization error Spotify reports to the callback
+///
+/// Only the error codes of the OAuth 2.0 authorization response have a
+/// message, and the code itself is never part of it: the callback URL can be
+/// opened by anyone, with any `error`.
+///
+/// # Returns
+/// The message, or None for an unknown error code
+fn authorization_failed_message(error: &str, retry_url: &str) -> Option<String> {
+    let text = match error {
+        "access_denied" => format!(
+            "Spotify linking was cancelled. <{}|Connect Spotify> whenever you're ready.",
+            retry_url
+        ),
+        "server_error" | "temporarily_unavailable" => format!(
+            "Spotify couldn't link your account right now. <{}|Try again> in a few minutes.",
+            retry_url
+        ),
+        "invalid_request"
+        | "unauthorized_client"
+        | "unsupported_response_type"
+        | "invalid_scope" => format!(
+            "Spotify linking failed because Spotify rejected savethebeat's request. <{}|Try again>, and let your workspace admin know if it keeps failing.",
+            retry_url
+        ),
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// Tell the user in Slack that linking didn't complete
+///
+/// Best effort: failures are logged, since the browser already shows the
+/// failure page. Only flows started from a link the bot signed notify the
+/// user, and only for known error codes (see `authorization_failed_message`).
+/// The message goes through the installation of the workspace that sent the
+/// connect link.
+async fn notify_authorization_failed(
+    state: &SpotifyState,
+    oauth_state: &OAuthState,
+    error: &str,
+    retry_url: &str,
+) {
+    if !oauth_state.link_signed {
+        tracing::debug!("Connect link wasn't signed, not notifying user");
+        return;
+    }
+    let Some(text) = authorization_failed_message(error, retry_url) else {
+        tracing::debug!("Unknown authorization error, not notifying user");
+        return;
+    };
+
+    let team_id = oauth_state
+        .slack_team_id
+        .as_deref()
+        .unwrap_or(&oauth_state.slack_workspace_id);
+
+    // Nothing to notify through if the app isn't installed in the workspace
+    let bot_token = match state
+        .bot_tokens
+        .for_team(team_id, oauth_state.slack_enterprise_id.as_deref())
+        .await
+    {
+        Ok(bot_token) => bot_token,
+        Err(e) => {
+            tracing::debug!(error = ?e, "No Slack bot token, not notifying user");
+            return;
+        }
+    };
+
+    if let Err(e) = state
+        .slack_client
+        .post_message(&bot_token, &oauth_state.slack_user_id, &text)
+        .await
+    {
+        tracing::warn!(error = ?e, "Failed to notify user about Spotify authorization failure");
+    }
 }
 
 /// Query parameters for /spotify/callback endpoint
+///
+/// Spotify sends `code` on success, or `error` (e.g., "access_denied" when the
+/// user clicks Cancel) with an optional `error_description`.
 #[derive(Debug, Deserialize)]
 pub struct CallbackQuery {
-    pub code: String,
+    pub code: Option<String>,
     pub state: String,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
 }
 
 /// Handles Spotify OAuth callback
 ///
 /// # Endpoint
 /// GET /spotify/callback?code=<CODE>&state=<STATE>
+/// GET /spotify/callback?error=<ERROR>&state=<STATE>
 ///
 /// # Flow
 /// 1. Validate and consume state token (CSRF protection)
-/// 2. Extract Slack workspace and user IDs from state
-/// 3. Exchange authorization code for access/refresh tokens
+/// 2. Extract Slack workspace and user IDs and PKCE verifier from state
+///    - If Spotify sent an error, notify the user in Slack and render the failure page
+/// 3. Exchange authorization code and verifier for access/refresh tokens
 /// 4. Calculate token expiry with 5-minute buffer
-/// 5. Upsert tokens to database
-/// 6. Return success HTML page
+/// 5. Fetch the Spotify profile (`/v1/me`)
+/// 6. Upsert tokens and profile to database, warning if the Spotify account is
+///    already linked to other Slack users
+/// 7. Return success HTML page showing the linked account
 ///
 /// # Query Parameters
-/// - `code`: Authorization code from Spotify
+/// - `code`: Authorization code from Spotify (absent if authorization failed)
 /// - `state`: State token (must match stored token)
+/// - `error`, `error_description`: Set by Spotify if authorization was denied or failed
 ///
 /// # Returns
-/// HTML success page
+/// HTML success page, or failure page with a retry link
 ///
 /// # Errors
-/// - 400 Bad Request if state is invalid or expired
+/// - 400 Bad Request if state is invalid or expired, or neither code nor error is present
 /// - 500 Internal Server Error if token exchange or database operation fails
 pub async fn callback(
     State(state): State<SpotifyState>,
@@ -144,7 +353,9 @@ pub async fn callback(
     tracing::info!("Received Spotify OAuth callback");
 
     // Validate and consume state token
-    let (workspace_id, user_id) = validate_and_consume_state(&state.state_store, &params.state)?;
+    let oauth_state = validate_and_consume_state(state.state_store.as_ref(), &params.state).await?;
+    let workspace_id = oauth_state.slack_workspace_id.clone();
+    let user_id = oauth_state.slack_user_id.clone();
 
     tracing::info!(
         slack_workspace_id = %workspace_id,
@@ -152,13 +363,37 @@ pub async fn callback(
         "Validated OAuth state"
     );
 
+    if let Some(error) = params.error {
+        tracing::warn!(
+            slack_workspace_id = %workspace_id,
+            slack_user_id = %user_id,
+            error = %error,
+            error_description = ?params.error_description,
+            "Spotify authorization was not granted"
+        );
+
+        let retry_url = state.connect_links.retry_url(&oauth_state);
+        notify_authorization_failed(&state, &oauth_state, &error, &retry_url).await;
+
+        return Ok(Html(render_error_page(
+            &error,
+            params.error_description.as_deref(),
+            &retry_url,
+        )));
+    }
+
+    let code = params
+        .code
+        .ok_or_else(|| AppError::BadRequest("Missing authorization code".to_string()))?;
+
     // Exchange authorization code for tokens
     tracing::debug!("Exchanging authorization code for tokens");
 
     let token_result = state
         .oauth_client
-        .exchange_code(AuthorizationCode::new(params.code))
-        .request_async(async_http_client)
+        .exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(PkceCodeVerifier::new(oauth_state.pkce_verifier))
+        .request_async(|request| state.spotify_client.oauth_http(request))
         .await
         .map_err(|e| {
             tracing::error!("Token exchange failed: {:?}", e);
@@ -192,15 +427,33 @@ pub async fn callback(
         "Received tokens from Spotify"
     );
 
+    // Fetch the linked Spotify profile; the tokens are stored even if this
+    // fails, with the profile cleared
+    let spotify_user = match state.spotify_client.get_current_user(&access_token).await {
+        Ok(user) => Some(user),
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to fetch Spotify profile after connect");
+            None
+        }
+    };
+
+    // Links sent before Grid users were keyed by enterprise ID name the workspace
+    let workspace_id = get_grid_enterprise(&state.db, &workspace_id)
+        .await?
+        .unwrap_or(workspace_id);
+
     // Store tokens in database
     let user_auth = upsert_user_auth(
         &state.db,
-        &workspace_id,
-        &user_id,
-        None, // spotify_user_id - we'll get this later when we call /v1/me
-        &access_token,
-        &refresh_token,
-        expires_at,
+        &state.cipher,
+        UserAuthParams {
+            workspace_id: &workspace_id,
+            user_id: &user_id,
+            profile: spotify_user.as_ref().map(SpotifyUser::profile_params),
+            access_token: &access_token,
+            refresh_token: &refresh_token,
+            expires_at,
+        },
     )
     .await
     .map_err(|e| {
@@ -215,8 +468,21 @@ pub async fn callback(
         "Successfully stored Spotify tokens"
     );
 
+    let mut other_links = 0;
+    if let Some(spotify_user) = &spotify_user {
+        other_links = count_other_spotify_links(&state.db, &spotify_user.id, user_auth.id).await?;
+        if other_links > 0 {
+            tracing::warn!(
+                user_auth_id = %user_auth.id,
+                spotify_user_id = %spotify_user.id,
+                other_links,
+                "Spotify account is linked to several Slack users"
+            );
+        }
+    }
+
     // Return success HTML page
-    let html = render_success_page(&workspace_id, &user_id);
+    let html = render_success_page(&workspace_id, &user_id, spotify_user.as_ref(), other_links);
 
     Ok(Html(html))
 }
@@ -246,7 +512,8 @@ pub struct VerifyResponse {
 /// 1. Fetch user authentication from database
 /// 2. Ensure token is valid (refresh if expired)
 /// 3. Call Spotify API to verify token works
-/// 4. Return user profile information
+/// 4. Update the stored Spotify profile
+/// 5. Return user profile information
 ///
 /// # Query Parameters
 /// - `slack_workspace_id`: Slack workspace ID (e.g., "T123ABC")
@@ -271,7 +538,9 @@ pub async fn verify(
     // Ensure we have a valid access token (will refresh if needed)
     let access_token = ensure_valid_token(
         &state.db,
+        &state.cipher,
         &state.oauth_client,
+        &state.spotify_client,
         &params.slack_workspace_id,
         &params.slack_user_id,
     )
@@ -280,7 +549,19 @@ pub async fn verify(
     tracing::debug!("Obtained valid access token");
 
     // Call Spotify API to verify token works
-    let spotify_user = get_current_user(&access_token).await?;
+    let spotify_user = state.spotify_client.get_current_user(&access_token).await?;
+
+    // Keep the stored profile current
+    if let Some(user_auth) = get_user_auth(
+        &state.db,
+        &state.cipher,
+        &params.slack_workspace_id,
+        &params.slack_user_id,
+    )
+    .await?
+    {
+        update_spotify_profile(&state.db, user_auth.id, spotify_user.profile_params()).await?;
+    }
 
     tracing::info!(
         spotify_user_id = %spotify_user.id,
@@ -299,24 +580,13 @@ pub async fn verify(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
+    use crate::config::test_config;
     use crate::spotify::oauth::build_oauth_client;
-    use std::collections::HashMap;
-    use std::sync::{Arc, RwLock};
-
-    async fn setup_test_state() -> SpotifyState {
-        let config = Config {
-            port: 3000,
-            host: "0.0.0.0".to_string(),
-            database_url: "postgresql://localhost/savethebeat_test".to_string(),
-            spotify_client_id: "test_client_id".to_string(),
-            spotify_client_secret: "test_client_secret".to_string(),
-            spotify_redirect_uri: "http://localhost:3000/spotify/callback".to_string(),
-            base_url: "http://localhost:3000".to_string(),
-            slack_signing_secret: None,
-            slack_bot_token: None,
-            rust_log: "info".to_string(),
-        };
+    use crate::spotify::state_store::{MemoryStateStore, StateLimits};
+    use std::sync::Arc;
+
+    async fn setup_test_state() -> (SpotifyState, Arc<MemoryStateStore>) {
+        let config = test_config();
 
         // Create a lazy database pool for tests (won't connect until needed)
         let db = sqlx::postgres::PgPoolOptions::new()
@@ -324,19 +594,34 @@ mod tests {
             .connect_lazy(&config.database_url)
             .unwrap();
 
-        SpotifyState {
+        let store = Arc::new(MemoryStateStore::new(StateLimits::from_config(&config)));
+
+        let state = SpotifyState {
             oauth_client: build_oauth_client(&config),
-            state_store: Arc::new(RwLock::new(HashMap::new())),
-            db,
-        }
+            spotify_client: SpotifyClient::new(
+                reqwest::Client::new(),
+                &config.spotify_api_base_url,
+            ),
+            state_store: store.clone(),
+            db: db.clone(),
+            cipher: TokenCipher::default(),
+            connect_links: ConnectLinks::from_config(&config),
+            slack_client: SlackClient::new(reqwest::Client::new(), &config.slack_api_base_url),
+            bot_tokens: BotTokens::new(db, TokenCipher::default(), None),
+        };
+
+        (state, store)
     }
 
     #[tokio::test]
     async fn test_connect_generates_redirect() {
-        let state = setup_test_state().await;
+        let (state, store) = setup_test_state().await;
         let params = ConnectQuery {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            sig: None,
         };
 
         let result = connect(State(state.clone()), Query(params)).await;
@@ -344,21 +629,48 @@ mod tests {
         assert!(result.is_ok());
 
         // Verify state was stored
-        let store = state.state_store.read().unwrap();
         assert_eq!(store.len(), 1);
 
         // Verify stored state contains correct metadata
-        let (_token, oauth_state) = store.iter().next().unwrap();
+        let oauth_state = &store.states()[0];
         assert_eq!(oauth_state.slack_workspace_id, "T123");
         assert_eq!(oauth_state.slack_user_id, "U456");
+        assert!(!oauth_state.link_signed);
+    }
+
+    #[tokio::test]
+    async fn test_connect_records_signed_link() {
+        let (state, store) = setup_test_state().await;
+        let link = state
+            .connect_links
+            .url("E1", "U456", Some("T123"), Some("E1"));
+        let Query(params) = Query::<ConnectQuery>::try_from_uri(&link.parse().unwrap()).unwrap();
+        assert!(connect(State(state.clone()), Query(params)).await.is_ok());
+
+        // A link for another user with the same signature isn't signed
+        let Query(mut forged) =
+            Query::<ConnectQuery>::try_from_uri(&link.parse().unwrap()).unwrap();
+        forged.slack_user_id = "U_VICTIM".to_string();
+        assert!(connect(State(state), Query(forged)).await.is_ok());
+
+        let mut states = store.states();
+        states.sort_by_key(|oauth_state| oauth_state.slack_user_id.clone());
+        assert_eq!(states[0].slack_user_id, "U456");
+        assert!(states[0].link_signed);
+        assert_eq!(states[0].slack_team_id.as_deref(), Some("T123"));
+        assert_eq!(states[1].slack_user_id, "U_VICTIM");
+        assert!(!states[1].link_signed);
     }
 
     #[tokio::test]
     async fn test_connect_redirect_url_format() {
-        let state = setup_test_state().await;
+        let (state, _store) = setup_test_state().await;
         let params = ConnectQuery {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            sig: None,
         };
 
         let result = connect(State(state), Query(params)).await;
@@ -370,12 +682,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_connect_multiple_users() {
-        let state = setup_test_state().await;
+        let (state, store) = setup_test_state().await;
 
         // Connect first user
         let params1 = ConnectQuery {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U456".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            sig: None,
         };
         let result1 = connect(State(state.clone()), Query(params1)).await;
         assert!(result1.is_ok());
@@ -384,21 +699,25 @@ mod tests {
         let params2 = ConnectQuery {
             slack_workspace_id: "T123".to_string(),
             slack_user_id: "U789".to_string(),
+            slack_team_id: None,
+            slack_enterprise_id: None,
+            sig: None,
         };
         let result2 = connect(State(state.clone()), Query(params2)).await;
         assert!(result2.is_ok());
 
         // Verify both states are stored
-        let store = state.state_store.read().unwrap();
         assert_eq!(store.len(), 2);
     }
 
     #[tokio::test]
     async fn test_callback_invalid_state() {
-        let state = setup_test_state().await;
+        let (state, _store) = setup_test_state().await;
         let params = CallbackQuery {
-            code: "test_code".to_string(),
+            code: Some("test_code".to_string()),
             state: "invalid_state_token".to_string(),
+            error: None,
+            error_description: None,
         };
 
         let result = callback(State(state), Query(params)).await;
@@ -406,4 +725,119 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), AppError::OAuthStateNotFound));
     }
+
+    #[tokio::test]
+    async fn test_callback_access_denied_renders_failure_page() {
+        let (state, store) = setup_test_state().await;
+        store_state(
+            store.as_ref(),
+            "denied_state".to_string(),
+            "verifier".to_string(),
+            StateParams {
+                workspace_id: "T123".to_string(),
+                user_id: "U456".to_string(),
+                team_id: None,
+                enterprise_id: None,
+                link_signed: false,
+            },
+        )
+        .await
+        .unwrap();
+
+        let params = CallbackQuery {
+            code: None,
+            state: "denied_state".to_string(),
+            error: Some("access_denied".to_string()),
+            error_description: None,
+        };
+
+        let Html(page) = callback(State(state), Query(params)).await.unwrap();
+        assert!(page.contains("Spotify Not Connected"));
+        assert!(page.contains(
+            "http://localhost:3000/spotify/connect?slack_workspace_id=T123&amp;slack_user_id=U456"
+        ));
+
+        // State is consumed
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_callback_without_code_or_error() {
+        let (state, store) = setup_test_state().await;
+        store_state(
+            store.as_ref(),
+            "state".to_string(),
+            "verifier".to_string(),
+            StateParams {
+                workspace_id: "T123".to_string(),
+                user_id: "U456".to_string(),
+                team_id: None,
+                enterprise_id: None,
+                link_signed: false,
+            },
+        )
+        .await
+        .unwrap();
+
+        let params = CallbackQuery {
+            code: None,
+            state: "state".to_string(),
+            error: None,
+            error_description: None,
+        };
+
+        let result = callback(State(state), Query(params)).await;
+        assert!(matches!(result.unwrap_err(), AppError::BadRequest(_)));
+    }
+
+    #[test]
+    fn test_authorization_failed_message_never_echoes_error() {
+        let retry_url = "http://localhost:3000/spotify/connect";
+        let cancelled = authorization_failed_message("access_denied", retry_url).unwrap();
+        assert!(cancelled.contains("cancelled"));
+        assert!(cancelled.contains(retry_url));
+
+        for error in ["server_error", "invalid_scope"] {
+            let text = authorization_failed_message(error, retry_url).unwrap();
+            assert!(!text.contains(error), "{}", text);
+            assert!(text.contains(retry_url));
+        }
+
+        assert!(authorization_failed_message("<!channel> click here", retry_url).is_none());
+        assert!(authorization_failed_message("", retry_url).is_none());
+    }
+
+    #[test]
+    fn test_render_error_page_escapes_description() {
+        let page = render_error_page(
+            "server_error",
+            Some("<script>alert(1)</script>"),
+            "http://localhost:3000/spotify/connect",
+        );
+
+        assert!(page.contains("Spotify Connection Failed"));
+        assert!(page.contains("&lt;script&gt;"));
+        assert!(!page.contains("<script>"));
+    }
+
+    #[test]
+    fn test_render_success_page_shows_account() {
+        let user = SpotifyUser {
+            id: "spotify123".to_string(),
+            display_name: Some("<b>Listener</b>".to_string()),
+            country: Some("SE".to_string()),
+            product: Some("premium".to_string()),
+        };
+
+        let page = render_success_page("T123", "U456", Some(&user), 0);
+        assert!(page.contains("&lt;b&gt;Listener&lt;/b&gt; (spotify123), premium"));
+        assert!(!page.contains("{{NOTICE}}"));
+        assert!(!page.contains("also linked"));
+
+        let page = render_success_page("T123", "U456", Some(&user), 2);
+        assert!(page.contains("also linked to 2 other Slack users"));
+
+        let page = render_success_page("T123", "U456", None, 0);
+        assert!(page.contains("unknown"));
+    }
 }
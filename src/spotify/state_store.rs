@@ -0,0 +1,349 @@
+//! Storage for pending Spotify OAuth states
+//!
+//! `/spotify/connect` stores a state per attempt and `/spotify/callback`
+//! consumes it. The Postgres store lets the callback land on any instance; the
+//! in-memory store only works when a single instance serves both requests.
+
+use crate::config::Config;
+use crate::db::models::OAuthStateRecord;
+use crate::db::repository::{
+    consume_oauth_state, count_oauth_states_since, delete_oauth_states_before, insert_oauth_state,
+    trim_oauth_states_for_user,
+};
+use crate::error::AppError;
+use crate::spotify::oauth::{OAuthState, STATE_TTL_MINUTES};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+
+/// How often expired states are purged
+const CLEANUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Limits on pending states, so connect spam can't grow the store unbounded
+#[derive(Debug, Clone, Copy)]
+pub struct StateLimits {
+    /// Pending states across all users; new attempts are refused beyond this
+    pub max_pending: usize,
+    /// Pending states per Slack user; the oldest are dropped beyond this
+    pub max_per_user: usize,
+}
+
+impl StateLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_pending: config.oauth_state_max_pending,
+            max_per_user: config.oauth_state_max_per_user.max(1),
+        }
+    }
+}
+
+/// Pending OAuth state storage
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    /// Store a new state, enforcing the store's limits
+    ///
+    /// # Errors
+    /// Returns `AppError::TooManyRequests` if the store is full
+    async fn insert(&self, token: String, state: OAuthState) -> Result<(), AppError>;
+
+    /// Remove and return a state (one-time use)
+    ///
+    /// Returns the state even if it has expired; callers check expiry.
+    async fn consume(&self, token: &str) -> Result<Option<OAuthState>, AppError>;
+
+    /// Delete expired states
+    ///
+    /// # Returns
+    /// Number of states deleted
+    async fn purge_expired(&self) -> Result<u64, AppError>;
+}
+
+/// State store shared between handlers
+pub type SharedStateStore = Arc<dyn StateStore>;
+
+/// Build the state store selected by `OAUTH_STATE_STORE`
+///
+/// # Errors
+/// Returns error if the configured store is unknown
+pub fn build_state_store(config: &Config, db: PgPool) -> anyhow::Result<SharedStateStore> {
+    let limits = StateLimits::from_config(config);
+    match config.oauth_state_store.as_str() {
+        "postgres" => Ok(Arc::new(PgStateStore::new(db, limits))),
+        "memory" => Ok(Arc::new(MemoryStateStore::new(limits))),
+        other => anyhow::bail!(
+            "Unknown OAUTH_STATE_STORE {:?} (expected postgres or memory)",
+            other
+        ),
+    }
+}
+
+/// Spawn a task that periodically purges expired OAuth states
+pub fn spawn_cleanup_task(store: SharedStateStore) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            match store.purge_expired().await {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!(deleted, "Purged expired OAuth states"),
+                Err(e) => tracing::error!(error = ?e, "Failed to purge expired OAuth states"),
+            }
+        }
+    })
+}
+
+fn too_many_pending() -> AppError {
+    AppError::TooManyRequests(
+        "Too many pending Spotify connection attempts, try again later".to_string(),
+    )
+}
+
+/// In-process state store for single-instance deployments
+#[derive(Debug)]
+pub struct MemoryStateStore {
+    states: RwLock<HashMap<String, OAuthState>>,
+    limits: StateLimits,
+}
+
+impl MemoryStateStore {
+    pub fn new(limits: StateLimits) -> Self {
+        Self {
+            states: RwLock::new(HashMap::new()),
+            limits,
+        }
+    }
+
+    /// Number of stored states, including expired ones not yet purged
+    pub fn len(&self) -> usize {
+        self.states
+            .read()
+            .expect("Failed to acquire read lock on state store")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Snapshot of the stored states
+    #[cfg(test)]
+    pub(crate) fn states(&self) -> Vec<OAuthState> {
+        self.states.read().unwrap().values().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl StateStore for MemoryStateStore {
+    async fn insert(&self, token: String, state: OAuthState) -> Result<(), AppError> {
+        let mut map = self
+            .states
+            .write()
+            .expect("Failed to acquire write lock on state store");
+
+        // Drop the user's oldest states to make room for this one
+        let mut user_tokens: Vec<(String, chrono::DateTime<Utc>)> = map
+            .iter()
+            .filter(|(_, s)| {
+                s.slack_workspace_id == state.slack_workspace_id
+                    && s.slack_user_id == state.slack_user_id
+            })
+            .map(|(t, s)| (t.clone(), s.created_at))
+            .collect();
+        if user_tokens.len() >= self.limits.max_per_user {
+            user_tokens.sort_by_key(|(_, created_at)| *created_at);
+            let excess = user_tokens.len() + 1 - self.limits.max_per_user;
+            for (token, _) in user_tokens.into_iter().take(excess) {
+                map.remove(&token);
+            }
+        }
+
+        if map.len() >= self.limits.max_pending {
+            map.retain(|_, s| !s.is_expired());
+            if map.len() >= self.limits.max_pending {
+                return Err(too_many_pending());
+            }
+        }
+
+        map.insert(token, state);
+        Ok(())
+    }
+
+    async fn consume(&self, token: &str) -> Result<Option<OAuthState>, AppError> {
+        let mut map = self
+            .states
+            .write()
+            .expect("Failed to acquire write lock on state store");
+        Ok(map.remove(token))
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let mut map = self
+            .states
+            .write()
+            .expect("Failed to acquire write lock on state store");
+        let before = map.len();
+        map.retain(|_, s| !s.is_expired());
+        Ok((before - map.len()) as u64)
+    }
+}
+
+/// Postgres-backed state store shared by all instances
+#[derive(Debug, Clone)]
+pub struct PgStateStore {
+    db: PgPool,
+    limits: StateLimits,
+}
+
+impl PgStateStore {
+    pub fn new(db: PgPool, limits: StateLimits) -> Self {
+        Self { db, limits }
+    }
+}
+
+#[async_trait]
+impl StateStore for PgStateStore {
+    async fn insert(&self, token: String, state: OAuthState) -> Result<(), AppError> {
+        // Both limits are soft: concurrent inserts may briefly overshoot them
+        trim_oauth_states_for_user(
+            &self.db,
+            &state.slack_workspace_id,
+            &state.slack_user_id,
+            self.limits.max_per_user as i64 - 1,
+        )
+        .await?;
+
+        let pending =
+            count_oauth_states_since(&self.db, Utc::now() - Duration::minutes(STATE_TTL_MINUTES))
+                .await?;
+        if pending >= self.limits.max_pending as i64 {
+            return Err(too_many_pending());
+        }
+
+        insert_oauth_state(
+            &self.db,
+            &OAuthStateRecord {
+                token,
+                slack_workspace_id: state.slack_workspace_id,
+                slack_user_id: state.slack_user_id,
+                created_at: state.created_at,
+                pkce_verifier: state.pkce_verifier,
+                slack_team_id: state.slack_team_id,
+                slack_enterprise_id: state.slack_enterprise_id,
+                link_signed: state.link_signed,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn consume(&self, token: &str) -> Result<Option<OAuthState>, AppError> {
+        let record = consume_oauth_state(&self.db, token).await?;
+        Ok(record.map(|r| OAuthState {
+            slack_workspace_id: r.slack_workspace_id,
+            slack_user_id: r.slack_user_id,
+            created_at: r.created_at,
+            pkce_verifier: r.pkce_verifier,
+            slack_team_id: r.slack_team_id,
+            slack_enterprise_id: r.slack_enterprise_id,
+            link_signed: r.link_signed,
+        }))
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let cutoff = Utc::now() - Duration::minutes(STATE_TTL_MINUTES);
+        Ok(delete_oauth_states_before(&self.db, cutoff).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: StateLimits = StateLimits {
+        max_pending: 3,
+        max_per_user: 2,
+    };
+
+    fn state(user_id: &str, age_minutes: i64) -> OAuthState {
+        OAuthState {
+            slack_workspace_id: "T123".to_string(),
+            slack_user_id: user_id.to_string(),
+            created_at: Utc::now() - Duration::minutes(age_minutes),
+            pkce_verifier: format!("verifier_{}", user_id),
+            slack_team_id: Some("T_SENT".to_string()),
+            slack_enterprise_id: None,
+            link_signed: false,
+        }
+    }
+
+    async fn check_store(store: &dyn StateStore) {
+        // Per-user cap drops the user's oldest state
+        store.insert("a1".into(), state("UA", 3)).await.unwrap();
+        store.insert("a2".into(), state("UA", 2)).await.unwrap();
+        store.insert("a3".into(), state("UA", 1)).await.unwrap();
+        assert!(store.consume("a1").await.unwrap().is_none());
+
+        // Global cap refuses new states
+        store.insert("b1".into(), state("UB", 0)).await.unwrap();
+        let result = store.insert("c1".into(), state("UC", 0)).await;
+        assert!(matches!(result, Err(AppError::TooManyRequests(_))));
+
+        // Consuming is one-time
+        let consumed = store.consume("a3").await.unwrap().unwrap();
+        assert_eq!(consumed.slack_user_id, "UA");
+        assert_eq!(consumed.pkce_verifier, "verifier_UA");
+        assert_eq!(consumed.slack_team_id.as_deref(), Some("T_SENT"));
+        assert!(store.consume("a3").await.unwrap().is_none());
+
+        // Expired states are purged
+        store.insert("old".into(), state("UC", 11)).await.unwrap();
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        assert!(store.consume("old").await.unwrap().is_none());
+        assert!(store.consume("b1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_memory_state_store() {
+        let store = MemoryStateStore::new(LIMITS);
+        check_store(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_full_of_expired_states_accepts_new() {
+        let store = MemoryStateStore::new(LIMITS);
+        for (i, user) in ["U1", "U2", "U3"].iter().enumerate() {
+            store
+                .insert(format!("old{}", i), state(user, 11))
+                .await
+                .unwrap();
+        }
+
+        store.insert("new".into(), state("U4", 0)).await.unwrap();
+        assert_eq!(store.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn test_pg_state_store(pool: PgPool) -> sqlx::Result<()> {
+        let store = PgStateStore::new(pool, LIMITS);
+        check_store(&store).await;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_pg_state_store_shared_between_instances(pool: PgPool) -> sqlx::Result<()> {
+        let first = PgStateStore::new(pool.clone(), LIMITS);
+        let second = PgStateStore::new(pool, LIMITS);
+
+        first.insert("token".into(), state("U1", 0)).await.unwrap();
+        let consumed = second.consume("token").await.unwrap().unwrap();
+        assert_eq!(consumed.slack_user_id, "U1");
+        assert!(first.consume("token").await.unwrap().is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+// In-process stand-in for the Slack Web API
+//
+// Serves `POST|GET /api/{method}` and records every call so tests can assert on
+// what the app sent. Responses for known methods are canned; any method's
+// response can be overridden with `set_response`.
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Path, Query, State},
+    routing::any,
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A Web API call received by the fake server
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    pub method: String,
+    pub token: Option<String>,
+    pub params: Value,
+}
+
+#[derive(Default)]
+struct Inner {
+    threads: HashMap<(String, String), Vec<Value>>,
+    /// Top-level messages per channel, newest first
+    histories: HashMap<String, Vec<Value>>,
+    /// Channels the bot isn't in, and whether each is private
+    unjoined_channels: HashMap<String, bool>,
+    overrides: HashMap<String, Value>,
+    calls: Vec<RecordedCall>,
+}
+
+#[derive(Clone)]
+pub struct FakeSlack {
+    pub base_url: String,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl FakeSlack {
+    /// Start the fake server on a random local port
+    pub async fn start() -> Self {
+        let inner = Arc::new(Mutex::new(Inner::default()));
+
+        let app = Router::new()
+            .route("/api/{method}", any(handle_method))
+            .with_state(inner.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/api", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self { base_url, inner }
+    }
+
+    /// Messages returned by `conversations.replies` for a thread
+    pub fn set_thread(&self, channel_id: &str, thread_ts: &str, messages: Vec<Value>) {
+        self.inner
+            .lock()
+            .unwrap()
+            .threads
+            .insert((channel_id.to_string(), thread_ts.to_string()), messages);
+    }
+
+    /// Messages returned by `conversations.history` for a channel, newest first
+    pub fn set_channel_history(&self, channel_id: &str, messages: Vec<Value>) {
+        self.inner
+            .lock()
+            .unwrap()
+            .histories
+            .insert(channel_id.to_string(), messages);
+    }
+
+    /// Make a channel unreadable until the bot joins it
+    ///
+    /// Public channels can be joined with `conversations.join`; private ones
+    /// can't.
+    pub fn set_channel_not_joined(&self, channel_id: &str, private: bool) {
+        self.inner
+            .lock()
+            .unwrap()
+            .unjoined_channels
+            .insert(channel_id.to_string(), private);
+    }
+
+    /// Replace the response for a Web API method
+    pub fn set_response(&self, method: &str, response: Value) {
+        self.inner
+            .lock()
+            .unwrap()
+            .overrides
+            .insert(method.to_string(), response);
+    }
+
+    /// All recorded calls to a Web API method, oldest first
+    pub fn calls(&self, method: &str) -> Vec<RecordedCall> {
+        self.inner
+            .lock()
+            .unwrap()
+            .calls
+            .iter()
+            .filter(|call| call.method == method)
+            .cloned()
+            .collect()
+    }
+
+    /// Emoji names added through `reactions.add`, oldest first
+    pub fn reactions(&self) -> Vec<String> {
+        self.calls("reactions.add")
+            .iter()
+            .filter_map(|call| call.params["name"].as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+async fn handle_method(
+    State(inner): State<Arc<Mutex<Inner>>>,
+    Path(method): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
+    body: Bytes,
+) -> Json<Value> {
+    // Merge query string, JSON body and form body into one params object
+    let mut params = json!(query);
+    if let Ok(Value::Object(fields)) = serde_json::from_slice::<Value>(&body) {
+        for (key, value) in fields {
+            params[key] = value;
+        }
+    } else {
+        for (key, value) in parse_form(&body) {
+            params[key] = json!(value);
+        }
+    }
+
+    let token = headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    let mut inner = inner.lock().unwrap();
+    inner.calls.push(RecordedCall {
+        method: method.clone(),
+        token,
+        params: params.clone(),
+    });
+
+    if let Some(response) = inner.overrides.get(&method) {
+        return Json(response.clone());
+    }
+
+    let channel = params["channel"].as_str().unwrap_or_default().to_string();
+    let response = match method.as_str() {
+        "conversations.replies" if inner.unjoined_channels.contains_key(&channel) => {
+            let error = if inner.unjoined_channels[&channel] {
+                "channel_not_found"
+            } else {
+                "not_in_channel"
+            };
+            json!({ "ok": false, "error": error })
+        }
+        "conversations.join" => match inner.unjoined_channels.get(&channel) {
+            Some(true) => json!({ "ok": false, "error": "method_not_supported_for_channel_type" }),
+            _ => {
+                inner.unjoined_channels.remove(&channel);
+                json!({ "ok": true, "channel": { "id": channel } })
+            }
+        },
+        "conversations.replies" => {
+            let key = (
+                params["channel"].as_str().unwrap_or_default().to_string(),
+                params["ts"].as_str().unwrap_or_default().to_string(),
+            );
+            match inner.threads.get(&key) {
+                Some(messages) => messages_page(messages, &params, true),
+                None => json!({ "ok": false, "error": "thread_not_found" }),
+            }
+        }
+        "conversations.history" => {
+            let messages = inner.histories.get(&channel).cloned().unwrap_or_default();
+            messages_page(&messages, &params, false)
+        }
+        "reactions.add"
+        | "chat.unfurl"
+        | "functions.completeSuccess"
+        | "functions.completeError" => json!({ "ok": true }),
+        "chat.postEphemeral" => json!({ "ok": true, "message_ts": "1700000000.000200" }),
+        "views.publish" => json!({ "ok": true, "view": params["view"] }),
+        "chat.postMessage" => json!({
+            "ok": true,
+            "channel": params["channel"],
+            "ts": "1700000000.000100",
+        }),
+        _ => json!({ "ok": false, "error": "unknown_method" }),
+    };
+
+    Json(response)
+}
+
+/// One page of `conversations.replies` / `conversations.history`
+///
+/// Honors `latest` (inclusive for replies, exclusive for history), `oldest`,
+/// `limit` and `cursor`. Cursors are message offsets.
+fn messages_page(messages: &[Value], params: &Value, latest_inclusive: bool) -> Value {
+    let number = |value: &Value| value.as_str().and_then(|v| v.parse::<f64>().ok());
+    let latest = number(&params["latest"]);
+    let oldest = number(&params["oldest"]);
+    let messages: Vec<&Value> = messages
+        .iter()
+        .filter(|message| {
+            let ts = number(&message["ts"]).unwrap_or_default();
+            let before_latest =
+                latest.is_none_or(|latest| ts < latest || (latest_inclusive && ts == latest));
+            before_latest && oldest.is_none_or(|oldest| ts >= oldest)
+        })
+        .collect();
+
+    let offset = number(&params["cursor"]).map_or(0, |cursor| cursor as usize);
+    let limit = number(&params["limit"]).map_or(messages.len(), |limit| limit as usize);
+    let end = (offset + limit).min(messages.len());
+    let has_more = end < messages.len();
+
+    json!({
+        "ok": true,
+        "messages": messages[offset.min(end)..end],
+        "has_more": has_more,
+        "response_metadata": { "next_cursor": if has_more { end.to_string() } else { String::new() } },
+    })
+}
+
+/// Parse an `application/x-www-form-urlencoded` body
+pub fn parse_form(body: &[u8]) -> Vec<(String, String)> {
+    oauth2::url::form_urlencoded::parse(body)
+        .into_owned()
+        .collect()
+}
@@ -0,0 +1,96 @@
+// In-process stand-in for Slack's Socket Mode WebSocket endpoint
+//
+// Greets every connection with `hello`, lets tests push envelopes (or a
+// `disconnect`) to a connection and records the acknowledgements sent back.
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Default)]
+struct Inner {
+    /// Outgoing message channel per accepted connection, oldest first
+    connections: Vec<mpsc::UnboundedSender<Message>>,
+    acks: Vec<Value>,
+}
+
+#[derive(Clone)]
+pub struct FakeSocketServer {
+    /// WebSocket URL to return from `apps.connections.open`
+    pub url: String,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl FakeSocketServer {
+    /// Start the fake server on a random local port
+    pub async fn start() -> Self {
+        let inner = Arc::new(Mutex::new(Inner::default()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/link", listener.local_addr().unwrap());
+
+        let accept_inner = inner.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(socket) = tokio_tungstenite::accept_async(stream).await else {
+                    continue;
+                };
+                let (mut sink, mut stream) = socket.split();
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                tx.send(Message::Text(
+                    json!({ "type": "hello", "num_connections": 1 })
+                        .to_string()
+                        .into(),
+                ))
+                .unwrap();
+                accept_inner.lock().unwrap().connections.push(tx);
+
+                tokio::spawn(async move {
+                    while let Some(message) = rx.recv().await {
+                        if sink.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+
+                let inner = accept_inner.clone();
+                tokio::spawn(async move {
+                    while let Some(Ok(message)) = stream.next().await {
+                        if let Message::Text(text) = message
+                            && let Ok(ack) = serde_json::from_str::<Value>(text.as_str())
+                        {
+                            inner.lock().unwrap().acks.push(ack);
+                        }
+                    }
+                });
+            }
+        });
+
+        Self { url, inner }
+    }
+
+    /// Number of connections accepted so far
+    pub fn connection_count(&self) -> usize {
+        self.inner.lock().unwrap().connections.len()
+    }
+
+    /// Send a message to the `index`th accepted connection
+    pub fn send(&self, index: usize, message: Value) {
+        let inner = self.inner.lock().unwrap();
+        inner.connections[index]
+            .send(Message::Text(message.to_string().into()))
+            .expect("Connection is closed");
+    }
+
+    /// Acknowledgement for an envelope, if the app sent one
+    pub fn ack(&self, envelope_id: &str) -> Option<Value> {
+        self.inner
+            .lock()
+            .unwrap()
+            .acks
+            .iter()
+            .find(|ack| ack["envelope_id"] == envelope_id)
+            .cloned()
+    }
+}
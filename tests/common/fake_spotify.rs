@@ -0,0 +1,240 @@
+// In-process stand-in for the Spotify Web API and accounts service
+//
+// One server hosts both: Web API routes live under `/v1` and the token endpoint
+// at `/api/token`, mirroring api.spotify.com and accounts.spotify.com.
+
+use super::fake_slack::parse_form;
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post, put},
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct Inner {
+    profile: Value,
+    profile_status: StatusCode,
+    tracks: HashMap<String, Value>,
+    saved_tracks: Vec<(String, String)>,
+    save_status: StatusCode,
+    token_requests: Vec<HashMap<String, String>>,
+    token_response: Option<(StatusCode, Value)>,
+    token_delay: Duration,
+    issued_tokens: usize,
+}
+
+#[derive(Clone)]
+pub struct FakeSpotify {
+    pub api_base_url: String,
+    pub accounts_base_url: String,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl FakeSpotify {
+    /// Start the fake server on a random local port
+    pub async fn start() -> Self {
+        let inner = Arc::new(Mutex::new(Inner {
+            profile: json!({ "id": "spotify_e2e_user", "display_name": "E2E User" }),
+            profile_status: StatusCode::OK,
+            tracks: HashMap::new(),
+            saved_tracks: Vec::new(),
+            save_status: StatusCode::OK,
+            token_requests: Vec::new(),
+            token_response: None,
+            token_delay: Duration::ZERO,
+            issued_tokens: 0,
+        }));
+
+        let app = Router::new()
+            .route("/v1/me", get(me))
+            .route("/v1/me/tracks", put(save_tracks))
+            .route("/v1/tracks/{id}", get(track))
+            .route("/api/token", post(token))
+            .with_state(inner.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self {
+            api_base_url: format!("{}/v1", address),
+            accounts_base_url: address,
+            inner,
+        }
+    }
+
+    /// Profile returned by `GET /v1/me`
+    pub fn set_profile(&self, profile: Value) {
+        self.inner.lock().unwrap().profile = profile;
+    }
+
+    /// Status returned by `GET /v1/me`; errors come without a profile
+    pub fn set_profile_status(&self, status: StatusCode) {
+        self.inner.lock().unwrap().profile_status = status;
+    }
+
+    /// Track returned by `GET /v1/tracks/{id}`; other IDs are not found
+    pub fn set_track(&self, track: Value) {
+        let id = track["id"].as_str().unwrap().to_string();
+        self.inner.lock().unwrap().tracks.insert(id, track);
+    }
+
+    /// Status returned by `PUT /v1/me/tracks`
+    pub fn set_save_status(&self, status: StatusCode) {
+        self.inner.lock().unwrap().save_status = status;
+    }
+
+    /// Fixed response for `POST /api/token` instead of freshly issued tokens
+    pub fn set_token_response(&self, status: StatusCode, body: Value) {
+        self.inner.lock().unwrap().token_response = Some((status, body));
+    }
+
+    /// Delay before `POST /api/token` responds, to widen race windows
+    pub fn set_token_delay(&self, delay: Duration) {
+        self.inner.lock().unwrap().token_delay = delay;
+    }
+
+    /// Go back to issuing fresh tokens from `POST /api/token`
+    pub fn clear_token_response(&self) {
+        self.inner.lock().unwrap().token_response = None;
+    }
+
+    /// `(access_token, track_id)` pairs saved through `PUT /v1/me/tracks`
+    pub fn saved_tracks(&self) -> Vec<(String, String)> {
+        self.inner.lock().unwrap().saved_tracks.clone()
+    }
+
+    /// Form bodies posted to the token endpoint, oldest first
+    pub fn token_requests(&self) -> Vec<HashMap<String, String>> {
+        self.inner.lock().unwrap().token_requests.clone()
+    }
+}
+
+fn bearer(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+async fn me(
+    State(inner): State<Arc<Mutex<Inner>>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if bearer(&headers).is_none() {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": { "status": 401, "message": "No token provided" } })),
+        );
+    }
+    let inner = inner.lock().unwrap();
+    if inner.profile_status != StatusCode::OK {
+        return (
+            inner.profile_status,
+            Json(
+                json!({ "error": { "status": inner.profile_status.as_u16(), "message": "Profile unavailable" } }),
+            ),
+        );
+    }
+    (StatusCode::OK, Json(inner.profile.clone()))
+}
+
+async fn track(
+    State(inner): State<Arc<Mutex<Inner>>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if bearer(&headers).is_none() {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": { "status": 401, "message": "No token provided" } })),
+        );
+    }
+    match inner.lock().unwrap().tracks.get(&id) {
+        Some(track) => (StatusCode::OK, Json(track.clone())),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": { "status": 404, "message": "Non existing id" } })),
+        ),
+    }
+}
+
+async fn save_tracks(
+    State(inner): State<Arc<Mutex<Inner>>>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+) -> StatusCode {
+    let Some(token) = bearer(&headers) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+
+    let mut inner = inner.lock().unwrap();
+    if inner.save_status.is_success() {
+        for id in query
+            .get("ids")
+            .map(String::as_str)
+            .unwrap_or_default()
+            .split(',')
+        {
+            inner.saved_tracks.push((token.clone(), id.to_string()));
+        }
+    }
+    inner.save_status
+}
+
+async fn token(State(inner): State<Arc<Mutex<Inner>>>, body: Bytes) -> (StatusCode, Json<Value>) {
+    let form: HashMap<String, String> = parse_form(&body).into_iter().collect();
+
+    let delay = {
+        let mut inner = inner.lock().unwrap();
+        inner.token_requests.push(form.clone());
+        inner.token_delay
+    };
+    tokio::time::sleep(delay).await;
+
+    let mut inner = inner.lock().unwrap();
+
+    if let Some((status, body)) = &inner.token_response {
+        return (*status, Json(body.clone()));
+    }
+
+    inner.issued_tokens += 1;
+    let n = inner.issued_tokens;
+
+    let response = match form.get("grant_type").map(String::as_str) {
+        Some("authorization_code") => json!({
+            "access_token": format!("issued_access_token_{}", n),
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "refresh_token": format!("issued_refresh_token_{}", n),
+            "scope": "user-library-modify",
+        }),
+        Some("refresh_token") => json!({
+            "access_token": format!("refreshed_access_token_{}", n),
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "refresh_token": format!("rotated_refresh_token_{}", n),
+        }),
+        Some("client_credentials") => json!({
+            "access_token": format!("app_access_token_{}", n),
+            "token_type": "Bearer",
+            "expires_in": 3600,
+        }),
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "unsupported_grant_type" })),
+            );
+        }
+    };
+
+    (StatusCode::OK, Json(response))
+}
@@ -60,6 +60,7 @@ async fn test_user_auth_crud_operations() {
     // Test data
     let workspace_id = "T_INTEGRATION_TEST";
     let user_id = "U_INTEGRATION_TEST";
+    let cipher = savethebeat::crypto::TokenCipher::default();
 
     // Cleanup any existing test data
     cleanup_test_data(&pool, workspace_id, user_id).await;
@@ -67,12 +68,18 @@ async fn test_user_auth_crud_operations() {
     // Test: Create user auth
     let user_auth = savethebeat::db::repository::upsert_user_auth(
         &pool,
-        workspace_id,
-        user_id,
-        Some("spotify_test_user".to_string()),
-        "test_access_token",
-        "test_refresh_token",
-        chrono::Utc::now() + chrono::Duration::hours(1),
+        &cipher,
+        savethebeat::db::repository::UserAuthParams {
+            workspace_id,
+            user_id,
+            profile: Some(savethebeat::db::repository::SpotifyProfileParams {
+                spotify_user_id: "spotify_test_user",
+                ..Default::default()
+            }),
+            access_token: "test_access_token",
+            refresh_token: "test_refresh_token",
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        },
     )
     .await
     .expect("Failed to create user_auth");
@@ -82,10 +89,11 @@ async fn test_user_auth_crud_operations() {
     assert_eq!(user_auth.access_token, "test_access_token");
 
     // Test: Retrieve user auth
-    let retrieved = savethebeat::db::repository::get_user_auth(&pool, workspace_id, user_id)
-        .await
-        .expect("Failed to get user_auth")
-        .expect("User auth not found");
+    let retrieved =
+        savethebeat::db::repository::get_user_auth(&pool, &cipher, workspace_id, user_id)
+            .await
+            .expect("Failed to get user_auth")
+            .expect("User auth not found");
 
     assert_eq!(retrieved.id, user_auth.id);
     assert_eq!(retrieved.access_token, "test_access_token");
@@ -94,6 +102,7 @@ async fn test_user_auth_crud_operations() {
     let new_expiry = chrono::Utc::now() + chrono::Duration::hours(2);
     savethebeat::db::repository::update_tokens(
         &pool,
+        &cipher,
         user_auth.id,
         "new_access_token",
         "new_refresh_token",
@@ -103,7 +112,7 @@ async fn test_user_auth_crud_operations() {
     .expect("Failed to update tokens");
 
     // Verify update
-    let updated = savethebeat::db::repository::get_user_auth(&pool, workspace_id, user_id)
+    let updated = savethebeat::db::repository::get_user_auth(&pool, &cipher, workspace_id, user_id)
         .await
         .expect("Failed to get updated user_auth")
         .expect("User auth not found after update");
@@ -131,6 +140,7 @@ async fn test_save_action_log_idempotency() {
 
     // Test: First save action
     let params1 = savethebeat::db::repository::SaveActionParams {
+        track_save_id: None,
         workspace_id,
         user_id,
         channel_id: "C_TEST",
@@ -148,33 +158,27 @@ async fn test_save_action_log_idempotency() {
 
     assert_eq!(action1.status, "saved");
 
-    // Test: Duplicate save attempt (should fail due to unique constraint)
+    // Test: Repeat attempt for the same track is appended to the history
     let params2 = savethebeat::db::repository::SaveActionParams {
+        track_save_id: None,
         workspace_id,
         user_id,
         channel_id: "C_TEST",
         thread_ts,                       // Same thread
         mention_ts: "1234567892.123456", // Different mention
-        track_id,                        // Same track - should violate unique constraint
-        status: "saved",
+        track_id,                        // Same track
+        status: "already_saved",
         error_code: None,
         error_message: None,
     };
 
-    let result = savethebeat::db::repository::create_save_action(&pool, params2).await;
+    let action2 = savethebeat::db::repository::create_save_action(&pool, params2)
+        .await
+        .expect("Failed to append repeat save action");
 
-    // Should fail with unique violation
-    assert!(result.is_err());
-    if let Err(e) = result {
-        let error_message = e.to_string();
-        assert!(
-            error_message.contains("unique") || error_message.contains("duplicate"),
-            "Expected unique constraint error, got: {}",
-            error_message
-        );
-    }
+    assert_eq!(action2.status, "already_saved");
 
-    // Test: Check for existing save action
+    // Test: Check for the latest save action
     let existing = savethebeat::db::repository::get_save_action(
         &pool,
         workspace_id,
@@ -186,7 +190,7 @@ async fn test_save_action_log_idempotency() {
     .expect("Failed to check existing save action")
     .expect("Save action not found");
 
-    assert_eq!(existing.id, action1.id);
+    assert_eq!(existing.id, action2.id);
 
     // Cleanup
     cleanup_test_data(&pool, workspace_id, user_id).await;